@@ -0,0 +1,142 @@
+use std::hint::black_box;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy, utils::Duration};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use sond_bevy_particles::{
+	simulation_systems, spawn_particles,
+	update::{AngularVelocity, Linear, MulScale, TargetTransform, Velocity},
+	Deterministic, InitialGlobalTransform, InitialTransform, Lifetime, ParticleFactory, Spewer,
+	SpewerBundle, TimeCreated,
+};
+
+const DT: Duration = Duration::from_nanos(16_666_667);
+const COUNTS: [usize; 3] = [1_000, 10_000, 100_000];
+
+fn app() -> App {
+	let mut app = App::new();
+	app.add_plugins((MinimalPlugins, TransformPlugin, HierarchyPlugin))
+		// The systems `ParticlesPlugin` adds, without its rendering setup.
+		.add_systems(PreUpdate, spawn_particles::<Without<Deterministic>>)
+		.add_systems(Update, simulation_systems::<Without<Deterministic>>())
+		.insert_resource(TimeUpdateStrategy::ManualDuration(DT));
+	// The first update has no elapsed time yet.
+	app.update();
+	app
+}
+
+fn factory(lifetime: Duration) -> impl ParticleFactory {
+	move |cmds: &mut Commands, xform: &GlobalTransform, t| {
+		let local = xform.compute_transform();
+		cmds.spawn((
+			TransformBundle::from_transform(local),
+			InitialTransform(local),
+			InitialGlobalTransform(*xform),
+			t,
+			Lifetime(lifetime),
+		))
+	}
+}
+
+/// 100 spewers, each emitting `count / 100` particles per frame.
+fn spewers(app: &mut App, count: usize, lifetime: Duration) {
+	let interval = DT / (count / 100) as u32;
+	for i in 0..100 {
+		app.world_mut().spawn(SpewerBundle {
+			spewer: Spewer {
+				interval,
+				use_global_coords: true,
+				..Spewer::new(factory(lifetime))
+			},
+			transform: TransformBundle::from_transform(Transform::from_xyz(i as f32, 0.0, 0.0)),
+			..default()
+		});
+	}
+	app.update();
+}
+
+fn spawn(c: &mut Criterion) {
+	let mut group = c.benchmark_group("spawn");
+	group.sample_size(20);
+	for count in COUNTS {
+		group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+			b.iter_batched_ref(
+				|| {
+					let mut app = app();
+					spewers(&mut app, count, Duration::from_secs(60));
+					app
+				},
+				|app| app.update(),
+				BatchSize::PerIteration,
+			)
+		});
+	}
+	group.finish();
+}
+
+fn tick_behavior(c: &mut Criterion, name: &str, behavior: impl Bundle + Clone) {
+	let mut group = c.benchmark_group(format!("tick/{name}"));
+	for count in COUNTS {
+		let mut app = app();
+		let particles = (0..count).map(|i| {
+			let xform = Transform::from_xyz(i as f32, 0.0, 0.0);
+			(
+				TransformBundle::from_transform(xform),
+				InitialTransform(xform),
+				TimeCreated(Duration::ZERO),
+				Lifetime(Duration::MAX),
+				behavior.clone(),
+			)
+		});
+		app.world_mut().spawn_batch(particles.collect::<Vec<_>>());
+		app.update();
+		group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+			b.iter(|| black_box(&mut app).update())
+		});
+	}
+	group.finish();
+}
+
+fn tick(c: &mut Criterion) {
+	tick_behavior(c, "none", ());
+	tick_behavior(
+		c,
+		"linear",
+		Linear {
+			velocity: Vec3::ONE,
+		},
+	);
+	tick_behavior(c, "angular", AngularVelocity(Vec3::Y));
+	tick_behavior(
+		c,
+		"mul_scale",
+		MulScale {
+			scale: Vec3::splat(0.5),
+		},
+	);
+	tick_behavior(
+		c,
+		"target_transform",
+		TargetTransform::new(Transform::from_xyz(0.0, 10.0, 0.0)),
+	);
+	tick_behavior(c, "velocity", Velocity(Vec3::ONE));
+}
+
+/// Steady state of spawning and despawning `count` particles per frame.
+fn churn(c: &mut Criterion) {
+	let mut group = c.benchmark_group("churn");
+	group.sample_size(20);
+	for count in COUNTS {
+		let mut app = app();
+		spewers(&mut app, count, DT * 10);
+		for _ in 0..20 {
+			app.update();
+		}
+		group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+			b.iter(|| black_box(&mut app).update())
+		});
+	}
+	group.finish();
+}
+
+criterion_group!(benches, spawn, tick, churn);
+criterion_main!(benches);
@@ -0,0 +1,73 @@
+//! Renders a fountain of sparks to transparent PNG frames in `previews/`, without opening
+//! a window.
+//!
+//! `cargo run --example capture_preview --features capture`
+
+use bevy::{
+	app::ScheduleRunnerPlugin, prelude::*, render::mesh::SphereMeshBuilder, utils::Duration,
+	window::ExitCondition,
+};
+use nanorand::{Rng, WyRand};
+use sond_bevy_particles::{
+	capture::EffectCapturePlugin,
+	update::{GravityScale, Velocity},
+	InitialGlobalTransform, InitialTransform, Lifetime, ParticleBundle, ParticlesPlugin, Spewer,
+	SpewerBundle,
+};
+
+fn main() {
+	App::new()
+		.add_plugins((
+			DefaultPlugins.set(WindowPlugin {
+				primary_window: None,
+				exit_condition: ExitCondition::DontExit,
+				close_when_requested: false,
+			}),
+			ScheduleRunnerPlugin::run_loop(Duration::ZERO),
+			ParticlesPlugin,
+			EffectCapturePlugin::new("sparks", "previews").with_size(320, 320),
+		))
+		.add_systems(Startup, spawn_sparks)
+		.run();
+}
+
+fn spawn_sparks(
+	mut cmds: Commands,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+	let mesh = meshes.add(SphereMeshBuilder::new(0.04, default()).ico(1).unwrap());
+	let material = materials.add(StandardMaterial {
+		base_color: Color::srgb(1.0, 0.6, 0.2),
+		unlit: true,
+		..default()
+	});
+	let mut rng = WyRand::new_seed(1);
+	cmds.spawn(SpewerBundle {
+		spewer: Spewer {
+			interval: Duration::from_millis(10),
+			use_global_coords: true,
+			..Spewer::new(move |cmds: &mut Commands, xform: &GlobalTransform, t| {
+				let local = xform.compute_transform();
+				let (x, z) = (rng.generate::<f32>() - 0.5, rng.generate::<f32>() - 0.5);
+				cmds.spawn((
+					ParticleBundle {
+						mesh_bundle: MaterialMeshBundle {
+							mesh: mesh.clone(),
+							material: material.clone(),
+							transform: local,
+							..default()
+						},
+						lifetime: Lifetime(Duration::from_millis(1500)),
+						time_created: t,
+						initial_transform: InitialTransform(local),
+						initial_global_transform: InitialGlobalTransform(*xform),
+					},
+					Velocity(Vec3::new(x, 2.0, z) * 2.0),
+					GravityScale(1.0),
+				))
+			})
+		},
+		..default()
+	});
+}
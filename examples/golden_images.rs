@@ -0,0 +1,211 @@
+//! Renders a few canonical effects with fixed seeds, and compares their frames against the
+//! golden images in `examples/golden/`, to catch shader and pipeline changes that alter
+//! how effects look. Exits with an error if any frame differs.
+//!
+//! `cargo run --example golden_images --features capture`
+//!
+//! Captured frames, and images highlighting any differences, are saved to
+//! `target/golden_images/`. Pass `--update` to overwrite the golden images after an
+//! intended change, and effect names to only render those.
+
+use bevy::{
+	app::ScheduleRunnerPlugin, log::LogPlugin, pbr::ExtendedMaterial, prelude::*,
+	render::mesh::SphereMeshBuilder, utils::Duration, window::ExitCondition,
+};
+use nanorand::{Rng, WyRand};
+use sond_bevy_particles::{
+	buffer::{BufferParticle, ParticleBuffer, ParticleBufferBundle},
+	capture::{EffectCapturePlugin, GoldenImages},
+	color::{ColorOverLifetime, ParticleColor},
+	material::{Billboard, ParticleExtension, ParticleMaterial},
+	mesh::{ParticleMesh, ParticleMeshes},
+	update::{GravityScale, SizeOverLifetime, Velocity},
+	InitialGlobalTransform, InitialTransform, Lifetime, ParticleBundle, ParticlesPlugin, Spewer,
+	SpewerBundle,
+};
+
+type SpawnEffect = fn(&mut World);
+
+const EFFECTS: [(&str, SpawnEffect); 3] = [
+	("sparks", spawn_sparks),
+	("smoke", spawn_smoke),
+	("buffer", spawn_buffer),
+];
+
+fn main() -> AppExit {
+	let mut update = false;
+	let mut names = Vec::new();
+	for arg in std::env::args().skip(1) {
+		match &*arg {
+			"--update" => update = true,
+			_ if EFFECTS.iter().any(|(name, _)| *name == arg) => names.push(arg),
+			_ => panic!("unknown effect {arg}"),
+		}
+	}
+	let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+	let mut failed = Vec::new();
+	let mut logging = false;
+	for &(name, spawn) in &EFFECTS {
+		if !names.is_empty() && !names.iter().any(|n| n == name) {
+			continue;
+		}
+		let golden = GoldenImages::new(root.join("examples/golden")).updating(update);
+		let capture = EffectCapturePlugin::new(name, root.join("target/golden_images"))
+			.with_size(128, 128)
+			.with_frames(3, Duration::from_millis(250))
+			.with_golden(golden);
+		let mut plugins = DefaultPlugins.set(WindowPlugin {
+			primary_window: None,
+			exit_condition: ExitCondition::DontExit,
+			close_when_requested: false,
+		});
+		// The global logger can only be set by the first app.
+		if std::mem::replace(&mut logging, true) {
+			plugins = plugins.disable::<LogPlugin>();
+		}
+		let exit = App::new()
+			.add_plugins((
+				plugins,
+				ScheduleRunnerPlugin::run_loop(Duration::ZERO),
+				ParticlesPlugin,
+				capture,
+			))
+			.add_systems(Startup, spawn)
+			.run();
+		if exit.is_error() {
+			failed.push(name);
+		}
+	}
+	if failed.is_empty() {
+		AppExit::Success
+	} else {
+		eprintln!("differing effects: {}", failed.join(", "));
+		AppExit::error()
+	}
+}
+
+/// Lit spheres thrown up and falling under gravity.
+fn spawn_sparks(world: &mut World) {
+	let mesh = world
+		.resource_mut::<Assets<Mesh>>()
+		.add(SphereMeshBuilder::new(0.04, default()).ico(1).unwrap());
+	let material = world
+		.resource_mut::<Assets<StandardMaterial>>()
+		.add(StandardMaterial {
+			base_color: Color::srgb(1.0, 0.6, 0.2),
+			unlit: true,
+			..default()
+		});
+	let mut rng = WyRand::new_seed(1);
+	world.spawn(SpewerBundle {
+		spewer: Spewer {
+			interval: Duration::from_millis(10),
+			use_global_coords: true,
+			// Spawned while warming up, so the pipelines are ready by the first frame.
+			pending_burst: 1,
+			..Spewer::new(move |cmds: &mut Commands, xform: &GlobalTransform, t| {
+				let local = xform.compute_transform();
+				let (x, z) = (rng.generate::<f32>() - 0.5, rng.generate::<f32>() - 0.5);
+				cmds.spawn((
+					ParticleBundle {
+						mesh_bundle: MaterialMeshBundle {
+							mesh: mesh.clone(),
+							material: material.clone(),
+							transform: local,
+							..default()
+						},
+						lifetime: Lifetime(Duration::from_millis(1500)),
+						time_created: t,
+						initial_transform: InitialTransform(local),
+						initial_global_transform: InitialGlobalTransform(*xform),
+					},
+					Velocity(Vec3::new(x, 2.0, z) * 2.0),
+					GravityScale(1.0),
+				))
+			})
+		},
+		..default()
+	});
+}
+
+/// Alpha-blended billboards rising, growing, and fading out.
+fn spawn_smoke(world: &mut World) {
+	let mesh = world.resource_scope(|world, mut cache: Mut<ParticleMeshes>| {
+		ParticleMesh::Quad.get_or_add(&mut cache, &mut world.resource_mut::<Assets<Mesh>>())
+	});
+	let material = world
+		.resource_mut::<Assets<ParticleMaterial>>()
+		.add(ExtendedMaterial {
+			base: StandardMaterial {
+				base_color: Color::srgb(0.6, 0.6, 0.65),
+				alpha_mode: AlphaMode::Blend,
+				unlit: true,
+				..default()
+			},
+			extension: ParticleExtension {
+				billboard: Billboard::FaceCamera,
+				..default()
+			},
+		});
+	let mut rng = WyRand::new_seed(2);
+	world.spawn(SpewerBundle {
+		spewer: Spewer {
+			interval: Duration::from_millis(50),
+			use_global_coords: true,
+			// Spawned while warming up, so the pipelines are ready by the first frame.
+			pending_burst: 1,
+			..Spewer::new(move |cmds: &mut Commands, xform: &GlobalTransform, t| {
+				let local = xform.compute_transform().with_scale(Vec3::splat(0.4));
+				let (x, z) = (rng.generate::<f32>() - 0.5, rng.generate::<f32>() - 0.5);
+				cmds.spawn((
+					ParticleBundle {
+						mesh_bundle: MaterialMeshBundle {
+							mesh: mesh.clone(),
+							material: material.clone(),
+							transform: local,
+							..default()
+						},
+						lifetime: Lifetime(Duration::from_secs(2)),
+						time_created: t,
+						initial_transform: InitialTransform(local),
+						initial_global_transform: InitialGlobalTransform(*xform),
+					},
+					Velocity(Vec3::new(x * 0.3, 1.0, z * 0.3)),
+					SizeOverLifetime::uniform(|s| 1.0 + 2.0 * s),
+					ParticleColor::default(),
+					ColorOverLifetime(|s| LinearRgba::WHITE.with_alpha(0.6 * (1.0 - s))),
+				))
+			})
+		},
+		..default()
+	});
+}
+
+/// A depth-sorted [ParticleBuffer] fountain.
+fn spawn_buffer(world: &mut World) {
+	let mesh = world.resource_scope(|world, mut cache: Mut<ParticleMeshes>| {
+		ParticleMesh::Quad.get_or_add(&mut cache, &mut world.resource_mut::<Assets<Mesh>>())
+	});
+	let mut buffer = ParticleBuffer::new(|rng: &mut WyRand| {
+		let angle = rng.generate::<f32>() * std::f32::consts::TAU;
+		BufferParticle {
+			velocity: Vec3::new(angle.cos(), 4.0, angle.sin()),
+			color: LinearRgba::new(0.2, 0.6, 1.0, 0.5),
+			..default()
+		}
+	});
+	buffer.rng = WyRand::new_seed(3);
+	buffer.interval = Duration::from_millis(5);
+	buffer.lifetime = Duration::from_millis(1200);
+	buffer.acceleration = Vec3::NEG_Y * 6.0;
+	buffer.size = 0.08;
+	buffer.depth_sort = true;
+	// Drawn while warming up, so the pipeline is ready by the first frame.
+	buffer.push(
+		Vec3::ZERO,
+		Vec3::Y * 4.0,
+		LinearRgba::new(0.2, 0.6, 1.0, 0.5),
+		0,
+	);
+	world.spawn(ParticleBufferBundle::new(buffer, mesh));
+}
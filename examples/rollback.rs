@@ -0,0 +1,120 @@
+//! Deterministic particles in a `bevy_ggrs` sync-test session, which rolls back and
+//! resimulates every frame, logging a warning if the checksums don't match.
+//!
+//! `cargo run --example rollback --features ggrs`, or with `--no-default-features` to
+//! simulate without any rendering, as on a dedicated server.
+
+use bevy::{
+	app::ScheduleRunnerPlugin,
+	prelude::*,
+	transform::systems::{propagate_transforms, sync_simple_transforms},
+	utils::Duration,
+};
+use bevy_ggrs::{prelude::*, AddRollbackCommandExtension, LocalInputs, LocalPlayers};
+use sond_bevy_particles::{
+	ggrs::RollbackParticlesPlugin, update::Linear, Deterministic, DeterministicParticleSystems,
+	DeterministicParticlesPlugin, InitialTransform, Lifetime, Spewer, SpewerBundle, TimeCreated,
+};
+use std::hash::{Hash, Hasher};
+
+type Config = GgrsConfig<u8>;
+
+const FPS: usize = 60;
+const FRAMES: u32 = 300;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+	let session = SessionBuilder::<Config>::new()
+		.with_num_players(1)
+		.with_check_distance(4)
+		.add_player(PlayerType::Local, 0)?
+		.start_synctest_session()?;
+
+	App::new()
+		.add_plugins((
+			MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+				1.0 / FPS as f64,
+			))),
+			bevy::log::LogPlugin::default(),
+			TransformPlugin,
+			HierarchyPlugin,
+			GgrsPlugin::<Config>::default(),
+			DeterministicParticlesPlugin::new(GgrsSchedule),
+			RollbackParticlesPlugin,
+		))
+		.set_rollback_schedule_fps(FPS)
+		.rollback_component_with_clone::<Transform>()
+		.checksum_component::<Transform>(checksum_transform)
+		.add_systems(ReadInputs, read_inputs)
+		.add_systems(Startup, setup)
+		.rollback_component_with_copy::<GlobalTransform>()
+		.add_systems(
+			GgrsSchedule,
+			// Spewers emit from their `GlobalTransform`, so it must be up to date in every resimulated frame.
+			(move_spewer, sync_simple_transforms, propagate_transforms)
+				.chain()
+				.before(DeterministicParticleSystems),
+		)
+		.add_systems(Update, report)
+		.insert_resource(Session::SyncTest(session))
+		.run();
+
+	Ok(())
+}
+
+fn read_inputs(mut cmds: Commands, local_players: Res<LocalPlayers>) {
+	let inputs = local_players.0.iter().map(|&handle| (handle, 0)).collect();
+	cmds.insert_resource(LocalInputs::<Config>(inputs));
+}
+
+fn setup(mut cmds: Commands) {
+	let spewer = Spewer {
+		interval: Duration::from_secs_f32(1.0 / 30.0),
+		use_global_coords: true,
+		..Spewer::seeded(42).instance(|cmds: &mut Commands, xform: &GlobalTransform, t| {
+			let xform = xform.compute_transform();
+			let mut particle = cmds.spawn((
+				TransformBundle::from_transform(xform),
+				InitialTransform(xform),
+				t,
+				Lifetime(Duration::from_secs(2)),
+				Linear { velocity: Vec3::Y },
+			));
+			particle.add_rollback();
+			particle
+		})
+	};
+	cmds.spawn((
+		SpewerBundle {
+			spewer,
+			..default()
+		},
+		Deterministic,
+	))
+	.add_rollback();
+}
+
+fn move_spewer(mut q: Query<&mut Transform, With<Spewer>>, t: Res<Time>) {
+	for mut xform in &mut q {
+		xform.translation.x = t.elapsed_seconds().sin();
+	}
+}
+
+fn checksum_transform(xform: &Transform) -> u64 {
+	let mut hasher = bevy_ggrs::checksum_hasher();
+	xform
+		.translation
+		.to_array()
+		.map(f32::to_bits)
+		.hash(&mut hasher);
+	hasher.finish()
+}
+
+fn report(particles: Query<&TimeCreated>, mut frames: Local<u32>, mut exit: EventWriter<AppExit>) {
+	*frames += 1;
+	if (*frames).is_multiple_of(60) {
+		info!("{} live particles", particles.iter().count());
+	}
+	if *frames >= FRAMES {
+		exit.send(AppExit::Success);
+	}
+}
@@ -0,0 +1,120 @@
+//! Headless stress test of the particle update systems, reporting frame times.
+//!
+//! `cargo run --release --example stress_test -- --spewers 100 --rate 600 --lifetime 2 --frames 600`
+//!
+//! `--rate` is particles per second per spewer. Simulated time advances by a fixed 1/60s
+//! per frame regardless of how long the frame took, so results are comparable between runs.
+
+use bevy::{prelude::*, time::TimeUpdateStrategy, utils::Duration};
+use sond_bevy_particles::{
+	simulation_systems, spawn_particles,
+	update::{AngularVelocity, Linear},
+	Deterministic, InitialGlobalTransform, InitialTransform, Lifetime, Spewer, SpewerBundle,
+	TimeCreated,
+};
+use std::time::Instant;
+
+struct Args {
+	spewers: u32,
+	rate: f32,
+	lifetime: f32,
+	frames: u32,
+}
+
+impl Args {
+	fn parse() -> Self {
+		let mut args = Self {
+			spewers: 100,
+			rate: 600.0,
+			lifetime: 2.0,
+			frames: 600,
+		};
+		let mut iter = std::env::args().skip(1);
+		while let Some(flag) = iter.next() {
+			let value = iter
+				.next()
+				.unwrap_or_else(|| panic!("missing value for {flag}"));
+			match &*flag {
+				"--spewers" => args.spewers = parse(&flag, &value),
+				"--rate" => args.rate = parse(&flag, &value),
+				"--lifetime" => args.lifetime = parse(&flag, &value),
+				"--frames" => args.frames = parse(&flag, &value),
+				_ => panic!("unknown flag {flag}"),
+			}
+		}
+		args
+	}
+}
+
+fn parse<T: std::str::FromStr>(flag: &str, value: &str) -> T {
+	value
+		.parse()
+		.unwrap_or_else(|_| panic!("invalid value for {flag}: {value}"))
+}
+
+fn main() {
+	let args = Args::parse();
+
+	let mut app = App::new();
+	app.add_plugins((MinimalPlugins, TransformPlugin, HierarchyPlugin))
+		// The systems `ParticlesPlugin` adds, without its rendering setup.
+		.add_systems(PreUpdate, spawn_particles::<Without<Deterministic>>)
+		.add_systems(Update, simulation_systems::<Without<Deterministic>>())
+		.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+			1.0 / 60.0,
+		)));
+
+	let lifetime = Duration::from_secs_f32(args.lifetime);
+	for i in 0..args.spewers {
+		app.world_mut().spawn(SpewerBundle {
+			spewer: Spewer {
+				interval: Duration::from_secs_f32(1.0 / args.rate),
+				use_global_coords: true,
+				..Spewer::new(move |cmds: &mut Commands, xform: &GlobalTransform, t| {
+					let local = xform.compute_transform();
+					cmds.spawn((
+						TransformBundle::from_transform(local),
+						InitialTransform(local),
+						InitialGlobalTransform(*xform),
+						t,
+						Lifetime(lifetime),
+						Linear { velocity: Vec3::Y },
+						AngularVelocity(Vec3::Z),
+					))
+				})
+			},
+			transform: TransformBundle::from_transform(Transform::from_xyz(i as f32, 0.0, 0.0)),
+			..default()
+		});
+	}
+
+	let mut frame_times = Vec::with_capacity(args.frames as usize);
+	for frame in 1..=args.frames {
+		let start = Instant::now();
+		app.update();
+		frame_times.push(start.elapsed());
+		if frame % 60 == 0 {
+			let live = app
+				.world_mut()
+				.query::<&TimeCreated>()
+				.iter(app.world())
+				.count();
+			let recent = &frame_times[frame_times.len() - 60..];
+			println!(
+				"frame {frame}: {live} particles, {:.2?} average over the last 60 frames",
+				recent.iter().sum::<Duration>() / 60,
+			);
+		}
+	}
+
+	frame_times.sort();
+	let total = frame_times.iter().sum::<Duration>();
+	println!(
+		"{} frames: mean {:.2?}, median {:.2?}, p99 {:.2?}, max {:.2?}",
+		frame_times.len(),
+		total / frame_times.len().max(1) as u32,
+		frame_times[frame_times.len() / 2],
+		frame_times[frame_times.len() * 99 / 100],
+		frame_times[frame_times.len() - 1],
+	);
+}
@@ -0,0 +1,250 @@
+use bevy::{
+	prelude::*,
+	utils::{Duration, HashMap},
+};
+
+#[cfg(feature = "render")]
+use crate::template::ParticleTemplate;
+use crate::{Lifetime, TimeCreated};
+
+/// A pre-simulated looping effect, recorded with a [LoopRecorder] and played back by a
+/// [BakedLoopPlayer] without running any particle behaviors.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct BakedLoop {
+	/// Length of one loop in seconds.
+	pub period: f32,
+	pub particles: Vec<BakedParticle>,
+}
+
+/// One recorded particle, with transforms relative to its spewer.
+#[derive(Debug, Clone)]
+pub struct BakedParticle {
+	/// Seconds into the loop at which the particle spawns.
+	pub spawn_offset: f32,
+	pub lifetime: f32,
+	/// `(age, transform)` pairs sorted by age.
+	pub samples: Vec<(f32, Transform)>,
+}
+
+impl BakedParticle {
+	pub fn sample(&self, age: f32) -> Transform {
+		let i = self.samples.partition_point(|(t, _)| *t <= age);
+		match (self.samples.get(i.wrapping_sub(1)), self.samples.get(i)) {
+			(Some((t0, a)), Some((t1, b))) => {
+				let s = (age - t0) / (t1 - t0).max(f32::EPSILON);
+				Transform {
+					translation: a.translation.lerp(b.translation, s),
+					rotation: a.rotation.slerp(b.rotation, s),
+					scale: a.scale.lerp(b.scale, s),
+				}
+			}
+			(Some((_, xform)), None) | (None, Some((_, xform))) => *xform,
+			(None, None) => Transform::IDENTITY,
+		}
+	}
+}
+
+/// Records the local-space particles of the [Spewer](crate::Spewer) on the same entity
+/// for one `period` after `warmup`, then adds the resulting [BakedLoop] asset, sends
+/// [LoopBaked], and removes itself.
+///
+/// The emission should repeat every `period` (e.g. a constant interval) for the
+/// loop to be seamless.
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct LoopRecorder {
+	pub period: Duration,
+	pub warmup: Duration,
+	started: Option<Duration>,
+	#[reflect(ignore)]
+	tracked: HashMap<Entity, BakedParticle>,
+	#[reflect(ignore)]
+	finished: Vec<BakedParticle>,
+}
+
+impl LoopRecorder {
+	pub fn new(period: Duration, warmup: Duration) -> Self {
+		Self {
+			period,
+			warmup,
+			started: None,
+			tracked: default(),
+			finished: default(),
+		}
+	}
+
+	pub fn record(
+		mut cmds: Commands,
+		mut q: Query<(Entity, &mut Self, Option<&Children>)>,
+		particles: Query<(&Transform, &TimeCreated, &Lifetime)>,
+		mut baked: ResMut<Assets<BakedLoop>>,
+		mut events: EventWriter<LoopBaked>,
+		t: Res<Time>,
+	) {
+		let now = t.elapsed();
+		for (id, mut recorder, children) in &mut q {
+			let recorder = &mut *recorder;
+			let start = *recorder.started.get_or_insert(now + recorder.warmup);
+			let Some(elapsed) = now.checked_sub(start) else {
+				continue;
+			};
+			let recording = elapsed < recorder.period;
+
+			let mut alive = Vec::new();
+			for &child in children.into_iter().flatten() {
+				let Ok((xform, created, lifetime)) = particles.get(child) else {
+					continue;
+				};
+				let Some(offset) = created.checked_sub(start) else {
+					continue;
+				};
+				if offset >= recorder.period && !recorder.tracked.contains_key(&child) {
+					continue;
+				}
+				let particle = recorder
+					.tracked
+					.entry(child)
+					.or_insert_with(|| BakedParticle {
+						spawn_offset: offset.as_secs_f32(),
+						lifetime: lifetime.as_secs_f32(),
+						samples: Vec::new(),
+					});
+				let age = now.saturating_sub(**created).as_secs_f32();
+				particle.samples.push((age, *xform));
+				alive.push(child);
+			}
+			let finished = recorder
+				.tracked
+				.extract_if(|id, _| !alive.contains(id))
+				.map(|(_, particle)| particle);
+			recorder.finished.extend(finished);
+
+			if !recording && recorder.tracked.is_empty() {
+				let handle = baked.add(BakedLoop {
+					period: recorder.period.as_secs_f32(),
+					particles: std::mem::take(&mut recorder.finished),
+				});
+				events.send(LoopBaked {
+					entity: id,
+					baked: handle,
+				});
+				cmds.entity(id).remove::<Self>();
+			}
+		}
+	}
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct LoopBaked {
+	pub entity: Entity,
+	pub baked: Handle<BakedLoop>,
+}
+
+/// Plays a [BakedLoop] back as children of this entity, using `template` for their
+/// mesh and material. Particle lifetimes and behaviors are not used; every frame only
+/// looks up the recorded transforms.
+///
+/// The children are spawned again when `baked` is changed to another loop, or when the
+/// loop is modified, e.g. hot-reloaded.
+#[cfg(feature = "render")]
+#[derive(Component)]
+pub struct BakedLoopPlayer<M: Material = StandardMaterial> {
+	pub baked: Handle<BakedLoop>,
+	pub template: ParticleTemplate<M>,
+	/// Simulated seconds per real second.
+	pub speed: f32,
+	phase: f32,
+	/// One entity per particle and per extra loop that particle is alive for.
+	slots: Vec<(Entity, usize, f32)>,
+	/// The loop `slots` were spawned for.
+	slots_for: Option<AssetId<BakedLoop>>,
+}
+
+#[cfg(feature = "render")]
+impl<M: Material> BakedLoopPlayer<M> {
+	pub fn new(baked: Handle<BakedLoop>, template: ParticleTemplate<M>) -> Self {
+		Self {
+			baked,
+			template,
+			speed: 1.0,
+			phase: 0.0,
+			slots: Vec::new(),
+			slots_for: None,
+		}
+	}
+
+	pub fn tick(
+		mut cmds: Commands,
+		mut q: Query<(Entity, &mut Self)>,
+		mut slots: Query<(&mut Transform, &mut Visibility)>,
+		mut events: EventReader<AssetEvent<BakedLoop>>,
+		baked: Res<Assets<BakedLoop>>,
+		t: Res<Time>,
+	) {
+		let modified = events
+			.read()
+			.filter_map(|event| match *event {
+				AssetEvent::Modified { id } => Some(id),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		for (id, mut player) in &mut q {
+			let Some(baked) = baked.get(&player.baked) else {
+				continue;
+			};
+			let period = baked.period.max(f32::EPSILON);
+			player.phase = (player.phase + t.delta_seconds() * player.speed).rem_euclid(period);
+
+			let baked_id = player.baked.id();
+			if player.slots_for != Some(baked_id) || modified.contains(&baked_id) {
+				for (slot, ..) in player.slots.drain(..) {
+					if let Some(slot) = cmds.get_entity(slot) {
+						slot.despawn_recursive();
+					}
+				}
+				player.slots_for = Some(baked_id);
+			}
+			if player.slots.is_empty() {
+				let slots = baked
+					.particles
+					.iter()
+					.enumerate()
+					.flat_map(|(i, particle)| {
+						let copies = (particle.lifetime / period).ceil().max(1.0) as usize;
+						(0..copies).map(move |n| (i, n as f32 * period))
+					})
+					.map(|(i, extra_age)| {
+						let slot = cmds
+							.spawn(MaterialMeshBundle {
+								mesh: player.template.mesh.clone(),
+								material: player.template.material.clone(),
+								visibility: Visibility::Hidden,
+								..default()
+							})
+							.set_parent(id)
+							.id();
+						(slot, i, extra_age)
+					})
+					.collect();
+				player.slots = slots;
+				continue;
+			}
+
+			for &(slot, i, extra_age) in &player.slots {
+				let Ok((mut xform, mut vis)) = slots.get_mut(slot) else {
+					continue;
+				};
+				let Some(particle) = baked.particles.get(i) else {
+					continue;
+				};
+				let age = (player.phase - particle.spawn_offset).rem_euclid(period) + extra_age;
+				if age < particle.lifetime {
+					*xform = particle.sample(age);
+					*vis = Visibility::Inherited;
+				} else {
+					*vis = Visibility::Hidden;
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,206 @@
+//! Segmented beams between two entities, e.g. lightning, electric arcs, or tethers.
+
+use bevy::{prelude::*, render::view::NoFrustumCulling, utils::Duration};
+use nanorand::{Rng, WyRand};
+
+use crate::{
+	trail::{replace_mesh, RibbonBuilder, RibbonPoint},
+	update::{main_camera, Cameras},
+};
+
+/// Draws a jagged, camera-facing ribbon from `start` to `end`, regenerated `refresh_rate`
+/// times per second.
+///
+/// The path is built by midpoint displacement: each of the `detail` passes splits every
+/// segment in two and offsets the new point sideways by up to `jitter` times the segment's
+/// length. Offsets are relative to the beam, so it stays attached while its ends move
+/// between refreshes.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component, from_reflect = false)]
+pub struct Beam {
+	pub start: Entity,
+	pub end: Entity,
+	/// Number of subdivision passes. The main path has `2^detail` segments.
+	pub detail: u32,
+	/// Sideways offset of each new point, as a fraction of the segment it splits.
+	pub jitter: f32,
+	/// Chance for each point of the main path to fork off a shorter branch.
+	pub branch_chance: f32,
+	/// Width of the beam in world units, before `width_over_beam`.
+	pub width: f32,
+	/// Factor of `width` along the beam, from `0.0` at `start` to `1.0` at `end`.
+	#[reflect(ignore)]
+	pub width_over_beam: fn(f32) -> f32,
+	/// Vertex color along the beam, multiplied with the material's color.
+	#[reflect(ignore)]
+	pub color_over_beam: fn(f32) -> LinearRgba,
+	/// New paths per second. `0.0` keeps the first path.
+	pub refresh_rate: f32,
+	#[reflect(ignore)]
+	pub rng: WyRand,
+	/// Elapsed time of the last refresh.
+	pub last_refresh: Option<Duration>,
+	/// Main path followed by any branches, in beam space: `x` runs from `0.0` at `start`
+	/// to `1.0` at `end`, and `y` and `z` are sideways offsets as fractions of its length.
+	pub paths: Vec<Vec<Vec3>>,
+}
+
+impl Beam {
+	pub fn new(start: Entity, end: Entity, width: f32) -> Self {
+		Self {
+			start,
+			end,
+			detail: 5,
+			jitter: 0.3,
+			branch_chance: 0.0,
+			width,
+			width_over_beam: |_| 1.0,
+			color_over_beam: |_| LinearRgba::WHITE,
+			refresh_rate: 20.0,
+			rng: WyRand::new(),
+			last_refresh: None,
+			paths: Vec::new(),
+		}
+	}
+
+	pub fn seeded(self, seed: u64) -> Self {
+		Self {
+			rng: WyRand::new_seed(seed),
+			..self
+		}
+	}
+
+	/// Generates new paths.
+	pub fn refresh(&mut self) {
+		let main = displace(
+			vec![Vec3::ZERO, Vec3::X],
+			self.detail,
+			self.jitter,
+			&mut self.rng,
+		);
+		let mut paths = vec![];
+		for &from in &main[1..main.len().saturating_sub(1)] {
+			if self.rng.generate::<f32>() >= self.branch_chance {
+				continue;
+			}
+			let length = (1.0 - from.x) * (0.2 + 0.3 * self.rng.generate::<f32>());
+			let angle = self.rng.generate::<f32>() * std::f32::consts::TAU;
+			let sideways = Vec3::new(0.0, angle.cos(), angle.sin()) * length * 0.5;
+			let to = from + Vec3::X * length + sideways;
+			let detail = self.detail.saturating_sub(2);
+			paths.push(displace(vec![from, to], detail, self.jitter, &mut self.rng));
+		}
+		paths.insert(0, main);
+		self.paths = paths;
+	}
+
+	pub fn tick(mut q: Query<&mut Self>, t: Res<Time>) {
+		let now = t.elapsed();
+		for mut beam in &mut q {
+			let due = match beam.last_refresh {
+				None => true,
+				Some(_) if beam.refresh_rate <= 0.0 => false,
+				Some(last) => now.saturating_sub(last).as_secs_f32() >= 1.0 / beam.refresh_rate,
+			};
+			if due {
+				beam.refresh();
+				beam.last_refresh = Some(now);
+			}
+		}
+	}
+
+	/// Builds the beam between `start` and `end` in world space, facing `view_position`.
+	pub fn build_mesh(&self, start: Vec3, end: Vec3, view_position: Vec3) -> Mesh {
+		let axis = end - start;
+		let length = axis.length();
+		let dir = axis.normalize_or(Vec3::X);
+		let side = dir.any_orthonormal_vector();
+		let up = dir.cross(side);
+		let to_world = |p: Vec3| start + axis * p.x + (side * p.y + up * p.z) * length;
+
+		let mut ribbon = RibbonBuilder::default();
+		for (i, path) in self.paths.iter().enumerate() {
+			// Branches are thinner than the main path.
+			let scale = if i == 0 { 1.0 } else { 0.5 };
+			let points = path
+				.iter()
+				.enumerate()
+				.map(|(j, &p)| {
+					let s = p.x.clamp(0.0, 1.0);
+					RibbonPoint {
+						position: to_world(p),
+						half_width: 0.5 * self.width * scale * (self.width_over_beam)(s),
+						color: (self.color_over_beam)(s),
+						v: j as f32 / (path.len() - 1).max(1) as f32,
+					}
+				})
+				.collect::<Vec<_>>();
+			ribbon.add_strip(&points, view_position);
+		}
+		ribbon.finish()
+	}
+
+	pub fn update_meshes(
+		mut cmds: Commands,
+		q: Query<(Entity, &Self, Option<&Handle<Mesh>>)>,
+		ends: Query<&GlobalTransform>,
+		mut meshes: ResMut<Assets<Mesh>>,
+		cameras: Cameras,
+	) {
+		let Some((.., camera)) = main_camera(&cameras) else {
+			return;
+		};
+		for (id, beam, handle) in &q {
+			let (Ok(start), Ok(end)) = (ends.get(beam.start), ends.get(beam.end)) else {
+				// Nothing to connect while either end is missing.
+				let empty = RibbonBuilder::default().finish();
+				replace_mesh(&mut cmds, id, handle, &mut meshes, empty);
+				continue;
+			};
+			let mesh =
+				beam.build_mesh(start.translation(), end.translation(), camera.translation());
+			replace_mesh(&mut cmds, id, handle, &mut meshes, mesh);
+		}
+	}
+}
+
+/// Subdivides `points` `detail` times, offsetting each new midpoint sideways.
+fn displace(mut points: Vec<Vec3>, detail: u32, jitter: f32, rng: &mut WyRand) -> Vec<Vec3> {
+	for _ in 0..detail {
+		let mut next = Vec::with_capacity(points.len() * 2 - 1);
+		for pair in points.windows(2) {
+			let (a, b) = (pair[0], pair[1]);
+			let offset = Vec3::new(
+				0.0,
+				rng.generate::<f32>() * 2.0 - 1.0,
+				rng.generate::<f32>() * 2.0 - 1.0,
+			);
+			next.push(a);
+			next.push((a + b) * 0.5 + offset * jitter * a.distance(b));
+		}
+		next.extend(points.last());
+		points = next;
+	}
+	points
+}
+
+/// A [Beam] drawn with `material`. Its mesh is created by the beam.
+#[derive(Bundle)]
+pub struct BeamBundle<M: Material = StandardMaterial> {
+	pub beam: Beam,
+	pub material: Handle<M>,
+	pub spatial: SpatialBundle,
+	/// The beam's bounds change every frame, so it must not be culled based on them.
+	pub no_frustum_culling: NoFrustumCulling,
+}
+
+impl<M: Material> BeamBundle<M> {
+	pub fn new(beam: Beam, material: Handle<M>) -> Self {
+		Self {
+			beam,
+			material,
+			spatial: default(),
+			no_frustum_culling: NoFrustumCulling,
+		}
+	}
+}
@@ -0,0 +1,50 @@
+#define_import_path sond_bevy_particles::billboard
+
+#import bevy_pbr::{
+	mesh_view_bindings::view,
+	view_transformations::{position_ndc_to_world, position_world_to_clip},
+}
+
+// Orientation of a billboarded particle, with its right, up, and back axes as columns.
+fn billboard_rotation(world_from_local: mat4x4<f32>) -> mat3x3<f32> {
+#ifdef BILLBOARD_AXIS_ALIGNED
+	let up = normalize(world_from_local[1].xyz);
+#ifdef VIEW_PROJECTION_ORTHOGRAPHIC
+	let to_camera = view.world_from_view[2].xyz;
+#else
+	let to_camera = view.world_position - world_from_local[3].xyz;
+#endif
+	let right = normalize(cross(up, to_camera));
+	let back = cross(right, up);
+	return mat3x3(right, up, back);
+#else
+	return mat3x3(view.world_from_view[0].xyz, view.world_from_view[1].xyz, view.world_from_view[2].xyz);
+#endif
+}
+
+fn billboard_scale(world_from_local: mat4x4<f32>) -> vec3<f32> {
+	return vec3(
+		length(world_from_local[0].xyz),
+		length(world_from_local[1].xyz),
+		length(world_from_local[2].xyz),
+	);
+}
+
+fn billboard_position(world_from_local: mat4x4<f32>, rotation: mat3x3<f32>, local_position: vec3<f32>) -> vec4<f32> {
+	let scale = billboard_scale(world_from_local);
+	let basis = mat3x3(rotation[0] * scale.x, rotation[1] * scale.y, rotation[2] * scale.z);
+	return vec4(world_from_local[3].xyz + basis * local_position, 1.0);
+}
+
+// World position of a point sprite vertex, with `size` pixels across a mesh one unit across.
+fn point_sprite_position(world_from_local: mat4x4<f32>, local_position: vec3<f32>, size: f32, attenuation_distance: f32) -> vec4<f32> {
+	let origin = position_world_to_clip(world_from_local[3].xyz);
+	var pixels = size * billboard_scale(world_from_local).xy;
+	// Only perspective projections have a `w` of the view depth.
+	if attenuation_distance > 0.0 && view.clip_from_view[3][3] == 0.0 {
+		pixels *= attenuation_distance / max(origin.w, 1e-4);
+	}
+	let offset = local_position.xy * pixels * 2.0 / view.viewport.zw;
+	let clip = origin + vec4(offset * origin.w, 0.0, 0.0);
+	return vec4(position_ndc_to_world(clip.xyz / clip.w), 1.0);
+}
@@ -0,0 +1,105 @@
+//! An engine-wide cap on live particles, shared out by priority.
+
+use std::cmp::Reverse;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{Deterministic, EffectName, Lifetime, TimeCreated};
+
+/// Caps the number of live cosmetic particles, e.g. to bound the cost of effects on
+/// low-end hardware. Insert it to enable the cap.
+///
+/// Particles over the cap are despawned right after they are spawned, lowest
+/// [ParticlePriority] first, and the newest first within a priority. So when the cap is
+/// hit, new ambient particles are the first to be throttled, then ones already alive
+/// are taken to make room for higher-priority effects like explosions, which are only
+/// throttled once no lower-priority particles are left.
+///
+/// [Deterministic] particles and [ParticleBuffer](crate::buffer::ParticleBuffer)s are
+/// neither counted nor despawned.
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct ParticleBudget {
+	pub max_particles: usize,
+	/// Live particles after the last update, e.g. for debug overlays.
+	pub live: usize,
+	/// Particles despawned to stay within the cap in the last update.
+	pub culled: usize,
+	/// How many of `culled` were from each [EffectName], to find the effects to trim.
+	pub culled_by_effect: HashMap<EffectName, usize>,
+}
+
+impl ParticleBudget {
+	pub fn new(max_particles: usize) -> Self {
+		Self {
+			max_particles,
+			live: 0,
+			culled: 0,
+			culled_by_effect: HashMap::default(),
+		}
+	}
+
+	/// Despawns the particles over the cap. Runs right after cosmetic spewers emit.
+	pub fn enforce(
+		mut cmds: Commands,
+		budget: Option<ResMut<Self>>,
+		q: Query<
+			(
+				Entity,
+				&TimeCreated,
+				Option<&ParticlePriority>,
+				Option<&EffectName>,
+			),
+			(With<Lifetime>, Without<Deterministic>),
+		>,
+	) {
+		let Some(mut budget) = budget else {
+			return;
+		};
+		let live = q.iter().len();
+		let over = live.saturating_sub(budget.max_particles);
+		budget.live = live - over;
+		budget.culled = over;
+		budget.culled_by_effect.clear();
+		if over == 0 {
+			return;
+		}
+		let mut particles = q
+			.iter()
+			.map(|(id, created, priority, effect)| {
+				let priority = priority.copied().unwrap_or_default();
+				((priority, Reverse(created.0)), id, effect)
+			})
+			.collect::<Vec<_>>();
+		if over < particles.len() {
+			particles.select_nth_unstable(over);
+		}
+		for &(_, id, effect) in &particles[..over] {
+			cmds.entity(id).despawn_recursive();
+			if let Some(effect) = effect {
+				match budget.culled_by_effect.get_mut(effect) {
+					Some(count) => *count += 1,
+					None => {
+						budget.culled_by_effect.insert(effect.clone(), 1);
+					}
+				}
+			}
+		}
+	}
+}
+
+/// How important the particles of this spewer are for a [ParticleBudget] to keep, e.g.
+/// negative for ambient effects and positive for gameplay effects. `0` by default.
+///
+/// Copied from the spewer onto each particle it spawns, so it still applies once a
+/// one-shot spewer is gone.
+#[derive(
+	Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Component, Deref, Reflect,
+)]
+#[reflect(Component)]
+pub struct ParticlePriority(pub i32);
+
+impl ParticlePriority {
+	pub const AMBIENT: Self = Self(-100);
+	pub const GAMEPLAY: Self = Self(100);
+}
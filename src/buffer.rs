@@ -0,0 +1,377 @@
+//! Opt-in storage for large numbers of lightweight particles.
+//!
+//! A [ParticleBuffer] keeps the hot data of all of its particles in contiguous `Vec`s on
+//! a single entity, instead of spawning one entity per particle, and is drawn with one
+//! instanced draw call. Particles in a buffer only support the simple motion the buffer
+//! itself simulates, not the behavior components in [update](crate::update).
+//!
+//! Individual particles that gameplay needs to interact with can be moved out of the
+//! buffer into full entities with [ParticleBuffer::promote].
+
+use bevy::{ecs::system::EntityCommands, prelude::*, utils::Duration};
+use nanorand::{Rng, WyRand};
+
+use crate::{
+	dilation::TimeDilation,
+	flicker::Flicker,
+	math::{integrate, lifetime_progress},
+	update::{SizeOverLifetime, Velocity},
+	Lifetime, ParticleFactory, ParticleLayers, SpanTarget, TimeCreated,
+};
+
+#[cfg(feature = "render")]
+mod offscreen;
+#[cfg(feature = "render")]
+mod render;
+#[cfg(feature = "render")]
+pub use render::*;
+
+/// Initial state of a particle emitted into a [ParticleBuffer], in the emitter's local space.
+#[derive(Debug, Clone, Copy)]
+pub struct BufferParticle {
+	pub position: Vec3,
+	pub velocity: Vec3,
+	pub color: LinearRgba,
+	/// See [ParticleBuffer::attributes].
+	pub attributes: Vec4,
+}
+
+impl Default for BufferParticle {
+	fn default() -> Self {
+		Self {
+			position: Vec3::ZERO,
+			velocity: Vec3::ZERO,
+			color: LinearRgba::WHITE,
+			attributes: Vec4::ZERO,
+		}
+	}
+}
+
+/// A particle removed from a [ParticleBuffer], in world space.
+#[derive(Debug, Clone, Copy)]
+pub struct TakenParticle {
+	pub position: Vec3,
+	pub velocity: Vec3,
+	/// Seconds since the particle was emitted.
+	pub age: f32,
+	pub seed: u32,
+	pub color: LinearRgba,
+	pub attributes: Vec4,
+}
+
+/// Marks an entity that was promoted out of a [ParticleBuffer], keeping the data the
+/// entity's own components don't cover.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct PromotedParticle {
+	pub seed: u32,
+	pub color: LinearRgba,
+	pub attributes: Vec4,
+}
+
+/// How the particles of a [ParticleBuffer] are blended into the view.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub enum BufferPass {
+	/// Alpha blended in the main transparent pass, in the order the particles are drawn.
+	#[default]
+	Transparent,
+	/// Weighted blended order-independent transparency: particles are accumulated into
+	/// offscreen targets weighted by their alpha and distance, then composited in one pass.
+	/// Overlapping particles blend plausibly without [depth_sort](ParticleBuffer::depth_sort),
+	/// which suits heavy overdraw like smoke, but the result is an approximation that
+	/// loses the exact front-to-back order of similar colors.
+	///
+	/// Needs a device that can blend each render target differently, which WebGL2 can't;
+	/// elsewhere the particles are drawn like [BufferPass::Transparent].
+	WeightedOit,
+	/// Alpha blended into a half-resolution target, which is then upsampled over the view,
+	/// to cut the fill-rate cost of large particles. Where the depth of the view changes
+	/// sharply, the nearest half-resolution texel in depth is used instead of blending
+	/// texels, so that particles don't bleed over the edges of opaque meshes.
+	///
+	/// Opaque meshes only occlude these particles if they are in the depth prepass, so this
+	/// needs a `DepthPrepass` on the camera. Other cameras draw the particles like
+	/// [BufferPass::Transparent], as do cameras with MSAA on devices that can't read
+	/// multisampled depth in shaders, like WebGL2.
+	HalfResolution,
+}
+
+pub trait BufferParticleFn: FnMut(&mut WyRand) -> BufferParticle + Send + Sync + 'static {}
+impl<F> BufferParticleFn for F where F: FnMut(&mut WyRand) -> BufferParticle + Send + Sync + 'static {}
+
+/// Emits and simulates particles stored as structure-of-arrays data on this entity.
+///
+/// Particles are emitted at the entity's `GlobalTransform` and simulated in world space.
+/// All the per-particle `Vec`s always have the same length.
+#[derive(Component, Reflect)]
+#[reflect(Component, from_reflect = false)]
+pub struct ParticleBuffer {
+	#[reflect(ignore)]
+	pub init: Box<dyn BufferParticleFn>,
+	pub interval: Duration,
+	pub lifetime: Duration,
+	/// Maximum number of live particles. Emission is skipped while the buffer is full.
+	pub capacity: usize,
+	/// World-space acceleration applied to every particle, e.g. gravity.
+	pub acceleration: Vec3,
+	/// Rendered size of each particle in world units.
+	pub size: f32,
+	/// Scales `size` over each particle's lifetime. Only the `x` and `y` curves are used,
+	/// since buffer particles are camera-facing quads.
+	#[reflect(ignore)]
+	pub size_over_lifetime: Option<SizeOverLifetime>,
+	/// Modulates the alpha of each particle, seeded by its seed.
+	pub flicker: Option<Flicker>,
+	/// Draws the particles back to front for each camera, so that overlapping particles
+	/// blend correctly. Costs a sort of every particle per camera each frame, so it can be
+	/// left off for additive or sparse effects.
+	pub depth_sort: bool,
+	/// How the particles are blended. Sorting is unneeded with [BufferPass::WeightedOit].
+	pub pass: BufferPass,
+	/// Elapsed time of the last emission. Set to the current time when the buffer is added,
+	/// unless it is already set.
+	pub last_spawn: Duration,
+	#[reflect(ignore)]
+	pub rng: WyRand,
+	pub positions: Vec<Vec3>,
+	pub velocities: Vec<Vec3>,
+	/// Seconds since each particle was emitted.
+	pub ages: Vec<f32>,
+	/// Random value per particle, for effects that need stable per-particle variation.
+	pub seeds: Vec<u32>,
+	pub colors: Vec<LinearRgba>,
+	/// Four values per particle for the shader to read, set from [BufferParticle::attributes]
+	/// when it is emitted, and free for gameplay to change afterwards, e.g. a charge level.
+	/// Only a [ParticleBufferShader] reads them.
+	pub attributes: Vec<Vec4>,
+}
+
+impl ParticleBuffer {
+	pub fn new(init: impl BufferParticleFn) -> Self {
+		Self {
+			init: Box::new(init),
+			interval: Duration::from_secs_f32(1.0 / 60.0),
+			lifetime: Duration::from_secs(1),
+			capacity: 10_000,
+			acceleration: Vec3::ZERO,
+			size: 0.1,
+			size_over_lifetime: None,
+			flicker: None,
+			depth_sort: false,
+			pass: BufferPass::Transparent,
+			last_spawn: Duration::ZERO,
+			rng: WyRand::new(),
+			positions: Vec::new(),
+			velocities: Vec::new(),
+			ages: Vec::new(),
+			seeds: Vec::new(),
+			colors: Vec::new(),
+			attributes: Vec::new(),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.positions.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.positions.is_empty()
+	}
+
+	/// Adds a particle that is already in world space, with zeroed [attributes](Self::attributes).
+	pub fn push(&mut self, position: Vec3, velocity: Vec3, color: LinearRgba, seed: u32) {
+		self.positions.push(position);
+		self.velocities.push(velocity);
+		self.ages.push(0.0);
+		self.seeds.push(seed);
+		self.colors.push(color);
+		self.attributes.push(Vec4::ZERO);
+	}
+
+	/// Removes particle `i`, replacing it with the last particle.
+	pub fn swap_remove(&mut self, i: usize) {
+		self.positions.swap_remove(i);
+		self.velocities.swap_remove(i);
+		self.ages.swap_remove(i);
+		self.seeds.swap_remove(i);
+		self.colors.swap_remove(i);
+		self.attributes.swap_remove(i);
+	}
+
+	/// Removes particle `i` like [swap_remove](Self::swap_remove), returning its data.
+	pub fn take(&mut self, i: usize) -> TakenParticle {
+		TakenParticle {
+			position: self.positions.swap_remove(i),
+			velocity: self.velocities.swap_remove(i),
+			age: self.ages.swap_remove(i),
+			seed: self.seeds.swap_remove(i),
+			color: self.colors.swap_remove(i),
+			attributes: self.attributes.swap_remove(i),
+		}
+	}
+
+	/// Index of the closest particle within `max_distance` of `point`, e.g. for a pickup.
+	pub fn nearest(&self, point: Vec3, max_distance: f32) -> Option<usize> {
+		self.positions
+			.iter()
+			.map(|pos| pos.distance_squared(point))
+			.enumerate()
+			.filter(|(_, dist)| *dist <= max_distance * max_distance)
+			.min_by(|(_, a), (_, b)| a.total_cmp(b))
+			.map(|(i, _)| i)
+	}
+
+	/// Moves particle `i` out of the buffer into an entity spawned by `factory`, like a
+	/// [Spewer](crate::Spewer)'s particles, so gameplay systems can interact with it.
+	///
+	/// `now` must be the elapsed time of the clock the buffer is ticked with. The entity
+	/// gets the particle's remaining [Lifetime], its [Velocity], and a [PromotedParticle].
+	/// It is no longer affected by the buffer's `acceleration`.
+	pub fn promote<'a>(
+		&mut self,
+		i: usize,
+		cmds: &'a mut Commands,
+		now: Duration,
+		factory: &mut impl ParticleFactory,
+	) -> EntityCommands<'a> {
+		let particle = self.take(i);
+		let created = TimeCreated(now.saturating_sub(Duration::from_secs_f32(particle.age)));
+		let xform = GlobalTransform::from_translation(particle.position);
+		let mut entity = factory(cmds, &xform, created);
+		entity.insert((
+			Transform::from_translation(particle.position),
+			created,
+			Lifetime(self.lifetime),
+			Velocity(particle.velocity),
+			PromotedParticle {
+				seed: particle.seed,
+				color: particle.color,
+				attributes: particle.attributes,
+			},
+		));
+		entity
+	}
+
+	/// Rendered width and height of particle `i` in world units.
+	pub fn size_of(&self, i: usize) -> Vec2 {
+		match &self.size_over_lifetime {
+			Some(curves) => {
+				let s = lifetime_progress(self.ages[i], self.lifetime.as_secs_f32());
+				self.size * curves.sample(s).truncate()
+			}
+			None => Vec2::splat(self.size),
+		}
+	}
+
+	/// Rendered color of particle `i`.
+	pub fn color_of(&self, i: usize) -> LinearRgba {
+		let color = self.colors[i];
+		match &self.flicker {
+			Some(flicker) if flicker.alpha => {
+				color.with_alpha(color.alpha * flicker.sample(self.seeds[i], self.ages[i]))
+			}
+			_ => color,
+		}
+	}
+
+	pub fn clear(&mut self) {
+		self.positions.clear();
+		self.velocities.clear();
+		self.ages.clear();
+		self.seeds.clear();
+		self.colors.clear();
+		self.attributes.clear();
+	}
+
+	/// Ages, moves, and emits particles. Each particle ages and moves at the scale of time
+	/// at its position in any [TimeDilation] bubbles.
+	pub fn tick(
+		mut q: Query<(
+			&mut Self,
+			&GlobalTransform,
+			Option<&ParticleLayers>,
+			SpanTarget,
+		)>,
+		volumes: Query<(&TimeDilation, &GlobalTransform, Option<&ParticleLayers>)>,
+		t: Res<Time>,
+	) {
+		let dt = t.delta_seconds();
+		let now = t.elapsed();
+		let volumes = TimeDilation::collect(&volumes);
+		let scale_at = |position, layers| {
+			if volumes.is_empty() {
+				1.0
+			} else {
+				TimeDilation::scale_among(&volumes, position, layers)
+			}
+		};
+		q.par_iter_mut()
+			.for_each(|(mut buffer, xform, layers, span)| {
+				#[cfg(feature = "trace")]
+				let _span = {
+					let (entity, name) = span;
+					bevy::log::info_span!("particle_buffer", ?entity, name = crate::span_name(name))
+						.entered()
+				};
+				#[cfg(not(feature = "trace"))]
+				let () = span;
+				if buffer.is_added() && buffer.last_spawn == Duration::ZERO {
+					buffer.last_spawn = now;
+				}
+				let buffer = &mut *buffer;
+
+				let lifetime = buffer.lifetime.as_secs_f32();
+				let mut i = 0;
+				while i < buffer.len() {
+					let dt = dt * scale_at(buffer.positions[i], layers);
+					buffer.ages[i] += dt;
+					if buffer.ages[i] >= lifetime {
+						// The swapped-in particle is aged on the next iteration.
+						buffer.swap_remove(i);
+						continue;
+					}
+					(buffer.positions[i], buffer.velocities[i]) = integrate(
+						buffer.positions[i],
+						buffer.velocities[i],
+						buffer.acceleration,
+						dt,
+					);
+					i += 1;
+				}
+
+				if buffer.interval.is_zero() {
+					return;
+				}
+				// Like a `Spewer`'s `CatchUp::max_backlog`, so a long stall doesn't emit every
+				// interval since: emissions due longer ago than the lifetime would have expired
+				// already, and more than `capacity` of them wouldn't fit.
+				let max_backlog = buffer
+					.interval
+					.saturating_mul(u32::try_from(buffer.capacity).unwrap_or(u32::MAX))
+					.min(buffer.lifetime);
+				buffer.last_spawn = buffer.last_spawn.max(now.saturating_sub(max_backlog));
+				while now.saturating_sub(buffer.last_spawn) >= buffer.interval {
+					buffer.last_spawn += buffer.interval;
+					if buffer.len() >= buffer.capacity {
+						continue;
+					}
+					let particle = (buffer.init)(&mut buffer.rng);
+					let seed = buffer.rng.generate();
+					// Simulate the part of the frame since the particle was due, so particles
+					// emitted within one frame don't clump together.
+					let position = xform.transform_point(particle.position);
+					let age = now.saturating_sub(buffer.last_spawn).as_secs_f32()
+						* scale_at(position, layers);
+					let (position, velocity) = integrate(
+						position,
+						xform.affine().transform_vector3(particle.velocity),
+						buffer.acceleration,
+						age,
+					);
+					buffer.push(position, velocity, particle.color, seed);
+					*buffer.ages.last_mut().unwrap() = age;
+					*buffer.attributes.last_mut().unwrap() = particle.attributes;
+				}
+			});
+	}
+}
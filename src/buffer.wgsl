@@ -0,0 +1,67 @@
+#import bevy_pbr::{
+	mesh_view_bindings::view,
+	view_transformations::position_world_to_clip,
+}
+#import sond_bevy_particles::buffer::VertexOutput
+
+struct Vertex {
+	@location(0) position: vec3<f32>,
+	// Per-instance data comes after all the attributes the mesh pipeline may use.
+	@location(8) i_position: vec3<f32>,
+	@location(9) i_size: vec2<f32>,
+	@location(10) i_color: vec4<f32>,
+	@location(11) i_progress: f32,
+	@location(12) i_seed: u32,
+	@location(13) i_attributes: vec4<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+	// The mesh's XY plane always faces the camera.
+	let right = view.world_from_view[0].xyz;
+	let up = view.world_from_view[1].xyz;
+	let offset = right * vertex.position.x * vertex.i_size.x + up * vertex.position.y * vertex.i_size.y;
+
+	let world_position = vertex.i_position + offset;
+	var out: VertexOutput;
+	out.clip_position = position_world_to_clip(world_position);
+	out.color = vertex.i_color;
+	out.view_depth = -(view.view_from_world * vec4(world_position, 1.0)).z;
+	out.local_position = vertex.position.xy;
+	out.progress = vertex.i_progress;
+	out.seed = vertex.i_seed;
+	out.attributes = vertex.i_attributes;
+	return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+	return in.color;
+}
+
+struct OitOutput {
+	@location(0) accum: vec4<f32>,
+	@location(1) revealage: f32,
+};
+
+// Weighted blended order-independent transparency, from McGuire and Bavoil 2013. The
+// accumulation target sums premultiplied colors weighted towards the camera, and the
+// revealage target multiplies `1 - alpha` of every particle over the pixel.
+@fragment
+fn fragment_oit(in: VertexOutput) -> OitOutput {
+	let alpha = in.color.a;
+	let z = in.view_depth;
+	let weight = alpha * clamp(10.0 / (1e-5 + pow(z / 5.0, 2.0) + pow(z / 200.0, 6.0)), 1e-2, 3e3);
+
+	var out: OitOutput;
+	out.accum = vec4(in.color.rgb * alpha, alpha) * weight;
+	out.revealage = alpha;
+	return out;
+}
+
+// Premultiplied, so that what is blended into the half-resolution target can be blended
+// over the view as a whole.
+@fragment
+fn fragment_premultiplied(in: VertexOutput) -> @location(0) vec4<f32> {
+	return vec4(in.color.rgb * in.color.a, in.color.a);
+}
@@ -0,0 +1,743 @@
+//! Drawing [ParticleBuffer](super::ParticleBuffer)s outside of the main transparent pass,
+//! into offscreen targets that are then composited over the view, for the
+//! [BufferPass]es other than [BufferPass::Transparent].
+
+use std::ops::Range;
+
+use bevy::{
+	asset::load_internal_asset,
+	core_pipeline::{
+		core_3d::{
+			graph::{Core3d, Node3d},
+			CORE_3D_DEPTH_FORMAT,
+		},
+		fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+		prepass::{DepthPrepass, ViewPrepassTextures},
+	},
+	ecs::{entity::EntityHashSet, query::QueryItem},
+	math::FloatOrd,
+	pbr::MeshPipeline,
+	prelude::*,
+	render::{
+		camera::ExtractedCamera,
+		render_graph::{
+			NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+		},
+		render_phase::{
+			sort_phase_system, CachedRenderPipelinePhaseItem, DrawFunctionId, DrawFunctions,
+			PhaseItem, PhaseItemExtraIndex, SortedPhaseItem, SortedRenderPhase,
+			SortedRenderPhasePlugin, TrackedRenderPass, ViewSortedRenderPhases,
+		},
+		render_resource::{
+			binding_types::{texture_2d, texture_2d_multisampled},
+			*,
+		},
+		renderer::{RenderContext, RenderDevice},
+		texture::{CachedTexture, TextureCache},
+		view::{ViewDepthTexture, ViewTarget},
+		Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+	},
+};
+
+use super::BufferPass;
+
+pub const PARTICLE_COMPOSITE_SHADER_HANDLE: Handle<Shader> =
+	Handle::weak_from_u128(95216407335861124505321751270155249112);
+
+/// Format of the weighted color sums of [BufferPass::WeightedOit]. Needs more range and
+/// precision than the view target, since the weights go up to thousands.
+pub const OIT_ACCUM_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+/// Format of the product of `1 - alpha` of [BufferPass::WeightedOit].
+pub const OIT_REVEALAGE_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+/// Format of the premultiplied colors of [BufferPass::HalfResolution], which keeps HDR
+/// values for HDR views.
+pub const HALF_RESOLUTION_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+pub(super) struct OffscreenParticlesPlugin;
+
+impl Plugin for OffscreenParticlesPlugin {
+	fn build(&self, app: &mut App) {
+		load_internal_asset!(
+			app,
+			PARTICLE_COMPOSITE_SHADER_HANDLE,
+			"../buffer_composite.wgsl",
+			Shader::from_wgsl
+		);
+		app.add_plugins(SortedRenderPhasePlugin::<OffscreenParticles, MeshPipeline>::default());
+		let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+			return;
+		};
+		render_app
+			.init_resource::<DrawFunctions<OffscreenParticles>>()
+			.init_resource::<SpecializedRenderPipelines<CompositePipeline>>()
+			.add_systems(ExtractSchedule, extract_offscreen_phases)
+			.add_systems(
+				Render,
+				(
+					sort_phase_system::<OffscreenParticles>.in_set(RenderSet::PhaseSort),
+					prepare_offscreen_targets.in_set(RenderSet::PrepareResources),
+					prepare_offscreen_composites.in_set(RenderSet::PrepareBindGroups),
+				),
+			)
+			.add_render_graph_node::<ViewNodeRunner<OffscreenParticlesNode>>(
+				Core3d,
+				OffscreenParticlesLabel,
+			)
+			.add_render_graph_edges(
+				Core3d,
+				(
+					Node3d::MainTransparentPass,
+					OffscreenParticlesLabel,
+					Node3d::EndMainPass,
+				),
+			);
+	}
+
+	fn finish(&self, app: &mut App) {
+		if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+			render_app.init_resource::<CompositePipeline>();
+		}
+	}
+}
+
+/// A [ParticleBuffer](super::ParticleBuffer) drawn by the [OffscreenParticlesNode] instead
+/// of the main transparent pass, grouped by its [BufferPass].
+pub struct OffscreenParticles {
+	pub pass: BufferPass,
+	pub distance: f32,
+	pub pipeline: CachedRenderPipelineId,
+	pub entity: Entity,
+	pub draw_function: DrawFunctionId,
+	pub batch_range: Range<u32>,
+	pub extra_index: PhaseItemExtraIndex,
+}
+
+impl PhaseItem for OffscreenParticles {
+	#[inline]
+	fn entity(&self) -> Entity {
+		self.entity
+	}
+
+	#[inline]
+	fn draw_function(&self) -> DrawFunctionId {
+		self.draw_function
+	}
+
+	#[inline]
+	fn batch_range(&self) -> &Range<u32> {
+		&self.batch_range
+	}
+
+	#[inline]
+	fn batch_range_mut(&mut self) -> &mut Range<u32> {
+		&mut self.batch_range
+	}
+
+	#[inline]
+	fn extra_index(&self) -> PhaseItemExtraIndex {
+		self.extra_index
+	}
+
+	#[inline]
+	fn batch_range_and_extra_index_mut(&mut self) -> (&mut Range<u32>, &mut PhaseItemExtraIndex) {
+		(&mut self.batch_range, &mut self.extra_index)
+	}
+}
+
+impl SortedPhaseItem for OffscreenParticles {
+	// Each pass is contiguous, back to front within it like the transparent phase.
+	type SortKey = (BufferPass, FloatOrd);
+
+	#[inline]
+	fn sort_key(&self) -> Self::SortKey {
+		(self.pass, FloatOrd(self.distance))
+	}
+}
+
+impl CachedRenderPipelinePhaseItem for OffscreenParticles {
+	#[inline]
+	fn cached_pipeline(&self) -> CachedRenderPipelineId {
+		self.pipeline
+	}
+}
+
+/// The range of `phase`'s items drawn in `pass`, since items are sorted by pass first.
+fn pass_range(phase: &SortedRenderPhase<OffscreenParticles>, pass: BufferPass) -> Range<usize> {
+	phase.items.partition_point(|item| item.pass < pass)
+		..phase.items.partition_point(|item| item.pass <= pass)
+}
+
+fn extract_offscreen_phases(
+	mut phases: ResMut<ViewSortedRenderPhases<OffscreenParticles>>,
+	cameras: Extract<Query<(Entity, &Camera), With<Camera3d>>>,
+	mut live_entities: Local<EntityHashSet>,
+) {
+	live_entities.clear();
+	for (entity, camera) in &cameras {
+		if !camera.is_active {
+			continue;
+		}
+		phases.insert_or_clear(entity);
+		live_entities.insert(entity);
+	}
+	phases.retain(|entity, _| live_entities.contains(entity));
+}
+
+/// An offscreen color target, with the single-sampled texture it resolves to when the
+/// view is multisampled, so that it can share the view's depth texture.
+struct OffscreenTarget {
+	texture: CachedTexture,
+	resolve: Option<CachedTexture>,
+}
+
+impl OffscreenTarget {
+	fn new(
+		cache: &mut TextureCache,
+		device: &RenderDevice,
+		label: &'static str,
+		format: TextureFormat,
+		size: UVec2,
+		samples: u32,
+	) -> Self {
+		let mut descriptor = TextureDescriptor {
+			label: Some(label),
+			size: Extent3d {
+				width: size.x,
+				height: size.y,
+				depth_or_array_layers: 1,
+			},
+			mip_level_count: 1,
+			sample_count: 1,
+			dimension: TextureDimension::D2,
+			format,
+			usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+			view_formats: &[],
+		};
+		let resolve = (samples > 1).then(|| cache.get(device, descriptor.clone()));
+		if samples > 1 {
+			descriptor.sample_count = samples;
+			descriptor.usage = TextureUsages::RENDER_ATTACHMENT;
+		}
+		Self {
+			texture: cache.get(device, descriptor),
+			resolve,
+		}
+	}
+
+	fn attachment(&self, clear: LinearRgba) -> RenderPassColorAttachment<'_> {
+		RenderPassColorAttachment {
+			view: &self.texture.default_view,
+			resolve_target: self.resolve.as_ref().map(|resolve| &*resolve.default_view),
+			ops: Operations {
+				load: LoadOp::Clear(clear.into()),
+				store: StoreOp::Store,
+			},
+		}
+	}
+
+	/// The view the composite pass reads.
+	fn sampled(&self) -> &TextureView {
+		&self.resolve.as_ref().unwrap_or(&self.texture).default_view
+	}
+}
+
+/// The accumulation and revealage targets of [BufferPass::WeightedOit] for one view.
+#[derive(Component)]
+struct OitTargets {
+	accum: OffscreenTarget,
+	revealage: OffscreenTarget,
+}
+
+/// The targets of [BufferPass::HalfResolution] for one view, at half the size of the view's
+/// target, rounded up.
+#[derive(Component)]
+struct HalfResolutionTargets {
+	color: CachedTexture,
+	/// The farthest depth of each 2x2 block of the depth prepass, so that opaque meshes
+	/// occlude particles at half resolution too.
+	depth: CachedTexture,
+}
+
+fn prepare_offscreen_targets(
+	mut cmds: Commands,
+	mut cache: ResMut<TextureCache>,
+	device: Res<RenderDevice>,
+	msaa: Res<Msaa>,
+	phases: Res<ViewSortedRenderPhases<OffscreenParticles>>,
+	views: Query<(Entity, &ExtractedCamera, Has<DepthPrepass>)>,
+) {
+	for (entity, camera, prepass) in &views {
+		let Some(size) = camera.physical_target_size else {
+			continue;
+		};
+		let Some(phase) = phases.get(&entity) else {
+			continue;
+		};
+		let mut view = cmds.entity(entity);
+		if !pass_range(phase, BufferPass::WeightedOit).is_empty() {
+			let samples = msaa.samples();
+			view.insert(OitTargets {
+				accum: OffscreenTarget::new(
+					&mut cache,
+					&device,
+					"particle_oit_accum",
+					OIT_ACCUM_FORMAT,
+					size,
+					samples,
+				),
+				revealage: OffscreenTarget::new(
+					&mut cache,
+					&device,
+					"particle_oit_revealage",
+					OIT_REVEALAGE_FORMAT,
+					size,
+					samples,
+				),
+			});
+		}
+		if prepass && !pass_range(phase, BufferPass::HalfResolution).is_empty() {
+			let half_size = (size + 1) / 2;
+			let mut descriptor = |label, format| {
+				cache.get(
+					&device,
+					TextureDescriptor {
+						label: Some(label),
+						size: Extent3d {
+							width: half_size.x,
+							height: half_size.y,
+							depth_or_array_layers: 1,
+						},
+						mip_level_count: 1,
+						sample_count: 1,
+						dimension: TextureDimension::D2,
+						format,
+						usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+						view_formats: &[],
+					},
+				)
+			};
+			view.insert(HalfResolutionTargets {
+				color: descriptor("particle_half_resolution_color", HALF_RESOLUTION_FORMAT),
+				depth: descriptor("particle_half_resolution_depth", CORE_3D_DEPTH_FORMAT),
+			});
+		}
+	}
+}
+
+/// A fullscreen pipeline and its bind group for one view.
+struct Fullscreen {
+	pipeline: CachedRenderPipelineId,
+	bind_group: BindGroup,
+}
+
+#[derive(Component)]
+struct OitComposite(Fullscreen);
+
+#[derive(Component)]
+struct HalfResolutionComposite {
+	downsample: Fullscreen,
+	composite: Fullscreen,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn prepare_offscreen_composites(
+	mut cmds: Commands,
+	device: Res<RenderDevice>,
+	pipeline: Res<CompositePipeline>,
+	mut pipelines: ResMut<SpecializedRenderPipelines<CompositePipeline>>,
+	pipeline_cache: Res<PipelineCache>,
+	msaa: Res<Msaa>,
+	oit_views: Query<(Entity, &ViewTarget, &OitTargets)>,
+	half_resolution_views: Query<(
+		Entity,
+		&ViewTarget,
+		&ViewPrepassTextures,
+		&HalfResolutionTargets,
+	)>,
+) {
+	let samples = msaa.samples();
+	let multisampled = (samples > 1) as usize;
+	for (entity, target, targets) in &oit_views {
+		let key = CompositeKey {
+			stage: CompositeStage::Oit,
+			format: target.main_texture_format(),
+			samples,
+		};
+		cmds.entity(entity).insert(OitComposite(Fullscreen {
+			pipeline: pipelines.specialize(&pipeline_cache, &pipeline, key),
+			bind_group: device.create_bind_group(
+				"particle_oit_composite_bind_group",
+				&pipeline.oit_layout,
+				&BindGroupEntries::sequential((
+					targets.accum.sampled(),
+					targets.revealage.sampled(),
+				)),
+			),
+		}));
+	}
+	for (entity, target, prepass, targets) in &half_resolution_views {
+		let Some(prepass_depth) = prepass.depth_view() else {
+			continue;
+		};
+		let downsample_key = CompositeKey {
+			stage: CompositeStage::DownsampleDepth,
+			format: CORE_3D_DEPTH_FORMAT,
+			samples,
+		};
+		let composite_key = CompositeKey {
+			stage: CompositeStage::HalfResolution,
+			format: target.main_texture_format(),
+			samples,
+		};
+		cmds.entity(entity).insert(HalfResolutionComposite {
+			downsample: Fullscreen {
+				pipeline: pipelines.specialize(&pipeline_cache, &pipeline, downsample_key),
+				bind_group: device.create_bind_group(
+					"particle_downsample_depth_bind_group",
+					&pipeline.downsample_layouts[multisampled],
+					&BindGroupEntries::single(prepass_depth),
+				),
+			},
+			composite: Fullscreen {
+				pipeline: pipelines.specialize(&pipeline_cache, &pipeline, composite_key),
+				bind_group: device.create_bind_group(
+					"particle_half_resolution_composite_bind_group",
+					&pipeline.half_resolution_layouts[multisampled],
+					&BindGroupEntries::sequential((
+						prepass_depth,
+						&targets.color.default_view,
+						&targets.depth.default_view,
+					)),
+				),
+			},
+		});
+	}
+}
+
+#[derive(Resource)]
+struct CompositePipeline {
+	oit_layout: BindGroupLayout,
+	/// Reading the depth prepass, if it is single-sampled and multisampled respectively.
+	downsample_layouts: [BindGroupLayout; 2],
+	half_resolution_layouts: [BindGroupLayout; 2],
+}
+
+impl FromWorld for CompositePipeline {
+	fn from_world(world: &mut World) -> Self {
+		let device = world.resource::<RenderDevice>();
+		// Loaded, not sampled, so the targets needn't be filterable. Depth textures are read
+		// as floats too, since `textureLoad` of a depth texture is unsupported on WebGL2.
+		let sample_type = TextureSampleType::Float { filterable: false };
+		let texture = texture_2d(sample_type);
+		let prepass_depth = [texture, texture_2d_multisampled(sample_type)];
+		let stages = ShaderStages::FRAGMENT;
+		Self {
+			oit_layout: device.create_bind_group_layout(
+				"particle_oit_composite_layout",
+				&BindGroupLayoutEntries::sequential(stages, (texture, texture)),
+			),
+			downsample_layouts: prepass_depth.map(|depth| {
+				device.create_bind_group_layout(
+					"particle_downsample_depth_layout",
+					&BindGroupLayoutEntries::single(stages, depth),
+				)
+			}),
+			half_resolution_layouts: prepass_depth.map(|depth| {
+				device.create_bind_group_layout(
+					"particle_half_resolution_composite_layout",
+					&BindGroupLayoutEntries::sequential(stages, (depth, texture, texture)),
+				)
+			}),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum CompositeStage {
+	Oit,
+	/// Writes the [HalfResolutionTargets] depth from the depth prepass.
+	DownsampleDepth,
+	HalfResolution,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct CompositeKey {
+	stage: CompositeStage,
+	/// Format of the texture written.
+	format: TextureFormat,
+	/// Sample count of the view.
+	samples: u32,
+}
+
+impl SpecializedRenderPipeline for CompositePipeline {
+	type Key = CompositeKey;
+
+	fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+		let multisampled = key.samples > 1;
+		let mut shader_defs = Vec::new();
+		if multisampled {
+			shader_defs.push("MULTISAMPLED".into());
+		}
+		let color_target = |blend| {
+			vec![Some(ColorTargetState {
+				format: key.format,
+				blend: Some(blend),
+				write_mask: ColorWrites::ALL,
+			})]
+		};
+		let (label, layout, entry_point, targets, depth_stencil, samples) = match key.stage {
+			CompositeStage::Oit => {
+				shader_defs.push("OIT".into());
+				(
+					"particle_oit_composite_pipeline",
+					&self.oit_layout,
+					"composite_oit",
+					color_target(BlendState::ALPHA_BLENDING),
+					None,
+					key.samples,
+				)
+			}
+			CompositeStage::DownsampleDepth => {
+				shader_defs.push("DOWNSAMPLE_DEPTH".into());
+				(
+					"particle_downsample_depth_pipeline",
+					&self.downsample_layouts[multisampled as usize],
+					"downsample_depth",
+					Vec::new(),
+					Some(DepthStencilState {
+						format: key.format,
+						depth_write_enabled: true,
+						depth_compare: CompareFunction::Always,
+						stencil: default(),
+						bias: default(),
+					}),
+					1,
+				)
+			}
+			CompositeStage::HalfResolution => {
+				shader_defs.push("HALF_RESOLUTION".into());
+				(
+					"particle_half_resolution_composite_pipeline",
+					&self.half_resolution_layouts[multisampled as usize],
+					"composite_half_resolution",
+					color_target(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+					None,
+					key.samples,
+				)
+			}
+		};
+		RenderPipelineDescriptor {
+			label: Some(label.into()),
+			layout: vec![layout.clone()],
+			push_constant_ranges: Vec::new(),
+			vertex: fullscreen_shader_vertex_state(),
+			fragment: Some(FragmentState {
+				shader: PARTICLE_COMPOSITE_SHADER_HANDLE,
+				shader_defs,
+				entry_point: entry_point.into(),
+				targets,
+			}),
+			primitive: default(),
+			depth_stencil,
+			multisample: MultisampleState {
+				count: samples,
+				..default()
+			},
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, RenderLabel)]
+struct OffscreenParticlesLabel;
+
+/// Draws the [OffscreenParticles] of each view, after the main transparent pass.
+#[derive(Default)]
+struct OffscreenParticlesNode;
+
+impl ViewNode for OffscreenParticlesNode {
+	type ViewQuery = (
+		&'static ExtractedCamera,
+		&'static ViewTarget,
+		&'static ViewDepthTexture,
+		Option<(&'static OitTargets, &'static OitComposite)>,
+		Option<(
+			&'static HalfResolutionTargets,
+			&'static HalfResolutionComposite,
+		)>,
+	);
+
+	fn run(
+		&self,
+		graph: &mut RenderGraphContext,
+		render_context: &mut RenderContext,
+		(camera, target, depth, oit, half_resolution): QueryItem<Self::ViewQuery>,
+		world: &World,
+	) -> Result<(), NodeRunError> {
+		let view_entity = graph.view_entity();
+		let Some(phase) = world
+			.resource::<ViewSortedRenderPhases<OffscreenParticles>>()
+			.get(&view_entity)
+		else {
+			return Ok(());
+		};
+		let pipeline_cache = world.resource::<PipelineCache>();
+		let pipeline =
+			|fullscreen: &Fullscreen| pipeline_cache.get_render_pipeline(fullscreen.pipeline);
+
+		if let Some((targets, OitComposite(composite))) = oit {
+			if let Some(composite_pipeline) = pipeline(composite) {
+				{
+					let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+						label: Some("particle_oit_accumulate_pass"),
+						color_attachments: &[
+							Some(targets.accum.attachment(LinearRgba::NONE)),
+							Some(targets.revealage.attachment(LinearRgba::WHITE)),
+						],
+						// Tested against, but not written, so opaque meshes occlude particles.
+						depth_stencil_attachment: Some(depth.get_attachment(StoreOp::Store)),
+						timestamp_writes: None,
+						occlusion_query_set: None,
+					});
+					if let Some(viewport) = camera.viewport.as_ref() {
+						pass.set_camera_viewport(viewport);
+					}
+					let range = pass_range(phase, BufferPass::WeightedOit);
+					phase.render_range(&mut pass, world, view_entity, range);
+				}
+				composite_fullscreen(
+					render_context,
+					camera,
+					target,
+					composite_pipeline,
+					composite,
+				);
+			}
+		}
+
+		if let Some((targets, stages)) = half_resolution {
+			if let (Some(downsample_pipeline), Some(composite_pipeline)) =
+				(pipeline(&stages.downsample), pipeline(&stages.composite))
+			{
+				let half_viewport = HalfViewport::new(camera);
+				{
+					let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+						label: Some("particle_downsample_depth_pass"),
+						color_attachments: &[],
+						depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+							view: &targets.depth.default_view,
+							depth_ops: Some(Operations {
+								load: LoadOp::Clear(0.0),
+								store: StoreOp::Store,
+							}),
+							stencil_ops: None,
+						}),
+						timestamp_writes: None,
+						occlusion_query_set: None,
+					});
+					half_viewport.set(&mut pass);
+					pass.set_render_pipeline(downsample_pipeline);
+					pass.set_bind_group(0, &stages.downsample.bind_group, &[]);
+					pass.draw(0..3, 0..1);
+				}
+				{
+					let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+						label: Some("particle_half_resolution_pass"),
+						color_attachments: &[Some(RenderPassColorAttachment {
+							view: &targets.color.default_view,
+							resolve_target: None,
+							ops: Operations {
+								load: LoadOp::Clear(LinearRgba::NONE.into()),
+								store: StoreOp::Store,
+							},
+						})],
+						depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+							view: &targets.depth.default_view,
+							depth_ops: Some(Operations {
+								load: LoadOp::Load,
+								store: StoreOp::Store,
+							}),
+							stencil_ops: None,
+						}),
+						timestamp_writes: None,
+						occlusion_query_set: None,
+					});
+					half_viewport.set(&mut pass);
+					let range = pass_range(phase, BufferPass::HalfResolution);
+					phase.render_range(&mut pass, world, view_entity, range);
+				}
+				composite_fullscreen(
+					render_context,
+					camera,
+					target,
+					composite_pipeline,
+					&stages.composite,
+				);
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Draws `fullscreen` over the camera's viewport of `target`.
+fn composite_fullscreen(
+	render_context: &mut RenderContext,
+	camera: &ExtractedCamera,
+	target: &ViewTarget,
+	pipeline: &RenderPipeline,
+	fullscreen: &Fullscreen,
+) {
+	let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+		label: Some("particle_composite_pass"),
+		color_attachments: &[Some(target.get_color_attachment())],
+		depth_stencil_attachment: None,
+		timestamp_writes: None,
+		occlusion_query_set: None,
+	});
+	if let Some(viewport) = camera.viewport.as_ref() {
+		pass.set_camera_viewport(viewport);
+	}
+	pass.set_render_pipeline(pipeline);
+	pass.set_bind_group(0, &fullscreen.bind_group, &[]);
+	pass.draw(0..3, 0..1);
+}
+
+/// The camera's viewport, or its whole target, scaled to the [HalfResolutionTargets].
+struct HalfViewport {
+	position: Vec2,
+	size: Vec2,
+	depth: Range<f32>,
+}
+
+impl HalfViewport {
+	fn new(camera: &ExtractedCamera) -> Self {
+		let (position, size, depth) = match &camera.viewport {
+			Some(viewport) => (
+				viewport.physical_position,
+				viewport.physical_size,
+				viewport.depth.clone(),
+			),
+			None => (
+				UVec2::ZERO,
+				camera.physical_target_size.unwrap_or_default(),
+				0.0..1.0,
+			),
+		};
+		Self {
+			position: position.as_vec2() / 2.0,
+			size: size.as_vec2() / 2.0,
+			depth,
+		}
+	}
+
+	fn set(&self, pass: &mut TrackedRenderPass) {
+		pass.set_viewport(
+			self.position.x,
+			self.position.y,
+			self.size.x,
+			self.size.y,
+			self.depth.start,
+			self.depth.end,
+		);
+	}
+}
@@ -0,0 +1,638 @@
+use std::ops::Range;
+
+use bevy::{
+	asset::load_internal_asset,
+	core_pipeline::{
+		core_3d::Transparent3d,
+		prepass::{DeferredPrepass, DepthPrepass, MotionVectorPrepass, NormalPrepass},
+	},
+	ecs::{
+		entity::EntityHashMap,
+		query::QueryItem,
+		system::{lifetimeless::*, SystemParamItem},
+	},
+	math::FloatOrd,
+	pbr::{
+		MeshPipeline, MeshPipelineKey, RenderMeshInstances, SetMeshBindGroup, SetMeshViewBindGroup,
+	},
+	prelude::*,
+	render::{
+		extract_component::{ExtractComponent, ExtractComponentPlugin},
+		mesh::{GpuBufferInfo, GpuMesh, MeshVertexBufferLayoutRef},
+		render_asset::RenderAssets,
+		render_phase::{
+			AddRenderCommand, DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand,
+			RenderCommandResult, SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
+		},
+		render_resource::*,
+		renderer::{RenderAdapter, RenderDevice, RenderQueue},
+		view::{
+			ExtractedView, NoFrustumCulling, RenderLayers, VisibilitySystems, VisibleEntities,
+			WithMesh,
+		},
+		Render, RenderApp, RenderSet,
+	},
+};
+use bytemuck::{Pod, Zeroable};
+use wgpu_types::DownlevelFlags;
+
+use super::{
+	offscreen::{
+		OffscreenParticles, OffscreenParticlesPlugin, HALF_RESOLUTION_FORMAT, OIT_ACCUM_FORMAT,
+		OIT_REVEALAGE_FORMAT,
+	},
+	BufferPass, ParticleBuffer,
+};
+use crate::{color::SpewerTint, math::lifetime_progress, Spewer};
+
+pub const PARTICLE_BUFFER_SHADER_HANDLE: Handle<Shader> =
+	Handle::weak_from_u128(168521377419905307988880133235019283914);
+/// `sond_bevy_particles::buffer`, for a [ParticleBufferShader] to import.
+pub const PARTICLE_BUFFER_TYPES_SHADER_HANDLE: Handle<Shader> =
+	Handle::weak_from_u128(63177052416315936712843909712462380158);
+
+/// Draws every [ParticleBuffer] that has a mesh as camera-facing, alpha-blended instances
+/// of that mesh's XY plane, for each camera that sees the buffer, e.g. with matching
+/// `RenderLayers`.
+pub struct ParticleBufferRenderPlugin;
+
+impl Plugin for ParticleBufferRenderPlugin {
+	fn build(&self, app: &mut App) {
+		load_internal_asset!(
+			app,
+			PARTICLE_BUFFER_TYPES_SHADER_HANDLE,
+			"../buffer_types.wgsl",
+			Shader::from_wgsl
+		);
+		load_internal_asset!(
+			app,
+			PARTICLE_BUFFER_SHADER_HANDLE,
+			"../buffer.wgsl",
+			Shader::from_wgsl
+		);
+		app.add_plugins((
+			ExtractComponentPlugin::<ParticleBuffer>::default(),
+			OffscreenParticlesPlugin,
+		))
+		.add_systems(
+			PostUpdate,
+			inherit_spewer_layers.before(VisibilitySystems::CheckVisibility),
+		)
+		.register_type::<ParticleBufferShader>();
+		let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+			return;
+		};
+		render_app
+			.add_render_command::<Transparent3d, DrawParticleBuffer>()
+			.add_render_command::<OffscreenParticles, DrawParticleBuffer>()
+			.init_resource::<SpecializedMeshPipelines<ParticleBufferPipeline>>()
+			.init_resource::<ParticleInstanceBuffer>()
+			.add_systems(
+				Render,
+				(
+					queue_particle_buffers.in_set(RenderSet::QueueMeshes),
+					prepare_instance_buffers.in_set(RenderSet::PrepareResources),
+				),
+			);
+	}
+
+	fn finish(&self, app: &mut App) {
+		if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+			render_app.init_resource::<ParticleBufferPipeline>();
+		}
+	}
+}
+
+/// A [ParticleBuffer] with the mesh drawn for each of its particles, usually a `Rectangle`.
+#[derive(Bundle)]
+pub struct ParticleBufferBundle {
+	pub buffer: ParticleBuffer,
+	pub mesh: Handle<Mesh>,
+	pub spatial: SpatialBundle,
+	/// Particles don't stay within the bounds of the mesh, so the whole buffer must not be
+	/// culled based on them.
+	pub no_frustum_culling: NoFrustumCulling,
+}
+
+impl ParticleBufferBundle {
+	pub fn new(buffer: ParticleBuffer, mesh: Handle<Mesh>) -> Self {
+		Self {
+			buffer,
+			mesh,
+			spatial: default(),
+			no_frustum_culling: NoFrustumCulling,
+		}
+	}
+}
+
+/// Shades the particles of the [ParticleBuffer] on the same entity with this fragment
+/// shader instead of their flat color, e.g. to react to their
+/// [attributes](ParticleBuffer::attributes).
+///
+/// The shader takes the `VertexOutput` imported from `sond_bevy_particles::buffer`, which
+/// carries each particle's color, lifetime progress, seed, and attributes. It needs the
+/// entry point of the buffer's [pass](ParticleBuffer::pass): `fragment` for
+/// [BufferPass::Transparent], `fragment_oit` for [BufferPass::WeightedOit], and
+/// `fragment_premultiplied` for [BufferPass::HalfResolution], which falls back to `fragment`
+/// for cameras without a depth prepass.
+#[derive(Debug, Clone, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
+pub struct ParticleBufferShader(pub Handle<Shader>);
+
+/// Gives each [ParticleBuffer] that is a child of a [Spewer] the spewer's `RenderLayers`,
+/// like the spewer's own particles, so the same cameras see both.
+fn inherit_spewer_layers(
+	mut cmds: Commands,
+	buffers: Query<(Entity, &Parent, Option<&RenderLayers>), With<ParticleBuffer>>,
+	spewers: Query<&RenderLayers, With<Spewer>>,
+) {
+	for (id, parent, layers) in &buffers {
+		let Ok(spewer_layers) = spewers.get(parent.get()) else {
+			continue;
+		};
+		if layers != Some(spewer_layers) {
+			cmds.entity(id).insert(spewer_layers.clone());
+		}
+	}
+}
+
+/// Per-instance vertex data for one particle.
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+pub struct ParticleInstance {
+	/// World-space position.
+	pub position: Vec3,
+	/// Width and height in world units.
+	pub size: Vec2,
+	pub color: [f32; 4],
+	/// Fraction of its lifetime the particle has lived, from `0.0` to `1.0`.
+	pub progress: f32,
+	pub seed: u32,
+	pub attributes: [f32; 4],
+}
+
+/// Render-world copy of a [ParticleBuffer]'s particles.
+#[derive(Component, Deref)]
+pub struct ParticleInstances {
+	#[deref]
+	instances: Vec<ParticleInstance>,
+	/// Copied from [ParticleBuffer::depth_sort].
+	pub depth_sort: bool,
+	/// Copied from [ParticleBuffer::pass].
+	pub pass: BufferPass,
+	/// Copied from the buffer's [ParticleBufferShader].
+	pub shader: Option<Handle<Shader>>,
+}
+
+impl ExtractComponent for ParticleBuffer {
+	type QueryData = (
+		&'static ParticleBuffer,
+		Option<&'static SpewerTint>,
+		Option<&'static ParticleBufferShader>,
+	);
+	type QueryFilter = ();
+	type Out = ParticleInstances;
+
+	fn extract_component(
+		(buffer, tint, shader): QueryItem<'_, Self::QueryData>,
+	) -> Option<ParticleInstances> {
+		if buffer.is_empty() {
+			return None;
+		}
+		let lifetime = buffer.lifetime.as_secs_f32();
+		let instances = buffer
+			.positions
+			.iter()
+			.enumerate()
+			.map(|(i, &position)| ParticleInstance {
+				position,
+				size: buffer.size_of(i),
+				color: tint
+					.map_or(buffer.color_of(i), |tint| tint.apply(buffer.color_of(i)))
+					.to_f32_array(),
+				progress: lifetime_progress(buffer.ages[i], lifetime),
+				seed: buffer.seeds[i],
+				attributes: buffer.attributes[i].to_array(),
+			})
+			.collect();
+		Some(ParticleInstances {
+			instances,
+			depth_sort: buffer.depth_sort,
+			pass: buffer.pass,
+			shader: shader.map(|shader| shader.0.clone()),
+		})
+	}
+}
+
+#[allow(clippy::too_many_arguments)]
+fn queue_particle_buffers(
+	draw_functions: Res<DrawFunctions<Transparent3d>>,
+	offscreen_draw_functions: Res<DrawFunctions<OffscreenParticles>>,
+	pipeline: Res<ParticleBufferPipeline>,
+	msaa: Res<Msaa>,
+	mut pipelines: ResMut<SpecializedMeshPipelines<ParticleBufferPipeline>>,
+	pipeline_cache: Res<PipelineCache>,
+	meshes: Res<RenderAssets<GpuMesh>>,
+	render_mesh_instances: Res<RenderMeshInstances>,
+	buffers: Query<&ParticleInstances>,
+	mut phases: ResMut<ViewSortedRenderPhases<Transparent3d>>,
+	mut offscreen_phases: ResMut<ViewSortedRenderPhases<OffscreenParticles>>,
+	views: Query<(
+		Entity,
+		&ExtractedView,
+		&VisibleEntities,
+		Has<DepthPrepass>,
+		Has<NormalPrepass>,
+		Has<MotionVectorPrepass>,
+		Has<DeferredPrepass>,
+	)>,
+) {
+	let draw_function = draw_functions.read().id::<DrawParticleBuffer>();
+	let offscreen_draw_function = offscreen_draw_functions.read().id::<DrawParticleBuffer>();
+
+	for (
+		view_entity,
+		view,
+		visible_entities,
+		depth_prepass,
+		normal_prepass,
+		motion_vector_prepass,
+		deferred,
+	) in &views
+	{
+		let (Some(phase), Some(offscreen_phase)) = (
+			phases.get_mut(&view_entity),
+			offscreen_phases.get_mut(&view_entity),
+		) else {
+			continue;
+		};
+
+		// The view bind group layout depends on which prepass textures the view has.
+		let mut view_key = MeshPipelineKey::from_msaa_samples(msaa.samples())
+			| MeshPipelineKey::from_hdr(view.hdr)
+			| MeshPipelineKey::BLEND_ALPHA;
+		if depth_prepass {
+			view_key |= MeshPipelineKey::DEPTH_PREPASS;
+		}
+		if normal_prepass {
+			view_key |= MeshPipelineKey::NORMAL_PREPASS;
+		}
+		if motion_vector_prepass {
+			view_key |= MeshPipelineKey::MOTION_VECTOR_PREPASS;
+		}
+		if deferred {
+			view_key |= MeshPipelineKey::DEFERRED_PREPASS;
+		}
+
+		let rangefinder = view.rangefinder3d();
+		// Only the buffers this view sees, e.g. with matching `RenderLayers`.
+		for &entity in visible_entities.iter::<WithMesh>() {
+			let Ok(instances) = buffers.get(entity) else {
+				continue;
+			};
+			let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(entity) else {
+				continue;
+			};
+			let Some(mesh) = meshes.get(mesh_instance.mesh_asset_id) else {
+				continue;
+			};
+			let pass = pipeline.pass_for(instances.pass, depth_prepass, msaa.samples());
+			let key = ParticleBufferPipelineKey {
+				mesh: view_key
+					| MeshPipelineKey::from_primitive_topology(mesh.primitive_topology()),
+				pass,
+				shader: instances.shader.clone(),
+			};
+			let pipeline = match pipelines.specialize(&pipeline_cache, &pipeline, key, &mesh.layout)
+			{
+				Ok(pipeline) => pipeline,
+				Err(e) => {
+					error!("{e}");
+					continue;
+				}
+			};
+			let distance = rangefinder.distance_translation(&mesh_instance.translation);
+			match pass {
+				BufferPass::Transparent => phase.add(Transparent3d {
+					entity,
+					pipeline,
+					draw_function,
+					distance,
+					batch_range: 0..1,
+					extra_index: PhaseItemExtraIndex::NONE,
+				}),
+				pass => offscreen_phase.add(OffscreenParticles {
+					pass,
+					entity,
+					pipeline,
+					draw_function: offscreen_draw_function,
+					distance,
+					batch_range: 0..1,
+					extra_index: PhaseItemExtraIndex::NONE,
+				}),
+			}
+		}
+	}
+}
+
+/// The instances of every [ParticleBuffer] in one vertex buffer, rewritten each frame, so
+/// that many buffers don't each create their own GPU buffer every frame. It only grows
+/// when there are more instances than it has room for.
+///
+/// Growing and uploading are both done on the frame that needs them: nothing is allocated
+/// ahead of time or spread over several frames, so a large enough burst can still hitch
+/// the frame it first appears on.
+#[derive(Resource)]
+struct ParticleInstanceBuffer(RawBufferVec<ParticleInstance>);
+
+impl Default for ParticleInstanceBuffer {
+	fn default() -> Self {
+		let mut buffer = RawBufferVec::new(BufferUsages::VERTEX);
+		buffer.set_label(Some("particle buffer instances"));
+		Self(buffer)
+	}
+}
+
+/// Where the instances of a [ParticleBuffer] are in the [ParticleInstanceBuffer], shared
+/// by every view unless they are sorted by depth, in which case each view has its own.
+#[derive(Component)]
+enum InstanceRanges {
+	Shared(Range<u32>),
+	PerView(EntityHashMap<Range<u32>>),
+}
+
+impl InstanceRanges {
+	fn get(&self, view: Entity) -> Option<Range<u32>> {
+		match self {
+			Self::Shared(range) => Some(range.clone()),
+			Self::PerView(ranges) => ranges.get(&view).cloned(),
+		}
+	}
+}
+
+fn prepare_instance_buffers(
+	mut cmds: Commands,
+	q: Query<(Entity, &ParticleInstances)>,
+	views: Query<(Entity, &ExtractedView, &VisibleEntities)>,
+	phases: Res<ViewSortedRenderPhases<Transparent3d>>,
+	mut instance_buffer: ResMut<ParticleInstanceBuffer>,
+	render_device: Res<RenderDevice>,
+	render_queue: Res<RenderQueue>,
+) {
+	// Shadow views have no transparent phase, and don't draw particle buffers.
+	let mut seen_by = EntityHashMap::<Vec<(Entity, &ExtractedView)>>::default();
+	for (view_entity, view, visible_entities) in &views {
+		if !phases.contains_key(&view_entity) {
+			continue;
+		}
+		for &entity in visible_entities.iter::<WithMesh>() {
+			if q.contains(entity) {
+				seen_by.entry(entity).or_default().push((view_entity, view));
+			}
+		}
+	}
+	let buffer = &mut instance_buffer.0;
+	buffer.clear();
+	for (entity, instances) in &q {
+		let Some(views) = seen_by.get(&entity) else {
+			continue;
+		};
+		let ranges = if instances.depth_sort {
+			let mut ranges = EntityHashMap::default();
+			for &(view_entity, view) in views {
+				let range = push_instances(buffer, instances);
+				let rangefinder = view.rangefinder3d();
+				// Back to front, like the transparent phase itself.
+				buffer.values_mut()[range.start as usize..range.end as usize].sort_unstable_by_key(
+					|instance| FloatOrd(rangefinder.distance_translation(&instance.position)),
+				);
+				ranges.insert(view_entity, range);
+			}
+			InstanceRanges::PerView(ranges)
+		} else {
+			InstanceRanges::Shared(push_instances(buffer, instances))
+		};
+		cmds.entity(entity).insert(ranges);
+	}
+	// Grow to the next power of two, rather than to the exact size like `write_buffer`, so
+	// that effects ramping up don't recreate the buffer every frame. This only makes
+	// reallocations rarer; each one still happens synchronously here.
+	if buffer.len() > buffer.capacity() {
+		buffer.reserve(buffer.len().next_power_of_two(), &render_device);
+	}
+	buffer.write_buffer(&render_device, &render_queue);
+}
+
+/// Appends `instances` to `buffer`, returning their range in it.
+fn push_instances(
+	buffer: &mut RawBufferVec<ParticleInstance>,
+	instances: &[ParticleInstance],
+) -> Range<u32> {
+	let start = buffer.len() as u32;
+	buffer.extend(instances.iter().copied());
+	start..buffer.len() as u32
+}
+
+#[derive(Resource)]
+struct ParticleBufferPipeline {
+	mesh_pipeline: MeshPipeline,
+	/// Whether the device can blend each color target differently, which
+	/// [BufferPass::WeightedOit] needs. WebGL2 can't.
+	independent_blend: bool,
+	/// Whether shaders can read multisampled depth, which [BufferPass::HalfResolution]
+	/// needs with MSAA. Devices without storage buffers, like WebGL2, are taken not to.
+	multisampled_depth: bool,
+}
+
+impl FromWorld for ParticleBufferPipeline {
+	fn from_world(world: &mut World) -> Self {
+		let device = world.resource::<RenderDevice>();
+		let adapter = world.resource::<RenderAdapter>();
+		let downlevel = adapter.get_downlevel_capabilities();
+		Self {
+			mesh_pipeline: world.resource::<MeshPipeline>().clone(),
+			independent_blend: downlevel.flags.contains(DownlevelFlags::INDEPENDENT_BLEND),
+			multisampled_depth: device.limits().max_storage_buffers_per_shader_stage > 0,
+		}
+	}
+}
+
+impl ParticleBufferPipeline {
+	/// The pass a buffer asking for `pass` is drawn in, falling back to
+	/// [BufferPass::Transparent] where this device or the view can't draw `pass`.
+	fn pass_for(&self, pass: BufferPass, depth_prepass: bool, samples: u32) -> BufferPass {
+		match pass {
+			BufferPass::WeightedOit if !self.independent_blend => BufferPass::Transparent,
+			BufferPass::HalfResolution if !depth_prepass => BufferPass::Transparent,
+			BufferPass::HalfResolution if samples > 1 && !self.multisampled_depth => {
+				BufferPass::Transparent
+			}
+			pass => pass,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ParticleBufferPipelineKey {
+	mesh: MeshPipelineKey,
+	pass: BufferPass,
+	shader: Option<Handle<Shader>>,
+}
+
+impl SpecializedMeshPipeline for ParticleBufferPipeline {
+	type Key = ParticleBufferPipelineKey;
+
+	fn specialize(
+		&self,
+		key: Self::Key,
+		layout: &MeshVertexBufferLayoutRef,
+	) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+		let mut descriptor = self.mesh_pipeline.specialize(key.mesh, layout)?;
+		descriptor.label = Some("particle_buffer_pipeline".into());
+		descriptor.vertex.shader = PARTICLE_BUFFER_SHADER_HANDLE;
+		// The fields of `ParticleInstance`, in order.
+		let formats = [
+			VertexFormat::Float32x3,
+			VertexFormat::Float32x2,
+			VertexFormat::Float32x4,
+			VertexFormat::Float32,
+			VertexFormat::Uint32,
+			VertexFormat::Float32x4,
+		];
+		let mut offset = 0;
+		let attributes = formats
+			.into_iter()
+			.zip(8..)
+			.map(|(format, shader_location)| {
+				let attribute = VertexAttribute {
+					format,
+					offset,
+					shader_location,
+				};
+				offset += format.size();
+				attribute
+			})
+			.collect();
+		descriptor.vertex.buffers.push(VertexBufferLayout {
+			array_stride: std::mem::size_of::<ParticleInstance>() as u64,
+			step_mode: VertexStepMode::Instance,
+			attributes,
+		});
+		if let Some(fragment) = descriptor.fragment.as_mut() {
+			fragment.shader = key.shader.clone().unwrap_or(PARTICLE_BUFFER_SHADER_HANDLE);
+			match key.pass {
+				BufferPass::Transparent => {}
+				BufferPass::WeightedOit => {
+					let sum = BlendComponent {
+						src_factor: BlendFactor::One,
+						dst_factor: BlendFactor::One,
+						operation: BlendOperation::Add,
+					};
+					// Multiplies the revealage by `1 - alpha`.
+					let product = BlendComponent {
+						src_factor: BlendFactor::Zero,
+						dst_factor: BlendFactor::OneMinusSrc,
+						operation: BlendOperation::Add,
+					};
+					fragment.entry_point = "fragment_oit".into();
+					fragment.targets = vec![
+						Some(ColorTargetState {
+							format: OIT_ACCUM_FORMAT,
+							blend: Some(BlendState {
+								color: sum,
+								alpha: sum,
+							}),
+							write_mask: ColorWrites::ALL,
+						}),
+						Some(ColorTargetState {
+							format: OIT_REVEALAGE_FORMAT,
+							blend: Some(BlendState {
+								color: product,
+								alpha: product,
+							}),
+							write_mask: ColorWrites::ALL,
+						}),
+					];
+				}
+				BufferPass::HalfResolution => {
+					fragment.entry_point = "fragment_premultiplied".into();
+					fragment.targets = vec![Some(ColorTargetState {
+						format: HALF_RESOLUTION_FORMAT,
+						blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+						write_mask: ColorWrites::ALL,
+					})];
+					// The half-resolution target is single-sampled, but the view bind group
+					// still has the multisampled prepass textures of the view.
+					descriptor.multisample.count = 1;
+				}
+			}
+		}
+		Ok(descriptor)
+	}
+}
+
+type DrawParticleBuffer = (
+	SetItemPipeline,
+	SetMeshViewBindGroup<0>,
+	SetMeshBindGroup<1>,
+	DrawInstances,
+);
+
+struct DrawInstances;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawInstances {
+	type Param = (
+		SRes<RenderAssets<GpuMesh>>,
+		SRes<RenderMeshInstances>,
+		SRes<ParticleInstanceBuffer>,
+	);
+	type ViewQuery = Entity;
+	type ItemQuery = Read<InstanceRanges>;
+
+	#[inline]
+	fn render<'w>(
+		item: &P,
+		view: Entity,
+		ranges: Option<&'w InstanceRanges>,
+		(meshes, render_mesh_instances, instance_buffer): SystemParamItem<'w, '_, Self::Param>,
+		pass: &mut TrackedRenderPass<'w>,
+	) -> RenderCommandResult {
+		let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(item.entity())
+		else {
+			return RenderCommandResult::Failure;
+		};
+		let Some(gpu_mesh) = meshes.into_inner().get(mesh_instance.mesh_asset_id) else {
+			return RenderCommandResult::Failure;
+		};
+		let Some(range) = ranges.and_then(|ranges| ranges.get(view)) else {
+			return RenderCommandResult::Failure;
+		};
+		let Some(instance_buffer) = instance_buffer.into_inner().0.buffer() else {
+			return RenderCommandResult::Failure;
+		};
+
+		pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+		// Offsetting the buffer instead of the instances works without base instance support,
+		// e.g. on WebGL2.
+		let stride = std::mem::size_of::<ParticleInstance>() as u64;
+		pass.set_vertex_buffer(
+			1,
+			instance_buffer.slice(range.start as u64 * stride..range.end as u64 * stride),
+		);
+		let instances = 0..range.len() as u32;
+		match &gpu_mesh.buffer_info {
+			GpuBufferInfo::Indexed {
+				buffer,
+				index_format,
+				count,
+			} => {
+				pass.set_index_buffer(buffer.slice(..), 0, *index_format);
+				pass.draw_indexed(0..*count, 0, instances);
+			}
+			GpuBufferInfo::NonIndexed => {
+				pass.draw(0..gpu_mesh.vertex_count, instances);
+			}
+		}
+		RenderCommandResult::Success
+	}
+}
@@ -0,0 +1,104 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+#ifdef OIT
+
+// Resolves the weighted blended order-independent transparency targets into a single
+// color, blended over the view with the combined coverage of every particle.
+@group(0) @binding(0) var accum_texture: texture_2d<f32>;
+@group(0) @binding(1) var revealage_texture: texture_2d<f32>;
+
+@fragment
+fn composite_oit(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+	let coords = vec2<i32>(in.position.xy);
+	let revealage = textureLoad(revealage_texture, coords, 0).r;
+	if revealage >= 1.0 {
+		// No particles covered this pixel.
+		discard;
+	}
+	let accum = textureLoad(accum_texture, coords, 0);
+	return vec4(accum.rgb / clamp(accum.a, 1e-4, 5e4), 1.0 - revealage);
+}
+
+#else
+
+// The depth prepass of the view, read as floats since loading from depth textures is
+// unsupported on WebGL2.
+#ifdef MULTISAMPLED
+@group(0) @binding(0) var prepass_depth: texture_multisampled_2d<f32>;
+#else
+@group(0) @binding(0) var prepass_depth: texture_2d<f32>;
+#endif
+
+fn load_prepass_depth(coords: vec2<i32>) -> f32 {
+	let max_coords = vec2<i32>(textureDimensions(prepass_depth)) - 1;
+	// The first sample or mip level, which is the same argument.
+	return textureLoad(prepass_depth, min(coords, max_coords), 0).r;
+}
+
+#ifdef DOWNSAMPLE_DEPTH
+
+// Writes the farthest depth of each 2x2 block of the prepass, in reverse-Z, so that
+// particles seen between nearer meshes aren't dropped from the half-resolution target.
+@fragment
+fn downsample_depth(in: FullscreenVertexOutput) -> @builtin(frag_depth) f32 {
+	let coords = vec2<i32>(in.position.xy) * 2;
+	return min(
+		min(load_prepass_depth(coords), load_prepass_depth(coords + vec2(1, 0))),
+		min(load_prepass_depth(coords + vec2(0, 1)), load_prepass_depth(coords + vec2(1, 1))),
+	);
+}
+
+#endif
+
+#ifdef HALF_RESOLUTION
+
+@group(0) @binding(1) var half_color: texture_2d<f32>;
+@group(0) @binding(2) var half_depth: texture_2d<f32>;
+
+// Relative depth difference above which a half-resolution texel is considered to be on
+// the other side of an edge.
+const EDGE_THRESHOLD: f32 = 0.1;
+
+// Upsamples the premultiplied half-resolution particles over the view. Texels are blended
+// bilinearly where their depths all match the view's, otherwise the texel with the closest
+// depth is used, so that particles don't bleed over the edges of opaque meshes.
+@fragment
+fn composite_half_resolution(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+	let depth = load_prepass_depth(vec2<i32>(in.position.xy));
+	// Relative to the centers of the half-resolution texels.
+	let half_position = in.position.xy * 0.5 - 0.5;
+	let base = vec2<i32>(floor(half_position));
+	let f = fract(half_position);
+	let max_coords = vec2<i32>(textureDimensions(half_color)) - 1;
+	var offsets = array<vec2<i32>, 4>(vec2(0, 0), vec2(1, 0), vec2(0, 1), vec2(1, 1));
+	var weights = array<f32, 4>(
+		(1.0 - f.x) * (1.0 - f.y),
+		f.x * (1.0 - f.y),
+		(1.0 - f.x) * f.y,
+		f.x * f.y,
+	);
+
+	var bilinear = vec4(0.0);
+	var nearest = vec4(0.0);
+	var nearest_difference = 3.4e38;
+	var max_difference = 0.0;
+	for (var i = 0; i < 4; i += 1) {
+		let coords = clamp(base + offsets[i], vec2(0), max_coords);
+		let color = textureLoad(half_color, coords, 0);
+		let difference = abs(textureLoad(half_depth, coords, 0).r - depth) / max(depth, 1e-6);
+		bilinear += color * weights[i];
+		if difference < nearest_difference {
+			nearest = color;
+			nearest_difference = difference;
+		}
+		max_difference = max(max_difference, difference);
+	}
+	if max_difference < EDGE_THRESHOLD {
+		return bilinear;
+	}
+	return nearest;
+}
+
+#endif
+
+#endif
@@ -0,0 +1,15 @@
+#define_import_path sond_bevy_particles::buffer
+
+struct VertexOutput {
+	@builtin(position) clip_position: vec4<f32>,
+	@location(0) color: vec4<f32>,
+	// Distance in front of the camera, for weighting order-independent transparency.
+	@location(1) view_depth: f32,
+	// Position on the mesh's XY plane, e.g. `-0.5..0.5` across a unit quad.
+	@location(2) local_position: vec2<f32>,
+	// Fraction of its lifetime the particle has lived.
+	@location(3) progress: f32,
+	@location(4) @interpolate(flat) seed: u32,
+	// `ParticleBuffer::attributes`.
+	@location(5) attributes: vec4<f32>,
+};
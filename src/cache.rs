@@ -0,0 +1,87 @@
+//! Assets shared by everything creating them from the same parameters.
+
+use std::hash::Hash;
+
+use bevy::{prelude::*, utils::HashMap};
+
+/// Assets created from keys of type `K`, e.g. the size of a quad mesh, so that spawning
+/// many effects which create the same mesh or material only adds it once:
+///
+/// ```ignore
+/// fn spawn_sparks(mut cache: ResMut<AssetCache<[u32; 2], Mesh>>, mut meshes: ResMut<Assets<Mesh>>) {
+///     let size = Vec2::splat(0.1);
+///     let mesh = cache.get_or_add(size.to_array().map(f32::to_bits), &mut meshes, || Rectangle::from_size(size).into());
+/// }
+/// ```
+///
+/// Only weak references are kept, so assets are still freed once nothing else uses them,
+/// and recreated by the next [AssetCache::get_or_add] for their key. Render assets whose
+/// `RenderAssetUsages` leave out the main world are removed from `Assets` once extracted,
+/// so they are recreated as well, and cached ones should keep the default usages.
+///
+/// Floats in keys should be converted to their bits, or rounded, to be hashed. Apps add the
+/// caches they use with `init_resource`.
+#[derive(Resource)]
+pub struct AssetCache<K, A: Asset> {
+	ids: HashMap<K, AssetId<A>>,
+}
+
+impl<K, A: Asset> Default for AssetCache<K, A> {
+	fn default() -> Self {
+		Self {
+			ids: HashMap::default(),
+		}
+	}
+}
+
+impl<K: Hash + Eq + Send + Sync + 'static, A: Asset> AssetCache<K, A> {
+	/// Returns the asset created for `key`, or adds the one `create` returns if there is
+	/// none or it has been freed.
+	pub fn get_or_add(
+		&mut self,
+		key: K,
+		assets: &mut Assets<A>,
+		create: impl FnOnce() -> A,
+	) -> Handle<A> {
+		if let Some(handle) = self
+			.ids
+			.get(&key)
+			.and_then(|&id| assets.get_strong_handle(id))
+		{
+			return handle;
+		}
+		let handle = assets.add(create());
+		self.ids.insert(key, handle.id());
+		handle
+	}
+
+	/// Number of keys with an asset, including ones freed since [AssetCache::clean_up] last
+	/// ran.
+	pub fn len(&self) -> usize {
+		self.ids.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ids.is_empty()
+	}
+
+	/// Forgets keys whose assets have been freed.
+	pub fn clean_up(&mut self, assets: &Assets<A>) {
+		self.ids.retain(|_, &mut id| assets.contains(id));
+	}
+
+	/// Calls [AssetCache::clean_up] whenever assets are freed.
+	pub fn clean_up_unused(
+		mut cache: ResMut<Self>,
+		mut events: EventReader<AssetEvent<A>>,
+		assets: Res<Assets<A>>,
+	) {
+		let unused = events
+			.read()
+			.filter(|event| matches!(event, AssetEvent::Unused { .. }))
+			.count();
+		if unused > 0 && !cache.is_empty() {
+			cache.clean_up(&assets);
+		}
+	}
+}
@@ -0,0 +1,506 @@
+//! Rendering an effect to a sequence of transparent PNG frames, e.g. to generate preview
+//! thumbnails for an effect library, or to compare against [GoldenImages] to catch
+//! unintended visual changes.
+
+use std::{
+	path::{Path, PathBuf},
+	sync::{mpsc, Mutex},
+};
+
+use bevy::{
+	app::AppExit,
+	core_pipeline::tonemapping::{DebandDither, Tonemapping},
+	prelude::*,
+	render::{
+		camera::RenderTarget,
+		render_asset::{RenderAssetUsages, RenderAssets},
+		render_graph::{self, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel},
+		render_resource::{
+			Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d,
+			ImageCopyBuffer, ImageDataLayout, Maintain, MapMode, TextureDimension, TextureFormat,
+			TextureUsages,
+		},
+		renderer::{RenderContext, RenderDevice, RenderQueue},
+		texture::GpuImage,
+		texture::{CompressedImageFormats, ImageSampler, ImageType},
+		Extract, Render, RenderApp, RenderSet,
+	},
+	utils::Duration,
+};
+
+use crate::time::ParticleTime;
+
+/// Renders whatever particles are in the app to `frames` PNG files in `output_dir`, named
+/// `{name}_000.png` onwards, against a transparent background, then exits the app.
+///
+/// Meant for an app of its own with no window, which only spawns the effect, so that it is
+/// captured in isolation:
+///
+/// ```ignore
+/// App::new()
+///     .add_plugins((
+///         DefaultPlugins.set(WindowPlugin {
+///             primary_window: None,
+///             exit_condition: ExitCondition::DontExit,
+///             close_when_requested: false,
+///         }),
+///         ScheduleRunnerPlugin::run_loop(Duration::ZERO),
+///         ParticlesPlugin,
+///         EffectCapturePlugin::new("sparks", "previews"),
+///     ))
+///     .add_systems(Startup, spawn_sparks)
+///     .run();
+/// ```
+///
+/// Time advances by exactly `frame_time` per frame however long rendering takes, and
+/// [ParticleTime] is paused for the first `warmup_frames` while render pipelines compile.
+/// Frames are stitched into a GIF or video by other tools, e.g. `ffmpeg`.
+///
+/// With [GoldenImages], each frame is also compared against its reference, and the app
+/// exits with an error if any differ.
+#[derive(Debug, Clone)]
+pub struct EffectCapturePlugin {
+	pub name: String,
+	pub output_dir: PathBuf,
+	pub width: u32,
+	pub height: u32,
+	pub frames: u32,
+	pub frame_time: Duration,
+	pub warmup_frames: u32,
+	pub camera: Transform,
+	pub golden: Option<GoldenImages>,
+}
+
+impl EffectCapturePlugin {
+	pub fn new(name: impl Into<String>, output_dir: impl Into<PathBuf>) -> Self {
+		Self {
+			name: name.into(),
+			output_dir: output_dir.into(),
+			width: 256,
+			height: 256,
+			frames: 60,
+			frame_time: Duration::from_secs_f64(1.0 / 30.0),
+			warmup_frames: 10,
+			camera: Transform::from_xyz(0.0, 1.5, 5.0).looking_at(Vec3::Y * 0.5, Vec3::Y),
+			golden: None,
+		}
+	}
+
+	pub fn with_size(self, width: u32, height: u32) -> Self {
+		Self {
+			width,
+			height,
+			..self
+		}
+	}
+
+	/// Captures `frames` frames, `frame_time` apart.
+	pub fn with_frames(self, frames: u32, frame_time: Duration) -> Self {
+		Self {
+			frames,
+			frame_time,
+			..self
+		}
+	}
+
+	pub fn with_camera(self, camera: Transform) -> Self {
+		Self { camera, ..self }
+	}
+
+	pub fn with_golden(self, golden: GoldenImages) -> Self {
+		Self {
+			golden: Some(golden),
+			..self
+		}
+	}
+}
+
+/// Reference frames for an [EffectCapturePlugin] to compare its captures against, named
+/// like the captured frames, e.g. to check in CI that shader or pipeline changes didn't
+/// alter how canonical effects look. Effects must be seeded to render the same each run.
+///
+/// A frame differs if more than `max_differing_pixels` of its pixels differ from the
+/// reference by more than `tolerance` in any channel, which allows for small differences
+/// between GPUs and drivers. A `{name}_000_diff.png` image highlighting the differing
+/// pixels is saved next to each differing frame.
+#[derive(Debug, Clone)]
+pub struct GoldenImages {
+	pub dir: PathBuf,
+	/// Largest difference in a channel, out of `255`, that still counts as the same.
+	pub tolerance: u8,
+	/// Fraction of pixels that may differ by more than `tolerance`.
+	pub max_differing_pixels: f32,
+	/// Overwrites the reference frames with the captured ones instead of comparing them,
+	/// after an intended visual change.
+	pub update: bool,
+}
+
+impl GoldenImages {
+	pub fn new(dir: impl Into<PathBuf>) -> Self {
+		Self {
+			dir: dir.into(),
+			tolerance: 8,
+			max_differing_pixels: 0.001,
+			update: false,
+		}
+	}
+
+	pub fn with_tolerance(self, tolerance: u8, max_differing_pixels: f32) -> Self {
+		Self {
+			tolerance,
+			max_differing_pixels,
+			..self
+		}
+	}
+
+	pub fn updating(self, update: bool) -> Self {
+		Self { update, ..self }
+	}
+
+	/// Compares the RGBA8 pixels of `frame` with those of `reference`.
+	pub fn compare(&self, frame: &[u8], reference: &[u8]) -> ImageDiff {
+		let mut diff = ImageDiff {
+			pixels: frame.len() / 4,
+			..default()
+		};
+		for (a, b) in frame.chunks_exact(4).zip(reference.chunks_exact(4)) {
+			let difference = a.iter().zip(b).map(|(a, b)| a.abs_diff(*b)).max();
+			let difference = difference.unwrap_or_default();
+			diff.max_difference = diff.max_difference.max(difference);
+			if difference > self.tolerance {
+				diff.differing_pixels += 1;
+			}
+		}
+		diff
+	}
+
+	/// Whether `diff` is within the allowed number of differing pixels.
+	pub fn accepts(&self, diff: &ImageDiff) -> bool {
+		diff.differing_pixels as f32 <= diff.pixels as f32 * self.max_differing_pixels
+	}
+}
+
+/// How much a captured frame differs from its reference. See [GoldenImages::compare].
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageDiff {
+	pub pixels: usize,
+	/// Pixels differing by more than the tolerance in any channel.
+	pub differing_pixels: usize,
+	/// Largest difference in any channel of any pixel.
+	pub max_difference: u8,
+}
+
+impl Plugin for EffectCapturePlugin {
+	fn build(&self, app: &mut App) {
+		let (sender, receiver) = mpsc::channel();
+		app.insert_resource(bevy::time::TimeUpdateStrategy::ManualDuration(
+			self.frame_time,
+		))
+		.insert_resource(EffectCapture {
+			config: self.clone(),
+			frame: 0,
+			saved: 0,
+			failures: 0,
+			receiver: Mutex::new(receiver),
+		})
+		.add_systems(Startup, setup_capture)
+		.add_systems(First, warm_up.before(bevy::time::TimeSystem))
+		.add_systems(PostUpdate, save_frames);
+
+		let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+			return;
+		};
+		render_app
+			.insert_resource(FrameSender(sender))
+			.add_systems(ExtractSchedule, extract_readbacks)
+			.add_systems(Render, send_frames.after(RenderSet::Render));
+		let mut graph = render_app.world_mut().resource_mut::<RenderGraph>();
+		graph.add_node(CaptureLabel, CaptureNode);
+		graph.add_node_edge(bevy::render::graph::CameraDriverLabel, CaptureLabel);
+	}
+}
+
+#[derive(Resource)]
+struct EffectCapture {
+	config: EffectCapturePlugin,
+	/// Frames since startup.
+	frame: u32,
+	saved: u32,
+	/// Frames that differed from their [GoldenImages].
+	failures: u32,
+	receiver: Mutex<mpsc::Receiver<Vec<u8>>>,
+}
+
+/// Copies the render target into `buffer` for reading back each frame.
+#[derive(Component, Clone)]
+struct Readback {
+	target: Handle<Image>,
+	buffer: Buffer,
+}
+
+#[derive(Resource, Default)]
+struct Readbacks(Vec<Readback>);
+
+#[derive(Resource)]
+struct FrameSender(mpsc::Sender<Vec<u8>>);
+
+fn setup_capture(
+	mut cmds: Commands,
+	capture: Res<EffectCapture>,
+	mut images: ResMut<Assets<Image>>,
+	device: Res<RenderDevice>,
+) {
+	let config = &capture.config;
+	let size = Extent3d {
+		width: config.width,
+		height: config.height,
+		depth_or_array_layers: 1,
+	};
+	let mut target = Image::new_fill(
+		size,
+		TextureDimension::D2,
+		&[0; 4],
+		TextureFormat::Rgba8UnormSrgb,
+		RenderAssetUsages::default(),
+	);
+	target.texture_descriptor.usage |=
+		TextureUsages::COPY_SRC | TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING;
+	let target = images.add(target);
+	let buffer = device.create_buffer(&BufferDescriptor {
+		label: Some("effect_capture_readback"),
+		size: padded_row_bytes(config.width) as u64 * config.height as u64,
+		usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+		mapped_at_creation: false,
+	});
+	cmds.spawn(Readback {
+		target: target.clone(),
+		buffer,
+	});
+	cmds.spawn(Camera3dBundle {
+		camera: Camera {
+			target: RenderTarget::Image(target),
+			clear_color: ClearColorConfig::Custom(Color::NONE),
+			..default()
+		},
+		// Keeps the background exactly transparent.
+		tonemapping: Tonemapping::None,
+		deband_dither: DebandDither::Disabled,
+		transform: config.camera,
+		..default()
+	});
+}
+
+fn padded_row_bytes(width: u32) -> usize {
+	RenderDevice::align_copy_bytes_per_row(width as usize * 4)
+}
+
+fn warm_up(mut capture: ResMut<EffectCapture>, time: Option<ResMut<ParticleTime>>) {
+	let warming_up = capture.frame < capture.config.warmup_frames;
+	capture.frame += 1;
+	if let Some(mut time) = time {
+		time.paused = warming_up;
+	}
+}
+
+fn save_frames(mut capture: ResMut<EffectCapture>, mut exit: EventWriter<AppExit>) {
+	let capture = &mut *capture;
+	let frames = capture
+		.receiver
+		.get_mut()
+		.map(|receiver| receiver.try_iter().collect::<Vec<_>>())
+		.unwrap_or_default();
+	let config = &capture.config;
+	for data in frames {
+		// Frames rendered while warming up only show the effect before it started.
+		if capture.frame <= config.warmup_frames + 1 || capture.saved >= config.frames {
+			continue;
+		}
+		let row_bytes = config.width as usize * 4;
+		let data = data
+			.chunks(padded_row_bytes(config.width))
+			.take(config.height as usize)
+			.flat_map(|row| &row[..row_bytes])
+			.copied()
+			.collect::<Vec<_>>();
+		let image = Image::new(
+			Extent3d {
+				width: config.width,
+				height: config.height,
+				depth_or_array_layers: 1,
+			},
+			TextureDimension::D2,
+			data,
+			TextureFormat::Rgba8UnormSrgb,
+			RenderAssetUsages::MAIN_WORLD,
+		);
+		let file_name = format!("{}_{:03}.png", config.name, capture.saved);
+		let path = config.output_dir.join(&file_name);
+		capture.saved += 1;
+		if let Err(e) = save_png(&image, &path) {
+			error!("failed to save {path:?}: {e}");
+		}
+		if let Some(golden) = &config.golden {
+			if !check_golden(golden, &image, &file_name, &config.output_dir) {
+				capture.failures += 1;
+			}
+		}
+	}
+	if capture.saved >= config.frames {
+		exit.send(if capture.failures > 0 {
+			AppExit::error()
+		} else {
+			AppExit::Success
+		});
+	}
+}
+
+fn save_png(image: &Image, path: &Path) -> Result<(), String> {
+	if let Some(dir) = path.parent() {
+		std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+	}
+	let image = image
+		.clone()
+		.try_into_dynamic()
+		.map_err(|e| e.to_string())?;
+	image.save(path).map_err(|e| e.to_string())
+}
+
+/// Compares `frame` with its reference in `golden`, or replaces the reference if updating.
+/// Returns whether it matched.
+fn check_golden(golden: &GoldenImages, frame: &Image, file_name: &str, output_dir: &Path) -> bool {
+	let path = golden.dir.join(file_name);
+	if golden.update {
+		if let Err(e) = save_png(frame, &path) {
+			error!("failed to update {path:?}: {e}");
+			return false;
+		}
+		return true;
+	}
+	let reference = std::fs::read(&path)
+		.map_err(|e| e.to_string())
+		.and_then(|bytes| {
+			Image::from_buffer(
+				&bytes,
+				ImageType::Extension("png"),
+				CompressedImageFormats::NONE,
+				true,
+				ImageSampler::Default,
+				RenderAssetUsages::MAIN_WORLD,
+			)
+			.map_err(|e| e.to_string())
+		});
+	let reference = match reference {
+		Ok(reference) => reference,
+		Err(e) => {
+			error!("no golden image {path:?}: {e}");
+			return false;
+		}
+	};
+	if reference.size() != frame.size() {
+		error!(
+			"{file_name} is {}, but {path:?} is {}",
+			frame.size(),
+			reference.size()
+		);
+		return false;
+	}
+	let diff = golden.compare(&frame.data, &reference.data);
+	if golden.accepts(&diff) {
+		return true;
+	}
+	error!(
+		"{file_name} differs from {path:?}: {} of {} pixels by up to {}",
+		diff.differing_pixels, diff.pixels, diff.max_difference
+	);
+	// Differing pixels in red over a faded copy of the frame.
+	let mut highlighted = frame.clone();
+	for (pixel, reference) in highlighted
+		.data
+		.chunks_exact_mut(4)
+		.zip(reference.data.chunks_exact(4))
+	{
+		let same = golden.compare(pixel, reference).differing_pixels == 0;
+		let value = (pixel[0] / 4).max(pixel[1] / 4).max(pixel[2] / 4);
+		pixel.copy_from_slice(&if same {
+			[value, value, value, 255]
+		} else {
+			[255, 0, 0, 255]
+		});
+	}
+	let diff_path = output_dir.join(file_name.replace(".png", "_diff.png"));
+	if let Err(e) = save_png(&highlighted, &diff_path) {
+		error!("failed to save {diff_path:?}: {e}");
+	}
+	false
+}
+
+fn extract_readbacks(mut cmds: Commands, q: Extract<Query<&Readback>>) {
+	cmds.insert_resource(Readbacks(q.iter().cloned().collect()));
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, RenderLabel)]
+struct CaptureLabel;
+
+/// Copies each [Readback] target into its buffer after cameras have rendered.
+struct CaptureNode;
+
+impl render_graph::Node for CaptureNode {
+	fn run(
+		&self,
+		_graph: &mut RenderGraphContext,
+		render_context: &mut RenderContext,
+		world: &World,
+	) -> Result<(), NodeRunError> {
+		let (Some(readbacks), Some(images)) = (
+			world.get_resource::<Readbacks>(),
+			world.get_resource::<RenderAssets<GpuImage>>(),
+		) else {
+			return Ok(());
+		};
+		for readback in &readbacks.0 {
+			let Some(image) = images.get(&readback.target) else {
+				continue;
+			};
+			let mut encoder = render_context
+				.render_device()
+				.create_command_encoder(&CommandEncoderDescriptor::default());
+			encoder.copy_texture_to_buffer(
+				image.texture.as_image_copy(),
+				ImageCopyBuffer {
+					buffer: &readback.buffer,
+					layout: ImageDataLayout {
+						offset: 0,
+						bytes_per_row: Some(padded_row_bytes(image.size.x) as u32),
+						rows_per_image: None,
+					},
+				},
+				Extent3d {
+					width: image.size.x,
+					height: image.size.y,
+					depth_or_array_layers: 1,
+				},
+			);
+			world
+				.resource::<RenderQueue>()
+				.submit(std::iter::once(encoder.finish()));
+		}
+		Ok(())
+	}
+}
+
+/// Waits for each frame's copy to finish and sends it to the main world.
+fn send_frames(readbacks: Res<Readbacks>, device: Res<RenderDevice>, sender: Res<FrameSender>) {
+	for readback in &readbacks.0 {
+		let slice = readback.buffer.slice(..);
+		let (mapped, on_mapped) = mpsc::sync_channel(1);
+		slice.map_async(MapMode::Read, move |result| {
+			let _ = mapped.send(result);
+		});
+		device.poll(Maintain::wait()).panic_on_timeout();
+		if let Ok(Ok(())) = on_mapped.recv() {
+			// Fails while the app is exiting, once the receiver is dropped.
+			let _ = sender.0.send(slice.get_mapped_range().to_vec());
+			readback.buffer.unmap();
+		}
+	}
+}
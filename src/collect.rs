@@ -0,0 +1,174 @@
+//! Particles flying to a point on screen, e.g. loot flying to the coin counter.
+
+use bevy::prelude::*;
+
+use crate::{
+	update::{main_camera, parent_global_transform, parent_space_vector, Cameras, Velocity},
+	TimeCreated,
+};
+
+/// Flies the particle to `target` on screen, e.g. coins bursting out of a chest and then
+/// flying to the coin counter in the corner.
+///
+/// After `delay`, the particle is moved in the camera's viewport towards the target,
+/// faster and faster, while its distance from the camera eases towards `depth`. It is
+/// converted between world and viewport coordinates every frame, so it stays on its way
+/// to the target however the camera moves. Its [Velocity] is stopped while it flies.
+///
+/// Once within `arrival_radius` of the target, [ParticleCollected] is triggered for the
+/// particle and for the target node, if any, and the particle is despawned unless
+/// `despawn` is off.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct CollectToScreen {
+	pub target: ScreenTarget,
+	/// Camera whose viewport the target is in. `None` uses the active camera with the
+	/// highest order, usually the main one.
+	pub camera: Option<Entity>,
+	/// Seconds after the particle spawned before it starts flying, e.g. to let a burst
+	/// scatter first.
+	pub delay: f32,
+	/// Speed gained per second of flight, in logical pixels per second.
+	pub acceleration: f32,
+	/// Distance from the camera the particle arrives at, in world units. Particles get
+	/// larger on screen as they approach a perspective camera.
+	pub depth: f32,
+	/// Distance from the target, in logical pixels, within which the particle has arrived.
+	pub arrival_radius: f32,
+	pub despawn: bool,
+}
+
+/// Where on screen a [CollectToScreen] particle flies to.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum ScreenTarget {
+	/// A point in the camera's viewport, in logical pixels from its top left corner.
+	Position(Vec2),
+	/// The center of a UI node, e.g. the coin counter, read from its `GlobalTransform`,
+	/// which Bevy UI keeps in logical pixels from the top left corner of the window.
+	Node(Entity),
+}
+
+/// Triggered for a [CollectToScreen] particle, and for its target node if it has one, once
+/// the particle arrives, e.g. to count the coin.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ParticleCollected {
+	pub particle: Entity,
+}
+
+impl CollectToScreen {
+	pub fn new(target: ScreenTarget) -> Self {
+		Self {
+			target,
+			camera: None,
+			delay: 0.0,
+			acceleration: 4000.0,
+			depth: 2.0,
+			arrival_radius: 8.0,
+			despawn: true,
+		}
+	}
+
+	pub fn with_camera(self, camera: Entity) -> Self {
+		Self {
+			camera: Some(camera),
+			..self
+		}
+	}
+
+	pub fn with_delay(self, delay: f32) -> Self {
+		Self { delay, ..self }
+	}
+
+	pub fn with_acceleration(self, acceleration: f32) -> Self {
+		Self {
+			acceleration,
+			..self
+		}
+	}
+
+	pub fn with_depth(self, depth: f32) -> Self {
+		Self { depth, ..self }
+	}
+
+	pub fn tick(
+		mut cmds: Commands,
+		mut q: Query<(
+			Entity,
+			&Self,
+			&mut Transform,
+			&GlobalTransform,
+			Option<&Parent>,
+			&TimeCreated,
+			Option<&mut Velocity>,
+		)>,
+		cameras: Cameras,
+		nodes: Query<&GlobalTransform, Without<Self>>,
+		parents: Query<&GlobalTransform>,
+		t: Res<Time>,
+	) {
+		if q.is_empty() {
+			return;
+		}
+		let main_camera = main_camera(&cameras).map(|(id, ..)| id);
+		let dt = t.delta_seconds();
+		for (id, item, mut xform, global_xform, parent, created, vel) in &mut q {
+			let flight = t.elapsed().saturating_sub(created.0).as_secs_f32() - item.delay;
+			if flight < 0.0 {
+				continue;
+			}
+			let Some((_, camera, camera_xform)) = item
+				.camera
+				.or(main_camera)
+				.and_then(|id| cameras.get(id).ok())
+			else {
+				continue;
+			};
+			let viewport = camera.logical_viewport_rect().map_or(Vec2::ZERO, |r| r.min);
+			let target = match item.target {
+				ScreenTarget::Position(position) => position,
+				ScreenTarget::Node(node) => match nodes.get(node) {
+					Ok(node) => node.translation().truncate() - viewport,
+					Err(_) => continue,
+				},
+			};
+			let position = global_xform.translation();
+			let Some(screen) = camera.world_to_viewport(camera_xform, position) else {
+				continue;
+			};
+			if let Some(mut vel) = vel {
+				vel.set_if_neq(Velocity(Vec3::ZERO));
+			}
+
+			let to_target = target - screen;
+			let distance = to_target.length();
+			let step = (item.acceleration * flight * dt).min(distance);
+			if distance - step <= item.arrival_radius {
+				let targets = match item.target {
+					ScreenTarget::Node(node) => vec![id, node],
+					ScreenTarget::Position(_) => vec![id],
+				};
+				cmds.trigger_targets(ParticleCollected { particle: id }, targets);
+				if item.despawn {
+					cmds.entity(id).despawn_recursive();
+				}
+				continue;
+			}
+			let fraction = if distance > 0.0 { step / distance } else { 1.0 };
+			let forward = camera_xform.forward();
+			let depth = (position - camera_xform.translation()).dot(*forward);
+			let depth = depth + (item.depth - depth) * fraction;
+			let Some(ray) = camera.viewport_to_world(camera_xform, screen + to_target * fraction)
+			else {
+				continue;
+			};
+			// The point on the ray at `depth` in front of the camera, for perspective and
+			// orthographic cameras alike.
+			let along = (depth - (ray.origin - camera_xform.translation()).dot(*forward))
+				/ ray.direction.dot(*forward);
+			let delta = ray.get_point(along) - position;
+			let parent = parent_global_transform(parent, &parents);
+			let local_delta = parent_space_vector(&parent, delta);
+			xform.translation += local_delta;
+		}
+	}
+}
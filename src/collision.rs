@@ -0,0 +1,408 @@
+//! Particles colliding with simple shapes, tested along the whole path they moved each
+//! frame, so fast particles don't tunnel through thin geometry.
+
+use std::sync::{Arc, Mutex};
+
+use bevy::{ecs::query::QueryFilter, math::Affine3A, prelude::*, utils::Duration};
+
+use crate::{
+	lifecycle::{DespawnWithSpewer, ParticleOf},
+	update::Velocity,
+	Deterministic, InitialGlobalTransform, Lifetime, ParticleFactory, ParticleLayers, TimeCreated,
+};
+
+/// Shape of a [ParticleCollider], in the collider entity's local space.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum ColliderShape {
+	/// Infinite plane through the origin, solid below its local XZ plane. Particles only
+	/// collide with it from above.
+	Plane,
+	Sphere {
+		radius: f32,
+	},
+	Cuboid {
+		half_size: Vec3,
+	},
+}
+
+/// Something particles with a [ParticleCollision] collide with.
+///
+/// Only affects particles sharing one of its [ParticleLayers].
+#[derive(Debug, Clone, Copy, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct ParticleCollider {
+	pub shape: ColliderShape,
+}
+
+impl ParticleCollider {
+	pub fn new(shape: ColliderShape) -> Self {
+		Self { shape }
+	}
+
+	/// Finds where a sphere of `radius` moving from `start` to `end` first touches this
+	/// collider, all in world space, returning the fraction of the way to `end` and the
+	/// surface normal there.
+	///
+	/// Paths starting inside the collider don't hit it, so particles spawned inside can
+	/// leave.
+	pub fn sweep(
+		&self,
+		xform: &GlobalTransform,
+		start: Vec3,
+		end: Vec3,
+		radius: f32,
+	) -> Option<(f32, Vec3)> {
+		let affine = xform.affine();
+		match self.shape {
+			ColliderShape::Plane => {
+				let origin = Vec3::from(affine.translation);
+				let normal = world_normal(&affine, Vec3::Y);
+				let d0 = (start - origin).dot(normal) - radius;
+				let d1 = (end - origin).dot(normal) - radius;
+				(d0 >= 0.0 && d1 < 0.0).then(|| (d0 / (d0 - d1), normal))
+			}
+			ColliderShape::Sphere {
+				radius: sphere_radius,
+			} => {
+				let center = Vec3::from(affine.translation);
+				let scale = xform.compute_transform().scale.abs().max_element();
+				let r = sphere_radius * scale + radius;
+				let (from, path) = (start - center, end - start);
+				let a = path.length_squared();
+				let b = from.dot(path);
+				let c = from.length_squared() - r * r;
+				if c < 0.0 || a <= f32::EPSILON {
+					return None;
+				}
+				let discriminant = b * b - a * c;
+				if discriminant < 0.0 {
+					return None;
+				}
+				let t = (-b - discriminant.sqrt()) / a;
+				(0.0..=1.0).contains(&t).then(|| {
+					let normal = (from + path * t).normalize_or(Vec3::Y);
+					(t, normal)
+				})
+			}
+			ColliderShape::Cuboid { half_size } => {
+				let inverse = affine.inverse();
+				let (from, to) = (
+					inverse.transform_point3(start),
+					inverse.transform_point3(end),
+				);
+				// The particle's radius, in the cuboid's local units on each axis.
+				let scale = xform
+					.compute_transform()
+					.scale
+					.abs()
+					.max(Vec3::splat(f32::EPSILON));
+				let half_size = half_size + Vec3::splat(radius) / scale;
+				if from.abs().cmple(half_size).all() {
+					return None;
+				}
+				let path = to - from;
+				let (mut enter, mut exit, mut axis) = (0.0f32, 1.0f32, None);
+				for i in 0..3 {
+					if path[i].abs() <= f32::EPSILON {
+						if from[i].abs() > half_size[i] {
+							return None;
+						}
+						continue;
+					}
+					let t0 = (-half_size[i] - from[i]) / path[i];
+					let t1 = (half_size[i] - from[i]) / path[i];
+					let (near, far) = (t0.min(t1), t0.max(t1));
+					if near > enter {
+						enter = near;
+						axis = Some(i);
+					}
+					exit = exit.min(far);
+					if enter > exit {
+						return None;
+					}
+				}
+				let axis = axis?;
+				let mut normal = Vec3::ZERO;
+				normal[axis] = -path[axis].signum();
+				Some((enter, world_normal(&affine, normal)))
+			}
+		}
+	}
+}
+
+/// Transforms a local surface normal into world space, keeping it perpendicular to the
+/// surface under non-uniform scale.
+fn world_normal(affine: &Affine3A, normal: Vec3) -> Vec3 {
+	let normal_matrix = Mat3::from(affine.matrix3).inverse().transpose();
+	(normal_matrix * normal).normalize_or(Vec3::Y)
+}
+
+/// A factory shared by every particle that spawns particles with it when colliding,
+/// spawning `count` particles at the contact point.
+#[derive(Clone)]
+pub struct ImpactSpawn {
+	pub factory: Arc<Mutex<Box<dyn ParticleFactory>>>,
+	pub count: u32,
+}
+
+impl ImpactSpawn {
+	pub fn new(factory: impl ParticleFactory, count: u32) -> Self {
+		Self {
+			factory: Arc::new(Mutex::new(Box::new(factory))),
+			count,
+		}
+	}
+}
+
+/// What happens to a particle when it hits a [ParticleCollider].
+#[derive(Clone)]
+pub enum CollisionResponse {
+	/// Bounces off, keeping `restitution` of its speed along the surface normal and losing
+	/// `friction` of its speed along the surface.
+	Bounce { restitution: f32, friction: f32 },
+	/// Despawned.
+	Kill,
+	/// Stops at the contact point and becomes a child of the collider, moving along with it.
+	/// Its [Velocity] and `ParticleCollision` are removed.
+	Stick,
+	/// Passes through, only sending [ParticleCollided].
+	EventOnly,
+	/// Replaced by the particles spawned with `ImpactSpawn`, e.g. sparks turning into smoke
+	/// puffs. They are given the particle's [ParticleOf], [ParticleLayers], and
+	/// [DespawnWithSpewer] and [Deterministic] markers, so they still count as particles
+	/// of the same spewer.
+	Spawn(ImpactSpawn),
+}
+
+/// Sent to a particle with a [ParticleCollision] when it hits a [ParticleCollider], before
+/// the [CollisionResponse] is applied.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ParticleCollided {
+	pub collider: Entity,
+	/// Where the particle touched the collider, in world space.
+	pub position: Vec3,
+	/// World-space normal of the collider's surface at `position`.
+	pub normal: Vec3,
+	/// World-space velocity of the particle when it hit.
+	pub velocity: Vec3,
+}
+
+/// Makes the particle collide with [ParticleCollider]s. Spewers give their particles
+/// their own responses through their factories.
+///
+/// Collisions are found by sweeping the particle from where it was at the previous update
+/// to where it is now, so it can't pass through a collider between updates however fast
+/// it moves. The particle stops at the first collider it hits each update.
+#[derive(Component, Clone, Reflect)]
+#[reflect(Component, from_reflect = false)]
+pub struct ParticleCollision {
+	/// Radius of the particle, in world units.
+	pub radius: f32,
+	#[reflect(ignore)]
+	pub response: CollisionResponse,
+	/// Lifetime lost each time the particle bounces.
+	pub lifetime_loss: Duration,
+	/// The particle is despawned when it bounces off slower than this, in units per second,
+	/// instead of jittering on the surface.
+	pub min_speed: f32,
+	/// World-space speed the particle's [Velocity] is clamped to after each update, which
+	/// bounds how far the next sweep can reach.
+	pub max_speed: f32,
+	/// World-space position at the last update.
+	pub previous: Option<Vec3>,
+}
+
+impl Default for ParticleCollision {
+	fn default() -> Self {
+		Self {
+			radius: 0.0,
+			response: CollisionResponse::Bounce {
+				restitution: 0.5,
+				friction: 0.1,
+			},
+			lifetime_loss: Duration::ZERO,
+			min_speed: 0.0,
+			max_speed: f32::INFINITY,
+			previous: None,
+		}
+	}
+}
+
+impl ParticleCollision {
+	pub fn new(radius: f32) -> Self {
+		Self {
+			radius,
+			..default()
+		}
+	}
+
+	pub fn with_response(self, response: CollisionResponse) -> Self {
+		Self { response, ..self }
+	}
+
+	pub fn with_lifetime_loss(self, lifetime_loss: Duration) -> Self {
+		Self {
+			lifetime_loss,
+			..self
+		}
+	}
+
+	pub fn with_min_speed(self, min_speed: f32) -> Self {
+		Self { min_speed, ..self }
+	}
+
+	pub fn with_max_speed(self, max_speed: f32) -> Self {
+		Self { max_speed, ..self }
+	}
+
+	/// Velocity after bouncing off a surface with `normal`.
+	pub fn bounce(velocity: Vec3, normal: Vec3, restitution: f32, friction: f32) -> Vec3 {
+		let into = velocity.dot(normal);
+		if into >= 0.0 {
+			return velocity;
+		}
+		let along = velocity - normal * into;
+		along * (1.0 - friction) - normal * into * restitution
+	}
+
+	/// Updates particles one at a time, so particles spawned on impact are spawned in a
+	/// stable order.
+	pub fn tick<F: QueryFilter>(
+		mut cmds: Commands,
+		mut q: Query<
+			(
+				Entity,
+				&mut Self,
+				&mut Transform,
+				&mut Velocity,
+				Option<&mut Lifetime>,
+				Option<&Parent>,
+				Option<&InitialGlobalTransform>,
+				Option<&ParticleLayers>,
+				(
+					Option<&ParticleOf>,
+					Has<Deterministic>,
+					Has<DespawnWithSpewer>,
+				),
+			),
+			F,
+		>,
+		colliders: Query<(
+			Entity,
+			&ParticleCollider,
+			&GlobalTransform,
+			Option<&ParticleLayers>,
+		)>,
+		parents: Query<&GlobalTransform>,
+		t: Res<Time>,
+	) {
+		let now = t.elapsed();
+		let colliders = colliders.iter().collect::<Vec<_>>();
+		for (
+			id,
+			mut collision,
+			mut xform,
+			mut vel,
+			lifetime,
+			parent,
+			initial,
+			layers,
+			(of, deterministic, despawn_with_spewer),
+		) in &mut q
+		{
+			let parent = parent
+				.and_then(|parent| parents.get(parent.get()).ok())
+				.map_or(Affine3A::IDENTITY, GlobalTransform::affine);
+			let mut velocity = parent.transform_vector3(vel.0);
+			if velocity.length_squared() > collision.max_speed * collision.max_speed {
+				velocity = velocity.clamp_length_max(collision.max_speed);
+				vel.0 = parent.inverse().transform_vector3(velocity);
+			}
+			let end = parent.transform_point3(xform.translation);
+			let start = collision
+				.previous
+				.or_else(|| initial.map(|initial| initial.translation()))
+				.unwrap_or(end);
+
+			let radius = collision.radius;
+			let hit = colliders
+				.iter()
+				.filter(|(.., collider_layers)| ParticleLayers::interact(layers, *collider_layers))
+				.filter_map(|&(collider_id, collider, collider_xform, _)| {
+					let (t, normal) = collider.sweep(collider_xform, start, end, radius)?;
+					Some((t, normal, collider_id, collider_xform))
+				})
+				.min_by(|(a, ..), (b, ..)| a.total_cmp(b));
+			let Some((t, normal, collider, collider_xform)) = hit else {
+				collision.previous = Some(end);
+				continue;
+			};
+
+			// Stay just outside the surface, so the next sweep doesn't start inside.
+			let contact = start.lerp(end, t) + normal * 1e-4;
+			cmds.trigger_targets(
+				ParticleCollided {
+					collider,
+					position: contact,
+					normal,
+					velocity,
+				},
+				id,
+			);
+			match &collision.response {
+				&CollisionResponse::Bounce {
+					restitution,
+					friction,
+				} => {
+					let bounced = Self::bounce(velocity, normal, restitution, friction);
+					let mut expired = bounced.length() < collision.min_speed;
+					if let Some(mut lifetime) = lifetime {
+						if !collision.lifetime_loss.is_zero() {
+							lifetime.0 = lifetime.0.saturating_sub(collision.lifetime_loss);
+							expired |= lifetime.0.is_zero();
+						}
+					}
+					if expired {
+						cmds.entity(id).despawn_recursive();
+						continue;
+					}
+					xform.translation = parent.inverse().transform_point3(contact);
+					vel.0 = parent.inverse().transform_vector3(bounced);
+					collision.previous = Some(contact);
+				}
+				CollisionResponse::Kill => cmds.entity(id).despawn_recursive(),
+				CollisionResponse::Stick => {
+					let world = Transform::from_matrix((parent * xform.compute_affine()).into())
+						.with_translation(contact);
+					let local = collider_xform.affine().inverse() * world.compute_affine();
+					*xform = Transform::from_matrix(local.into());
+					cmds.entity(id)
+						.remove::<(Self, Velocity)>()
+						.set_parent(collider);
+				}
+				CollisionResponse::EventOnly => collision.previous = Some(end),
+				CollisionResponse::Spawn(spawn) => {
+					if let Ok(mut factory) = spawn.factory.lock() {
+						let at = GlobalTransform::from_translation(contact);
+						for _ in 0..spawn.count {
+							let mut particle = factory(&mut cmds, &at, TimeCreated(now));
+							if let Some(&of) = of {
+								particle.insert(of);
+							}
+							if let Some(&layers) = layers {
+								particle.insert(layers);
+							}
+							if deterministic {
+								particle.insert(Deterministic);
+							}
+							if despawn_with_spewer {
+								particle.insert(DespawnWithSpewer);
+							}
+						}
+					}
+					cmds.entity(id).despawn_recursive();
+				}
+			}
+		}
+	}
+}
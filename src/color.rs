@@ -0,0 +1,378 @@
+//! Per-particle colors for particles sharing a material.
+
+#[cfg(feature = "render")]
+use bevy::utils::{HashMap, HashSet};
+use bevy::{ecs::query::QueryFilter, prelude::*};
+
+#[cfg(feature = "render")]
+use crate::{flicker::FlickerMaterial, template::MaterialOverrides, ParticleUserData};
+use crate::{math::lifetime_progress, Lifetime, TimeCreated};
+
+/// Tints the particle's material, multiplying its base color and emissive.
+///
+/// Bevy's mesh pipeline has no per-instance data for materials to read, so particles can't
+/// be tinted without changing their material. Instead, the material the particle was given
+/// is used as a template for [MaterialVariants] with the tint applied, which are shared by
+/// every particle with the same template and tint. Tints are rounded to
+/// [MaterialVariants::steps] first, so that similar tints don't each create a material.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct ParticleColor {
+	pub color: LinearRgba,
+	pub emissive: LinearRgba,
+}
+
+impl Default for ParticleColor {
+	fn default() -> Self {
+		Self {
+			color: LinearRgba::WHITE,
+			emissive: LinearRgba::WHITE,
+		}
+	}
+}
+
+impl ParticleColor {
+	pub fn new(color: impl Into<LinearRgba>) -> Self {
+		Self {
+			color: color.into(),
+			..default()
+		}
+	}
+
+	pub fn with_emissive(self, emissive: impl Into<LinearRgba>) -> Self {
+		Self {
+			emissive: emissive.into(),
+			..self
+		}
+	}
+
+	/// Multiplies both the color and the emissive by `tint`.
+	pub fn tinted(self, tint: SpewerTint) -> Self {
+		Self {
+			color: tint.apply(self.color),
+			emissive: tint.apply(self.emissive),
+		}
+	}
+}
+
+/// Multiplies the colors of every particle this [Spewer](crate::Spewer) or
+/// [ParticleBuffer](crate::buffer::ParticleBuffer) emits, e.g. to reuse one authored
+/// effect for each team or element at runtime.
+///
+/// Copied from the spewer onto each particle it spawns, where it is applied on top of
+/// the particle's [ParticleColor], including one set by [ColorOverLifetime], through the
+/// same [MaterialVariants]. A buffer multiplies its particles' colors as they are drawn,
+/// so changing its tint recolors live particles too.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct SpewerTint(pub Color);
+
+impl SpewerTint {
+	pub fn apply(&self, color: LinearRgba) -> LinearRgba {
+		let tint = self.0.to_linear();
+		LinearRgba::new(
+			color.red * tint.red,
+			color.green * tint.green,
+			color.blue * tint.blue,
+			color.alpha * tint.alpha,
+		)
+	}
+}
+
+/// Fades the particle's material to this opacity, on top of its [ParticleColor], through
+/// the same [MaterialVariants]. Opaque and masked materials are alpha blended while faded
+/// below `1.0`. Set by [ParticleLod](crate::lod::ParticleLod) while crossfading.
+#[cfg(feature = "render")]
+#[derive(Debug, Clone, Copy, PartialEq, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
+pub struct ParticleFade(pub f32);
+
+#[cfg(feature = "render")]
+impl Default for ParticleFade {
+	fn default() -> Self {
+		Self(1.0)
+	}
+}
+
+/// Points the material of each particle with a changed [ParticleColor], [SpewerTint],
+/// [ParticleFade], [MaterialOverrides], or [ParticleUserData] at the matching variant of
+/// its template.
+///
+/// Particles whose template hasn't loaded yet keep it until it has.
+#[cfg(feature = "render")]
+pub fn apply_material_variants<M: FlickerMaterial>(
+	mut q: Query<
+		(
+			Entity,
+			Option<Ref<ParticleColor>>,
+			Option<Ref<SpewerTint>>,
+			Option<Ref<ParticleFade>>,
+			Option<Ref<MaterialOverrides>>,
+			Option<Ref<ParticleUserData>>,
+			&mut Handle<M>,
+		),
+		Or<(
+			With<ParticleColor>,
+			With<SpewerTint>,
+			With<ParticleFade>,
+			With<MaterialOverrides>,
+			With<ParticleUserData>,
+		)>,
+	>,
+	mut variants: ResMut<MaterialVariants<M>>,
+	mut materials: ResMut<Assets<M>>,
+	mut pending: Local<HashSet<Entity>>,
+) {
+	if !pending.is_empty() {
+		pending.retain(|&id| q.contains(id));
+	}
+	for (id, tint, spewer_tint, fade, overrides, user_data, mut handle) in &mut q {
+		let changed = tint.as_ref().is_some_and(DetectChanges::is_changed)
+			|| spewer_tint.as_ref().is_some_and(DetectChanges::is_changed)
+			|| fade.as_ref().is_some_and(DetectChanges::is_changed)
+			|| overrides.as_ref().is_some_and(DetectChanges::is_changed)
+			|| user_data.as_ref().is_some_and(DetectChanges::is_changed);
+		if !changed && !pending.contains(&id) {
+			continue;
+		}
+		let tint = tint.as_deref().copied();
+		let tint = match spewer_tint {
+			Some(spewer_tint) => Some(tint.unwrap_or_default().tinted(*spewer_tint)),
+			None => tint,
+		};
+		let fade = fade.map_or(1.0, |fade| fade.0.clamp(0.0, 1.0));
+		let mut overrides = overrides.as_deref().cloned();
+		let tint = if fade < 1.0 {
+			let opaque = materials
+				.get(variants.template(&handle))
+				.is_some_and(|material| {
+					matches!(
+						material.standard().alpha_mode,
+						AlphaMode::Opaque | AlphaMode::Mask(_) | AlphaMode::AlphaToCoverage
+					)
+				});
+			if opaque {
+				overrides = Some(
+					overrides
+						.unwrap_or_default()
+						.with_alpha_mode(AlphaMode::Blend),
+				);
+			}
+			let mut tint = tint.unwrap_or_default();
+			tint.color.alpha *= fade;
+			Some(tint)
+		} else {
+			tint
+		};
+		match variants.variant(
+			&handle,
+			tint.as_ref(),
+			overrides.as_ref(),
+			user_data.as_deref(),
+			&mut materials,
+		) {
+			Some(variant) => {
+				pending.remove(&id);
+				handle.set_if_neq(variant);
+			}
+			None => {
+				pending.insert(id);
+			}
+		}
+	}
+}
+
+/// Sets the color of the particle's [ParticleColor] over its lifetime, from `0.0` to
+/// `1.0`. The particle must have a `ParticleColor` already.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct ColorOverLifetime(pub fn(f32) -> LinearRgba);
+
+impl ColorOverLifetime {
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<(&Self, &mut ParticleColor, &TimeCreated, &Lifetime), F>,
+		t: Res<Time>,
+	) {
+		q.par_iter_mut()
+			.for_each(|(item, tint, t_created, lifetime)| {
+				let s = lifetime_progress(
+					t.elapsed().saturating_sub(t_created.0).as_secs_f32(),
+					lifetime.0.as_secs_f32(),
+				);
+				tint.map_unchanged(|tint| &mut tint.color)
+					.set_if_neq((item.0)(s));
+			});
+	}
+}
+
+/// Materials created for [ParticleColor]s, [MaterialOverrides], and [ParticleUserData], by
+/// template, overrides, rounded tint, and user data.
+#[cfg(feature = "render")]
+#[derive(Resource)]
+pub struct MaterialVariants<M: Material> {
+	/// Steps per unit of each color channel that tints are rounded to.
+	pub steps: f32,
+	variants: HashMap<(AssetId<M>, VariantKey), Handle<M>>,
+	/// The template each variant was created from.
+	templates: HashMap<AssetId<M>, Handle<M>>,
+}
+
+#[cfg(feature = "render")]
+impl<M: Material> Default for MaterialVariants<M> {
+	fn default() -> Self {
+		Self {
+			steps: 64.0,
+			variants: default(),
+			templates: default(),
+		}
+	}
+}
+
+#[cfg(feature = "render")]
+impl<M: FlickerMaterial> MaterialVariants<M> {
+	/// Number of variants currently kept alive.
+	pub fn len(&self) -> usize {
+		self.variants.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.variants.is_empty()
+	}
+
+	/// Returns the variant of `material` (or of its template, if it is a variant itself)
+	/// with `overrides`, `tint`, and `user_data` applied, creating it if needed. Returns
+	/// `None` if the template isn't loaded. `user_data` is ignored unless the material
+	/// supports it.
+	pub fn variant(
+		&mut self,
+		material: &Handle<M>,
+		tint: Option<&ParticleColor>,
+		overrides: Option<&MaterialOverrides>,
+		user_data: Option<&ParticleUserData>,
+		materials: &mut Assets<M>,
+	) -> Option<Handle<M>> {
+		let user_data = user_data.filter(|_| M::USER_DATA);
+		let template = self.template(material).clone();
+		if tint.is_none() && overrides.is_none() && user_data.is_none() {
+			return Some(template);
+		}
+		let key = VariantKey {
+			tint: tint.map(|tint| self.round(tint)),
+			overrides: overrides.map(MaterialOverrides::key),
+			user_data: user_data.map(|data| data.to_array().map(f32::to_bits)),
+		};
+		if let Some(variant) = self.variants.get(&(template.id(), key.clone())) {
+			return Some(variant.clone());
+		}
+
+		let mut variant = materials.get(&template)?.clone();
+		if let Some(data) = user_data {
+			variant.set_user_data(data.0);
+		}
+		let standard = variant.standard_mut();
+		if let Some(overrides) = overrides {
+			overrides.apply(standard);
+		}
+		if let Some(tint) = key.tint {
+			let steps = self.steps.max(f32::EPSILON);
+			Self::apply_tint(standard, tint.map(|channel| channel as f32 / steps));
+		}
+
+		let variant = materials.add(variant);
+		self.variants.insert((template.id(), key), variant.clone());
+		self.templates.insert(variant.id(), template);
+		Some(variant)
+	}
+
+	/// The template `material` is a variant of, or `material` itself if it isn't one.
+	pub fn template<'a>(&'a self, material: &'a Handle<M>) -> &'a Handle<M> {
+		self.templates.get(&material.id()).unwrap_or(material)
+	}
+
+	/// Makes variants of `derived`, a material made from `material` elsewhere, variants of
+	/// the template of `material` instead.
+	pub(crate) fn add_derived(&mut self, derived: &Handle<M>, material: &Handle<M>) {
+		let template = self.template(material).clone();
+		self.templates.insert(derived.id(), template);
+	}
+
+	fn apply_tint(standard: &mut StandardMaterial, channel: [f32; 8]) {
+		let channel = |i: usize| channel[i];
+		let color = standard.base_color.to_linear();
+		standard.base_color = LinearRgba::new(
+			color.red * channel(0),
+			color.green * channel(1),
+			color.blue * channel(2),
+			color.alpha * channel(3),
+		)
+		.into();
+		let emissive = standard.emissive;
+		standard.emissive = LinearRgba::new(
+			emissive.red * channel(4),
+			emissive.green * channel(5),
+			emissive.blue * channel(6),
+			emissive.alpha * channel(7),
+		);
+	}
+
+	fn round(&self, tint: &ParticleColor) -> [i32; 8] {
+		let ParticleColor { color, emissive } = tint;
+		[
+			color.red,
+			color.green,
+			color.blue,
+			color.alpha,
+			emissive.red,
+			emissive.green,
+			emissive.blue,
+			emissive.alpha,
+		]
+		.map(|channel| (channel * self.steps).round() as i32)
+	}
+
+	/// Drops variants no longer used by any particle with a [ParticleColor], [SpewerTint],
+	/// [ParticleFade], [MaterialOverrides], or [ParticleUserData], once such particles have
+	/// been despawned or lost them.
+	pub fn clean_up(
+		mut variants: ResMut<Self>,
+		mut removed_tints: RemovedComponents<ParticleColor>,
+		mut removed_spewer_tints: RemovedComponents<SpewerTint>,
+		mut removed_fades: RemovedComponents<ParticleFade>,
+		mut removed_overrides: RemovedComponents<MaterialOverrides>,
+		mut removed_user_data: RemovedComponents<ParticleUserData>,
+		q: Query<
+			&Handle<M>,
+			Or<(
+				With<ParticleColor>,
+				With<SpewerTint>,
+				With<ParticleFade>,
+				With<MaterialOverrides>,
+				With<ParticleUserData>,
+			)>,
+		>,
+	) {
+		let removed = removed_tints.read().count()
+			+ removed_spewer_tints.read().count()
+			+ removed_fades.read().count()
+			+ removed_overrides.read().count()
+			+ removed_user_data.read().count();
+		if removed == 0 || variants.is_empty() {
+			return;
+		}
+		let used = q.iter().map(Handle::id).collect::<HashSet<_>>();
+		let variants = &mut *variants;
+		variants
+			.variants
+			.retain(|_, variant| used.contains(&variant.id()));
+		variants
+			.templates
+			.retain(|variant, _| used.contains(variant));
+	}
+}
+
+#[cfg(feature = "render")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct VariantKey {
+	tint: Option<[i32; 8]>,
+	overrides: Option<crate::template::OverridesKey>,
+	user_data: Option<[u32; 4]>,
+}
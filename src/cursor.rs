@@ -0,0 +1,131 @@
+//! Spewers following the point under the cursor, e.g. for spell-targeting sparkles or
+//! click feedback in strategy games.
+
+use bevy::{
+	prelude::*,
+	render::camera::RenderTarget,
+	window::{PrimaryWindow, WindowRef},
+};
+
+use crate::{
+	update::{main_camera, parent_global_transform, translate_now, Cameras},
+	PreviousGlobalTransform, Spewer,
+};
+
+/// Moves the [Spewer] on the same entity to where the cursor's ray from a camera hits
+/// `target`, every frame before it emits.
+///
+/// Interval emission pauses while the cursor is outside the camera's window or misses the
+/// target, without building up a backlog, and resumes at the new point without trailing
+/// particles from the last one. Bursts, e.g. sent on a click, still emit at the last point.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct FollowCursor {
+	pub target: CursorTarget,
+	/// Camera the cursor's ray is cast from. `None` uses the active camera with the highest
+	/// order, usually the main one.
+	pub camera: Option<Entity>,
+	/// World-space point under the cursor at the last update, if it hit the target.
+	pub position: Option<Vec3>,
+}
+
+/// What the cursor's ray is cast against for [FollowCursor].
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum CursorTarget {
+	/// The plane through `origin` facing `normal`, e.g. the ground.
+	Plane { origin: Vec3, normal: Dir3 },
+	/// The point at this distance from the camera along the ray, e.g. for effects in front
+	/// of the camera or with a depth read back from the scene.
+	Depth(f32),
+}
+
+impl CursorTarget {
+	/// The ground plane through the world origin.
+	pub const GROUND: Self = Self::Plane {
+		origin: Vec3::ZERO,
+		normal: Dir3::Y,
+	};
+
+	/// Where `ray` hits this target, if it does.
+	pub fn hit(&self, ray: Ray3d) -> Option<Vec3> {
+		match *self {
+			Self::Plane { origin, normal } => ray
+				.intersect_plane(origin, InfinitePlane3d::new(normal))
+				.map(|distance| ray.get_point(distance)),
+			Self::Depth(distance) => Some(ray.get_point(distance)),
+		}
+	}
+}
+
+impl FollowCursor {
+	pub fn new(target: CursorTarget) -> Self {
+		Self {
+			target,
+			camera: None,
+			position: None,
+		}
+	}
+
+	pub fn with_camera(self, camera: Entity) -> Self {
+		Self {
+			camera: Some(camera),
+			..self
+		}
+	}
+
+	pub fn tick(
+		mut q: Query<
+			(
+				&mut Self,
+				&mut Spewer,
+				&mut Transform,
+				&mut GlobalTransform,
+				Option<&Parent>,
+				Option<&mut PreviousGlobalTransform>,
+			),
+			Without<Camera>,
+		>,
+		parents: Query<&GlobalTransform, Without<Self>>,
+		cameras: Cameras,
+		windows: Query<&Window>,
+		primary: Query<Entity, With<PrimaryWindow>>,
+		t: Res<Time>,
+	) {
+		let main_camera = main_camera(&cameras).map(|(id, ..)| id);
+		for (mut item, mut spewer, mut xform, mut global_xform, parent, prev_global_xform) in &mut q
+		{
+			let ray = item
+				.camera
+				.or(main_camera)
+				.and_then(|id| cameras.get(id).ok())
+				.and_then(|(_, camera, camera_xform)| {
+					let window = match camera.target {
+						RenderTarget::Window(WindowRef::Primary) => primary.get_single().ok()?,
+						RenderTarget::Window(WindowRef::Entity(id)) => id,
+						_ => return None,
+					};
+					let cursor = windows.get(window).ok()?.cursor_position()?;
+					let viewport = camera.logical_viewport_rect().map_or(Vec2::ZERO, |r| r.min);
+					camera.viewport_to_world(camera_xform, cursor - viewport)
+				});
+			let hit = ray.and_then(|ray| item.target.hit(ray));
+			let resumed = item.position.is_none();
+			item.position = hit;
+			let Some(hit) = hit else {
+				spewer.last_spawn = t.elapsed();
+				spewer.next_interval = None;
+				continue;
+			};
+			let delta = hit - global_xform.translation();
+			if delta != Vec3::ZERO {
+				let parent = parent_global_transform(parent, &parents);
+				translate_now(&mut xform, &mut global_xform, &parent, delta);
+			}
+			if resumed {
+				if let Some(mut prev_global_xform) = prev_global_xform {
+					**prev_global_xform = *global_xform;
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,321 @@
+//! Floating damage numbers, a common gameplay effect built from this crate's behaviors.
+
+use ab_glyph::{point, Font as _, PxScale, ScaleFont};
+use bevy::{
+	pbr::NotShadowCaster,
+	prelude::*,
+	render::{
+		render_asset::RenderAssetUsages,
+		render_resource::{Extent3d, TextureDimension, TextureFormat},
+	},
+	utils::Duration,
+};
+use nanorand::{Rng, WyRand};
+
+use crate::{
+	cache::AssetCache,
+	color::ParticleColor,
+	material::{Billboard, ParticleExtension, ParticleMaterial},
+	math::lifetime_progress,
+	time::ParticleTime,
+	update::{Drag, SizeOverLifetime, Velocity},
+	InitialGlobalTransform, InitialTransform, Lifetime, ParticleBundle, TimeCreated,
+};
+
+/// What a damage number stands for, which decides its color.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum DamageKind {
+	#[default]
+	Normal,
+	/// Also drawn [DamageNumberStyle::critical_scale] times larger.
+	Critical,
+	Heal,
+	Custom(Color),
+}
+
+/// Spawns a floating number, styled by the [DamageNumberStyle] resource:
+///
+/// ```ignore
+/// commands.trigger(SpawnDamageNumber::new(42.0, hit_point).with_kind(DamageKind::Critical));
+/// ```
+///
+/// Nothing is spawned without a `DamageNumberStyle`, or before its font has loaded.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SpawnDamageNumber {
+	pub value: f32,
+	/// Where the number appears, in world space.
+	pub position: Vec3,
+	pub kind: DamageKind,
+}
+
+impl SpawnDamageNumber {
+	pub fn new(value: f32, position: Vec3) -> Self {
+		Self {
+			value,
+			position,
+			kind: DamageKind::Normal,
+		}
+	}
+
+	pub fn with_kind(self, kind: DamageKind) -> Self {
+		Self { kind, ..self }
+	}
+}
+
+/// How numbers spawned by [SpawnDamageNumber] look and move. They pop up, drift upwards
+/// while slowing down, and fade out.
+#[derive(Resource, Debug, Clone)]
+pub struct DamageNumberStyle {
+	pub font: Handle<Font>,
+	/// Height of the text in world units.
+	pub height: f32,
+	/// Digits shown after the decimal point.
+	pub precision: usize,
+	pub normal: Color,
+	pub critical: Color,
+	pub heal: Color,
+	/// Size of critical hits, as a factor of `height`.
+	pub critical_scale: f32,
+	pub lifetime: Duration,
+	/// Initial upward speed, in units per second.
+	pub rise: f32,
+	/// Largest initial sideways speed, in units per second, picked at random for each
+	/// number so that numbers spawned together don't overlap.
+	pub spread: f32,
+	/// Linear [Drag] slowing the number down.
+	pub drag: f32,
+	/// Scale over the number's lifetime, from `0.0` to `1.0`.
+	pub pop: fn(f32) -> f32,
+	/// Opacity over the number's lifetime, from `0.0` to `1.0`.
+	pub fade: fn(f32) -> f32,
+}
+
+impl DamageNumberStyle {
+	pub fn new(font: Handle<Font>) -> Self {
+		Self {
+			font,
+			height: 0.5,
+			precision: 0,
+			normal: Color::WHITE,
+			critical: Color::srgb(1.0, 0.8, 0.1),
+			heal: Color::srgb(0.3, 1.0, 0.3),
+			critical_scale: 1.5,
+			lifetime: Duration::from_millis(1200),
+			rise: 2.0,
+			spread: 0.5,
+			drag: 2.0,
+			pop: |s| {
+				// Overshoot, then settle at full size.
+				if s < 0.08 {
+					1.4 * s / 0.08
+				} else {
+					1.0 + 0.4 * (1.0 - (s - 0.08) / 0.12).max(0.0)
+				}
+			},
+			fade: |s| 1.0 - ((s - 0.6) / 0.4).clamp(0.0, 1.0),
+		}
+	}
+
+	pub fn color(&self, kind: DamageKind) -> Color {
+		match kind {
+			DamageKind::Normal => self.normal,
+			DamageKind::Critical => self.critical,
+			DamageKind::Heal => self.heal,
+			DamageKind::Custom(color) => color,
+		}
+	}
+}
+
+/// A number spawned by [SpawnDamageNumber]. Numbers showing the same text share their
+/// texture, mesh, and material, which are freed once no number uses them.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component, from_reflect = false)]
+pub struct DamageNumber {
+	pub value: f32,
+	pub kind: DamageKind,
+	/// Color of the text before fading.
+	pub color: Color,
+	/// Opacity over the number's lifetime, from `0.0` to `1.0`.
+	#[reflect(ignore)]
+	pub fade: fn(f32) -> f32,
+}
+
+impl DamageNumber {
+	/// Fades the alpha of each number's [ParticleColor].
+	pub fn fade(mut q: Query<(&Self, &mut ParticleColor, &TimeCreated, &Lifetime)>, t: Res<Time>) {
+		for (number, tint, created, lifetime) in &mut q {
+			let s = lifetime_progress(
+				t.elapsed().saturating_sub(created.0).as_secs_f32(),
+				lifetime.0.as_secs_f32(),
+			);
+			tint.map_unchanged(|tint| &mut tint.color.alpha)
+				.set_if_neq((number.fade)(s));
+		}
+	}
+}
+
+/// What the assets of a damage number are created from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DamageNumberKey {
+	font: AssetId<Font>,
+	text: String,
+	/// Bits of the mesh height, or of the material color.
+	params: [u32; 4],
+}
+
+/// Textures, meshes, and materials shared by damage numbers showing the same text.
+pub type DamageNumberCache<A> = AssetCache<DamageNumberKey, A>;
+
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_damage_numbers(
+	trigger: Trigger<SpawnDamageNumber>,
+	mut cmds: Commands,
+	style: Option<Res<DamageNumberStyle>>,
+	fonts: Res<Assets<Font>>,
+	mut images: ResMut<Assets<Image>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut materials: ResMut<Assets<ParticleMaterial>>,
+	mut image_cache: ResMut<DamageNumberCache<Image>>,
+	mut mesh_cache: ResMut<DamageNumberCache<Mesh>>,
+	mut material_cache: ResMut<DamageNumberCache<ParticleMaterial>>,
+	mut rng: Local<WyRand>,
+	t: Res<ParticleTime>,
+) {
+	let Some(style) = style else {
+		return;
+	};
+	let Some(font) = fonts.get(&style.font) else {
+		return;
+	};
+	let event = trigger.event();
+	let text = format!("{:.*}", style.precision, event.value);
+	let key = |params| DamageNumberKey {
+		font: style.font.id(),
+		text: text.clone(),
+		params,
+	};
+	let color = style.color(event.kind);
+	let material = material_cache.get_or_add(
+		key(color.to_linear().to_f32_array().map(f32::to_bits)),
+		&mut materials,
+		|| {
+			let image = image_cache.get_or_add(key([0; 4]), &mut images, || {
+				let mut image = text_image(font, &text, TEXT_PX);
+				// Kept for numbers of other kinds showing the same text.
+				image.asset_usage = RenderAssetUsages::default();
+				image
+			});
+			ParticleMaterial {
+				base: StandardMaterial {
+					base_color: color,
+					base_color_texture: Some(image),
+					alpha_mode: AlphaMode::Blend,
+					unlit: true,
+					..default()
+				},
+				extension: ParticleExtension {
+					billboard: Billboard::FaceCamera,
+					..default()
+				},
+			}
+		},
+	);
+	let mesh = mesh_cache.get_or_add(key([style.height.to_bits(), 0, 0, 0]), &mut meshes, || {
+		let size = text_size(font, &text, TEXT_PX);
+		Rectangle::new(style.height * size.x / size.y, style.height).into()
+	});
+
+	let scale = match event.kind {
+		DamageKind::Critical => style.critical_scale,
+		_ => 1.0,
+	};
+	let transform = Transform::from_translation(event.position).with_scale(Vec3::splat(scale));
+	let sideways = Vec2::new(rng.generate::<f32>(), rng.generate::<f32>()) * 2.0 - 1.0;
+	let sideways = sideways.clamp_length_max(1.0) * style.spread;
+	cmds.spawn((
+		ParticleBundle {
+			mesh_bundle: MaterialMeshBundle {
+				mesh,
+				material,
+				transform,
+				..default()
+			},
+			lifetime: Lifetime(style.lifetime),
+			time_created: TimeCreated(t.elapsed()),
+			initial_transform: InitialTransform(transform),
+			initial_global_transform: InitialGlobalTransform(transform.into()),
+		},
+		Velocity(Vec3::new(sideways.x, style.rise, sideways.y)),
+		Drag::linear(style.drag),
+		SizeOverLifetime::uniform(style.pop),
+		DamageNumber {
+			value: event.value,
+			kind: event.kind,
+			color,
+			fade: style.fade,
+		},
+		ParticleColor::default(),
+		NotShadowCaster,
+	));
+}
+
+/// Height of damage number textures, enough for the text to stay sharp while it pops up.
+const TEXT_PX: f32 = 64.0;
+
+/// Size of the image [text_image] renders `text` to.
+pub fn text_size(font: &Font, text: &str, px: f32) -> Vec2 {
+	let (_, width, height) = layout(font, text, px);
+	Vec2::new(width as f32, height as f32)
+}
+
+fn layout(font: &Font, text: &str, px: f32) -> (Vec<ab_glyph::Glyph>, u32, u32) {
+	let font = font.font.as_scaled(PxScale::from(px));
+	let mut glyphs = Vec::new();
+	let mut caret = 0.0;
+	let mut prev = None;
+	for c in text.chars() {
+		let mut glyph = font.scaled_glyph(c);
+		if let Some(prev) = prev.replace(glyph.id) {
+			caret += font.kern(prev, glyph.id);
+		}
+		glyph.position = point(caret, font.ascent());
+		caret += font.h_advance(glyph.id);
+		glyphs.push(glyph);
+	}
+	let width = (caret.ceil() as u32).max(1);
+	let height = (font.height().ceil() as u32).max(1);
+	(glyphs, width, height)
+}
+
+/// Renders `text` as white on a transparent image, `px` pixels tall.
+pub fn text_image(font: &Font, text: &str, px: f32) -> Image {
+	let (glyphs, width, height) = layout(font, text, px);
+	let font = font.font.as_scaled(PxScale::from(px));
+	let mut data = [255, 255, 255, 0].repeat((width * height) as usize);
+	for glyph in glyphs {
+		let Some(outlined) = font.font.outline_glyph(glyph) else {
+			continue;
+		};
+		let bounds = outlined.px_bounds();
+		outlined.draw(|x, y, coverage| {
+			let x = bounds.min.x as i32 + x as i32;
+			let y = bounds.min.y as i32 + y as i32;
+			if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+				return;
+			}
+			let alpha = &mut data[(y as u32 * width + x as u32) as usize * 4 + 3];
+			*alpha = (*alpha).max((coverage.clamp(0.0, 1.0) * 255.0) as u8);
+		});
+	}
+	Image::new(
+		Extent3d {
+			width,
+			height,
+			depth_or_array_layers: 1,
+		},
+		TextureDimension::D2,
+		data,
+		TextureFormat::Rgba8UnormSrgb,
+		RenderAssetUsages::RENDER_WORLD,
+	)
+}
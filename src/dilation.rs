@@ -0,0 +1,139 @@
+//! Regions where particles' time runs slower or faster, e.g. slow-motion bubbles.
+
+use bevy::{ecs::query::QueryFilter, prelude::*, utils::Duration};
+
+use crate::{update::particle_dt, ParticleLayers, TimeCreated};
+
+/// Makes time run at `scale` for particles within `radius` of this entity, e.g. a
+/// slow-motion bubble around a spell's impact, blending back to normal over `falloff`
+/// beyond it. Where bubbles overlap, their scales multiply.
+///
+/// Particles inside age at the [ParticleTimeScale] they are given, so their lifetimes and
+/// over-lifetime curves stretch, and every behavior integrating over time, like
+/// [Velocity](crate::update::Velocity) and [Drag](crate::update::Drag), simulates them
+/// for that fraction of each frame. A `scale` of `0.0` freezes them. Particles in a
+/// [ParticleBuffer](crate::buffer::ParticleBuffer) age and move at the scale at their own
+/// position too, with the buffer's [ParticleLayers], but the buffer keeps emitting at its
+/// normal rate.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct TimeDilation {
+	pub scale: f32,
+	pub radius: f32,
+	pub falloff: f32,
+}
+
+impl TimeDilation {
+	pub fn new(scale: f32, radius: f32) -> Self {
+		Self {
+			scale,
+			radius,
+			falloff: 0.0,
+		}
+	}
+
+	pub fn with_falloff(self, falloff: f32) -> Self {
+		Self { falloff, ..self }
+	}
+
+	/// The scale of time at `distance` from the bubble's center.
+	pub fn scale_at(&self, distance: f32) -> f32 {
+		let scale = self.scale.max(0.0);
+		if distance <= self.radius {
+			return scale;
+		}
+		if distance >= self.radius + self.falloff {
+			return 1.0;
+		}
+		let s = (distance - self.radius) / self.falloff;
+		let weight = 1.0 - s * s * (3.0 - 2.0 * s);
+		1.0 + (scale - 1.0) * weight
+	}
+
+	/// Every bubble with its center, to look up scales with [TimeDilation::scale_among].
+	pub(crate) fn collect<'a>(
+		volumes: &'a Query<(&Self, &GlobalTransform, Option<&ParticleLayers>)>,
+	) -> Vec<(Self, Vec3, Option<&'a ParticleLayers>)> {
+		volumes
+			.iter()
+			.map(|(volume, xform, layers)| (*volume, xform.translation(), layers))
+			.collect()
+	}
+
+	/// The scale of time at `position` for a particle on `layers`, from every bubble in
+	/// `volumes` it interacts with.
+	pub(crate) fn scale_among(
+		volumes: &[(Self, Vec3, Option<&ParticleLayers>)],
+		position: Vec3,
+		layers: Option<&ParticleLayers>,
+	) -> f32 {
+		let mut scale = 1.0;
+		for &(volume, center, volume_layers) in volumes {
+			if ParticleLayers::interact(layers, volume_layers) {
+				scale *= volume.scale_at(position.distance(center));
+			}
+		}
+		scale
+	}
+
+	/// Updates the [ParticleTimeScale] of every particle from the bubbles it is in, and
+	/// moves its [TimeCreated] to match, so ages elsewhere can still be taken from it.
+	pub fn tick<F: QueryFilter>(
+		par_cmds: ParallelCommands,
+		mut q: Query<
+			(
+				Entity,
+				&GlobalTransform,
+				&mut TimeCreated,
+				Option<&mut ParticleTimeScale>,
+				Option<&ParticleLayers>,
+			),
+			F,
+		>,
+		volumes: Query<(&Self, &GlobalTransform, Option<&ParticleLayers>)>,
+		scaled: Query<(), (With<ParticleTimeScale>, F)>,
+		t: Res<Time>,
+	) {
+		if volumes.is_empty() && scaled.is_empty() {
+			return;
+		}
+		let volumes = Self::collect(&volumes);
+		q.par_iter_mut()
+			.for_each(|(id, xform, mut created, time_scale, layers)| {
+				let scale = Self::scale_among(&volumes, xform.translation(), layers);
+				match time_scale {
+					Some(mut time_scale) => {
+						time_scale.set_if_neq(ParticleTimeScale(scale));
+					}
+					None if scale != 1.0 => par_cmds.command_scope(|mut cmds| {
+						cmds.entity(id).insert(ParticleTimeScale(scale));
+					}),
+					None => {}
+				}
+				// The part of this frame the particle existed for, before it is shifted.
+				let dt = particle_dt(&t, Some(&*created), None);
+				// Moved without triggering change detection, so the particle isn't queued
+				// again every frame. `handle_lifetimes` catches expiries pushed back as they
+				// come up, and re-queues ones brought forward.
+				let created = &mut created.bypass_change_detection().0;
+				if scale < 1.0 {
+					*created += Duration::from_secs_f32(dt * (1.0 - scale));
+				} else if scale > 1.0 {
+					*created = created.saturating_sub(Duration::from_secs_f32(dt * (scale - 1.0)));
+				}
+			});
+	}
+}
+
+/// How fast time runs for this particle, relative to the clock simulating it. Set every
+/// frame from the [TimeDilation] bubbles it is in, and read by
+/// [particle_dt].
+#[derive(Debug, Clone, Copy, PartialEq, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
+pub struct ParticleTimeScale(pub f32);
+
+impl Default for ParticleTimeScale {
+	fn default() -> Self {
+		Self(1.0)
+	}
+}
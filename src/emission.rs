@@ -0,0 +1,313 @@
+#[cfg(feature = "text")]
+use ab_glyph::{Font as _, Outline, OutlineCurve, PxScale};
+#[cfg(feature = "render")]
+use bevy::{
+	color::Luminance,
+	render::{mesh::VertexAttributeValues, render_resource::TextureFormat},
+};
+use bevy::{ecs::system::EntityCommands, prelude::*};
+use nanorand::{Rng, WyRand};
+
+#[cfg(feature = "render")]
+use crate::ParticleFactory;
+use crate::TimeCreated;
+
+/// Like [ParticleFactory], but also receives the color of the emitting point.
+pub trait ColoredParticleFactory
+where
+	for<'w, 's, 'a> Self: FnMut(&'a mut Commands<'w, 's>, &GlobalTransform, Color, TimeCreated) -> EntityCommands<'a>
+		+ Send
+		+ Sync
+		+ 'static,
+{
+}
+impl<F> ColoredParticleFactory for F where
+	for<'w, 's, 'a> F: FnMut(&'a mut Commands<'w, 's>, &GlobalTransform, Color, TimeCreated) -> EntityCommands<'a>
+		+ Send
+		+ Sync
+		+ 'static
+{
+}
+
+/// Which pixels of an image emit particles.
+#[derive(Debug, Clone, Copy, Reflect)]
+pub enum PixelMask {
+	/// Pixels with alpha above the threshold.
+	Alpha(f32),
+	/// Pixels with linear luminance above the threshold.
+	Luminance(f32),
+}
+
+/// Emits particles from the pixels of an image laid out on the spewer's local XY plane,
+/// centered on the spewer, with each particle receiving its pixel's color.
+#[cfg(feature = "render")]
+#[derive(Debug, Clone)]
+pub struct ImageEmitter {
+	pub pixels: Vec<(Vec2, Color)>,
+}
+
+#[cfg(feature = "render")]
+impl ImageEmitter {
+	/// `size` is the size of the whole image in the spewer's local space.
+	///
+	/// Returns `None` if the image can't be converted to RGBA8.
+	pub fn new(image: &Image, mask: PixelMask, size: Vec2) -> Option<Self> {
+		let image = image.convert(TextureFormat::Rgba8UnormSrgb)?;
+		let dims = image.size();
+		let pixel_size = size / dims.as_vec2();
+		let pixels = image
+			.data
+			.chunks_exact(4)
+			.enumerate()
+			.filter_map(|(i, px)| {
+				let color = Color::srgba_u8(px[0], px[1], px[2], px[3]);
+				let emits = match mask {
+					PixelMask::Alpha(threshold) => color.alpha() > threshold,
+					PixelMask::Luminance(threshold) => color.luminance() > threshold,
+				};
+				emits.then(|| {
+					let (x, y) = (i as u32 % dims.x, i as u32 / dims.x);
+					// Image rows go top to bottom, but +Y is up.
+					let px = Vec2::new(x as f32 + 0.5, (dims.y - y) as f32 - 0.5);
+					(px * pixel_size - size * 0.5, color)
+				})
+			})
+			.collect();
+		Some(Self { pixels })
+	}
+
+	/// Picks a random emitting pixel, returning its position and color.
+	pub fn sample(&self, rng: &mut WyRand) -> Option<(Vec2, Color)> {
+		if self.pixels.is_empty() {
+			return None;
+		}
+		Some(self.pixels[rng.generate_range(0..self.pixels.len())])
+	}
+
+	/// Wraps `spawn` in a [ParticleFactory] that offsets each particle to a random
+	/// emitting pixel and passes that pixel's color along.
+	pub fn factory(
+		self,
+		seed: u64,
+		mut spawn: impl ColoredParticleFactory,
+	) -> impl ParticleFactory {
+		let mut rng = WyRand::new_seed(seed);
+		move |cmds: &mut Commands, xform: &GlobalTransform, t: TimeCreated| {
+			let (pos, color) = self.sample(&mut rng).unwrap_or((Vec2::ZERO, Color::NONE));
+			let xform = xform.mul_transform(Transform::from_translation(pos.extend(0.0)));
+			spawn(cmds, &xform, color, t)
+		}
+	}
+}
+
+/// Which points of a string's glyphs emit particles.
+#[cfg(feature = "text")]
+#[derive(Debug, Clone, Copy, Reflect)]
+pub enum GlyphSampling {
+	/// Points along the outlines of the glyphs, `spacing` apart.
+	Outline { spacing: f32 },
+	/// Points inside the glyphs, on a grid of cells `spacing` wide.
+	Fill { spacing: f32 },
+}
+
+/// Emits particles from the glyphs of a string laid out on the spewer's local XY plane,
+/// centered on the spewer, e.g. for titles or damage numbers that dissolve into particles.
+///
+/// Convert it into a [PointCloud] to have particles gather into the text instead.
+#[cfg(feature = "text")]
+#[derive(Debug, Clone)]
+pub struct GlyphEmitter {
+	pub points: Vec<Vec2>,
+}
+
+#[cfg(feature = "text")]
+impl GlyphEmitter {
+	/// `height` is the height of a line of text in the spewer's local space. `text` is
+	/// split into lines at line breaks. Nothing is sampled if the spacing isn't positive.
+	pub fn new(font: &Font, text: &str, height: f32, sampling: GlyphSampling) -> Self {
+		let font = &font.font;
+		let units = height / font.height_unscaled();
+		let line_height = (font.height_unscaled() + font.line_gap_unscaled()) * units;
+		let mut points = Vec::new();
+		for (line, text) in text.lines().enumerate() {
+			let mut caret = Vec2::new(0.0, -(line as f32) * line_height);
+			let mut prev = None;
+			for c in text.chars() {
+				let id = font.glyph_id(c);
+				if let Some(prev) = prev.replace(id) {
+					caret.x += font.kern_unscaled(prev, id) * units;
+				}
+				let origin = caret;
+				caret.x += font.h_advance_unscaled(id) * units;
+				match sampling {
+					GlyphSampling::Outline { spacing } => {
+						if let Some(outline) = font.outline(id) {
+							sample_outline(&outline, origin, units, spacing, &mut points);
+						}
+					}
+					GlyphSampling::Fill { spacing } if spacing > 0.0 => {
+						let glyph = id.with_scale(PxScale::from(height / spacing));
+						let Some(outlined) = font.outline_glyph(glyph) else {
+							continue;
+						};
+						let bounds = outlined.px_bounds();
+						outlined.draw(|x, y, coverage| {
+							if coverage >= 0.5 {
+								// Pixel rows go top to bottom, but +Y is up.
+								let px = Vec2::new(
+									bounds.min.x + x as f32 + 0.5,
+									-(bounds.min.y + y as f32 + 0.5),
+								);
+								points.push(origin + px * spacing);
+							}
+						});
+					}
+					GlyphSampling::Fill { .. } => {}
+				}
+			}
+		}
+
+		let (min, max) = points
+			.iter()
+			.fold((Vec2::MAX, Vec2::MIN), |(min, max), &p| {
+				(min.min(p), max.max(p))
+			});
+		let center = (min + max) * 0.5;
+		for p in &mut points {
+			*p -= center;
+		}
+		Self { points }
+	}
+
+	pub fn sample(&self, rng: &mut WyRand) -> Option<Vec2> {
+		if self.points.is_empty() {
+			return None;
+		}
+		Some(self.points[rng.generate_range(0..self.points.len())])
+	}
+
+	/// Wraps `spawn` in a [ParticleFactory] that offsets each particle to a random point
+	/// of the text.
+	pub fn factory(self, seed: u64, mut spawn: impl ParticleFactory) -> impl ParticleFactory {
+		let mut rng = WyRand::new_seed(seed);
+		move |cmds: &mut Commands, xform: &GlobalTransform, t: TimeCreated| {
+			let pos = self.sample(&mut rng).unwrap_or_default();
+			let xform = xform.mul_transform(Transform::from_translation(pos.extend(0.0)));
+			spawn(cmds, &xform, t)
+		}
+	}
+}
+
+/// Adds points `spacing` apart along the curves of `outline`, which is in font units.
+#[cfg(feature = "text")]
+fn sample_outline(
+	outline: &Outline,
+	origin: Vec2,
+	units: f32,
+	spacing: f32,
+	points: &mut Vec<Vec2>,
+) {
+	/// Line segments each curve is measured with.
+	const STEPS: u32 = 16;
+
+	if spacing <= 0.0 {
+		return;
+	}
+	let to_local = |p: ab_glyph::Point| origin + Vec2::new(p.x, p.y) * units;
+	let mut contour_end = None;
+	let mut travelled = 0.0;
+	for curve in &outline.curves {
+		let at = |t: f32| {
+			let s = 1.0 - t;
+			match *curve {
+				OutlineCurve::Line(a, b) => to_local(a).lerp(to_local(b), t),
+				OutlineCurve::Quad(a, b, c) => {
+					to_local(a) * s * s + to_local(b) * 2.0 * s * t + to_local(c) * t * t
+				}
+				OutlineCurve::Cubic(a, b, c, d) => {
+					to_local(a) * s * s * s
+						+ to_local(b) * 3.0 * s * s * t
+						+ to_local(c) * 3.0 * s * t * t
+						+ to_local(d) * t * t * t
+				}
+			}
+		};
+		let mut prev = at(0.0);
+		if contour_end != Some(prev) {
+			// A new contour starts with a point of its own.
+			points.push(prev);
+			travelled = 0.0;
+		}
+		for i in 1..=STEPS {
+			let next = at(i as f32 / STEPS as f32);
+			let mut remaining = prev.distance(next);
+			while travelled + remaining >= spacing {
+				prev = prev.lerp(next, (spacing - travelled) / remaining);
+				points.push(prev);
+				remaining = prev.distance(next);
+				travelled = 0.0;
+			}
+			travelled += remaining;
+			prev = next;
+		}
+		contour_end = Some(prev);
+	}
+}
+
+/// A set of points for particles to be assigned to, e.g. as [MorphTarget](crate::update::MorphTarget)s.
+#[derive(Debug, Clone, Default)]
+pub struct PointCloud {
+	pub points: Vec<Vec3>,
+	next: usize,
+}
+
+impl PointCloud {
+	pub fn new(points: Vec<Vec3>) -> Self {
+		Self { points, next: 0 }
+	}
+
+	/// Uses the vertex positions of `mesh`. Returns `None` if it has no `Float32x3` positions.
+	#[cfg(feature = "render")]
+	pub fn from_mesh(mesh: &Mesh) -> Option<Self> {
+		match mesh.attribute(Mesh::ATTRIBUTE_POSITION)? {
+			VertexAttributeValues::Float32x3(positions) => Some(Self::new(
+				positions.iter().copied().map(Vec3::from).collect(),
+			)),
+			_ => None,
+		}
+	}
+
+	/// Returns the points in order, wrapping around once all have been used.
+	pub fn next_point(&mut self) -> Option<Vec3> {
+		let point = *self.points.get(self.next % self.points.len().max(1))?;
+		self.next = (self.next + 1) % self.points.len();
+		Some(point)
+	}
+
+	pub fn random_point(&self, rng: &mut WyRand) -> Option<Vec3> {
+		if self.points.is_empty() {
+			return None;
+		}
+		Some(self.points[rng.generate_range(0..self.points.len())])
+	}
+}
+
+#[cfg(feature = "render")]
+impl From<&ImageEmitter> for PointCloud {
+	fn from(emitter: &ImageEmitter) -> Self {
+		Self::new(
+			emitter
+				.pixels
+				.iter()
+				.map(|(pos, _)| pos.extend(0.0))
+				.collect(),
+		)
+	}
+}
+
+#[cfg(feature = "text")]
+impl From<&GlyphEmitter> for PointCloud {
+	fn from(emitter: &GlyphEmitter) -> Self {
+		Self::new(emitter.points.iter().map(|pos| pos.extend(0.0)).collect())
+	}
+}
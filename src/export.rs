@@ -0,0 +1,199 @@
+//! Offline recording of effects to per-frame particle transforms, for cutscene tooling or
+//! external renderers.
+
+use std::{
+	io::{self, Read, Write},
+	path::Path,
+	time::Duration,
+};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy, utils::HashMap};
+
+use crate::{lifecycle::ParticleOf, Deterministic, DeterministicParticlesPlugin};
+
+/// The world-space transforms of every particle of an effect, sampled at a fixed frame
+/// rate by [EffectRecording::record].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct EffectRecording {
+	/// Length of each frame in seconds.
+	pub frame_time: f32,
+	pub frames: u32,
+	/// One per particle, in the order they were spawned.
+	pub tracks: Vec<ParticleTrack>,
+}
+
+/// The transforms of one particle on each frame it was alive for.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParticleTrack {
+	/// Frame of the first transform.
+	pub first_frame: u32,
+	pub transforms: Vec<Transform>,
+}
+
+impl ParticleTrack {
+	/// The particle's transform on `frame`, if it was alive.
+	pub fn get(&self, frame: u32) -> Option<&Transform> {
+		let i = frame.checked_sub(self.first_frame)?;
+		self.transforms.get(i as usize)
+	}
+}
+
+/// Identifies files written by [EffectRecording::write].
+const MAGIC: &[u8; 4] = b"SBPR";
+const VERSION: u32 = 1;
+
+impl EffectRecording {
+	/// Runs an effect in a headless app of its own, stepping it `frames` times by
+	/// `frame_time`, and records every particle of the spewer `setup` returns.
+	///
+	/// The spewer is marked [Deterministic] and simulated by [DeterministicParticlesPlugin],
+	/// so give it [Spewer::seeded](crate::Spewer::seeded) for the recording to be the same
+	/// every time. `setup` can add anything else the effect needs, e.g. colliders.
+	pub fn record(
+		frames: u32,
+		frame_time: Duration,
+		setup: impl FnOnce(&mut World) -> Entity,
+	) -> Self {
+		let mut app = App::new();
+		app.add_plugins((
+			MinimalPlugins,
+			TransformPlugin,
+			HierarchyPlugin,
+			DeterministicParticlesPlugin::new(Update),
+		))
+		.insert_resource(TimeUpdateStrategy::ManualDuration(frame_time));
+		let spewer = setup(app.world_mut());
+		app.world_mut().entity_mut(spewer).insert(Deterministic);
+		// The first update starts the clock without advancing it.
+		app.update();
+
+		let mut tracks = Vec::<ParticleTrack>::new();
+		let mut tracked = HashMap::<Entity, usize>::new();
+		let mut particles = app
+			.world_mut()
+			.query::<(Entity, &ParticleOf, &GlobalTransform)>();
+		for frame in 0..frames {
+			app.update();
+			let world = app.world();
+			let mut spawned = particles
+				.iter(world)
+				.filter(|(id, of, _)| of.0 == spewer && !tracked.contains_key(id))
+				.map(|(id, ..)| id)
+				.collect::<Vec<_>>();
+			// Entities are usually spawned in increasing order, and always in the same one.
+			spawned.sort_by_key(|id| id.to_bits());
+			for id in spawned {
+				tracked.insert(id, tracks.len());
+				tracks.push(ParticleTrack {
+					first_frame: frame,
+					transforms: Vec::new(),
+				});
+			}
+			for (id, _, xform) in particles.iter(world) {
+				if let Some(&track) = tracked.get(&id) {
+					tracks[track].transforms.push(xform.compute_transform());
+				}
+			}
+		}
+		Self {
+			frame_time: frame_time.as_secs_f32(),
+			frames,
+			tracks,
+		}
+	}
+
+	/// Writes the recording in a simple little-endian binary format: a header of
+	/// `b"SBPR"`, the format version, `frame_time`, `frames`, and the number of tracks,
+	/// then each track as its first frame, its number of transforms, and those transforms
+	/// as translation, rotation, and scale, all as `u32`s and `f32`s.
+	pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+		writer.write_all(MAGIC)?;
+		writer.write_all(&VERSION.to_le_bytes())?;
+		writer.write_all(&self.frame_time.to_le_bytes())?;
+		writer.write_all(&self.frames.to_le_bytes())?;
+		writer.write_all(&(self.tracks.len() as u32).to_le_bytes())?;
+		for track in &self.tracks {
+			writer.write_all(&track.first_frame.to_le_bytes())?;
+			writer.write_all(&(track.transforms.len() as u32).to_le_bytes())?;
+			for xform in &track.transforms {
+				let floats = xform
+					.translation
+					.to_array()
+					.into_iter()
+					.chain(xform.rotation.to_array())
+					.chain(xform.scale.to_array());
+				for f in floats {
+					writer.write_all(&f.to_le_bytes())?;
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Reads a recording written by [EffectRecording::write].
+	pub fn read(mut reader: impl Read) -> io::Result<Self> {
+		let mut magic = [0; 4];
+		reader.read_exact(&mut magic)?;
+		if &magic != MAGIC {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				"not an effect recording",
+			));
+		}
+		let mut word = || -> io::Result<[u8; 4]> {
+			let mut word = [0; 4];
+			reader.read_exact(&mut word)?;
+			Ok(word)
+		};
+		let version = u32::from_le_bytes(word()?);
+		if version != VERSION {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("unsupported effect recording version {version}"),
+			));
+		}
+		let frame_time = f32::from_le_bytes(word()?);
+		let frames = u32::from_le_bytes(word()?);
+		let track_count = u32::from_le_bytes(word()?);
+		let mut tracks = Vec::new();
+		for _ in 0..track_count {
+			let first_frame = u32::from_le_bytes(word()?);
+			let len = u32::from_le_bytes(word()?);
+			let mut transforms = Vec::new();
+			for _ in 0..len {
+				let mut floats = [0.0; 10];
+				for f in &mut floats {
+					*f = f32::from_le_bytes(word()?);
+				}
+				transforms.push(Transform {
+					translation: Vec3::from_slice(&floats[0..3]),
+					rotation: Quat::from_slice(&floats[3..7]),
+					scale: Vec3::from_slice(&floats[7..10]),
+				});
+			}
+			tracks.push(ParticleTrack {
+				first_frame,
+				transforms,
+			});
+		}
+		Ok(Self {
+			frame_time,
+			frames,
+			tracks,
+		})
+	}
+
+	/// Writes the recording to a file at `path`. See [EffectRecording::write].
+	pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		let file = std::fs::File::create(path)?;
+		let mut writer = io::BufWriter::new(file);
+		self.write(&mut writer)?;
+		writer.flush()
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+		Self::read(io::BufReader::new(std::fs::File::open(path)?))
+	}
+}
@@ -0,0 +1,153 @@
+//! Flickering brightness for candle flames, electrical sparks, and damaged lights.
+
+#[cfg(feature = "render")]
+use bevy::utils::HashMap;
+use bevy::{ecs::query::QueryFilter, prelude::*};
+
+use crate::{noise, TimeCreated};
+
+/// How a [Flicker]'s intensity changes over time.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum FlickerMode {
+	/// Smoothly wavers, changing direction about `frequency` times per second.
+	Noise { frequency: f32 },
+	/// Jumps to a new random intensity `rate` times per second, e.g. for sparks or a
+	/// failing fluorescent tube.
+	RandomStep { rate: f32 },
+}
+
+/// Modulates the alpha and emissive of the particle's material over time, independently
+/// for each particle.
+///
+/// The material must not be shared with other particles, or they all show whichever
+/// intensity was applied last. The material's alpha and emissive when it first flickers
+/// are the ones modulated. [ParticleBuffer](crate::buffer::ParticleBuffer)s apply a
+/// `Flicker` to the alpha of each of their particles instead.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct Flicker {
+	pub mode: FlickerMode,
+	/// Lowest intensity, as a factor of the material's own.
+	pub min: f32,
+	/// Highest intensity, as a factor of the material's own.
+	pub max: f32,
+	/// Modulate the alpha of the material's base color.
+	pub alpha: bool,
+	/// Modulate the material's emissive color.
+	pub emissive: bool,
+	/// Intensity at the last update.
+	pub intensity: f32,
+}
+
+impl Flicker {
+	pub fn new(mode: FlickerMode, min: f32, max: f32) -> Self {
+		Self {
+			mode,
+			min,
+			max,
+			alpha: true,
+			emissive: true,
+			intensity: max,
+		}
+	}
+
+	pub fn noise(frequency: f32, min: f32, max: f32) -> Self {
+		Self::new(FlickerMode::Noise { frequency }, min, max)
+	}
+
+	pub fn random_step(rate: f32, min: f32, max: f32) -> Self {
+		Self::new(FlickerMode::RandomStep { rate }, min, max)
+	}
+
+	/// Intensity at `secs` into the particle's lifetime, seeded by `seed`.
+	pub fn sample(&self, seed: u32, secs: f32) -> f32 {
+		let n = match self.mode {
+			FlickerMode::Noise { frequency } => noise::gradient_noise(seed, secs * frequency),
+			FlickerMode::RandomStep { rate } => {
+				noise::random_step(seed, (secs * rate).floor() as i32)
+			}
+		};
+		self.min + (self.max - self.min) * (n * 0.5 + 0.5).clamp(0.0, 1.0)
+	}
+
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<(Entity, &mut Self, Option<&TimeCreated>), F>,
+		t: Res<Time>,
+	) {
+		q.par_iter_mut().for_each(|(id, item, created)| {
+			let secs = match created {
+				Some(created) => t.elapsed().saturating_sub(created.0).as_secs_f32(),
+				None => t.elapsed_seconds_wrapped(),
+			};
+			let intensity = item.sample(id.index(), secs);
+			item.map_unchanged(|item| &mut item.intensity)
+				.set_if_neq(intensity);
+		});
+	}
+
+	/// Applies the intensity of each changed [Flicker] to its material.
+	#[cfg(feature = "render")]
+	pub fn apply_to_materials<M: FlickerMaterial>(
+		q: Query<(&Self, &Handle<M>), Changed<Self>>,
+		mut materials: ResMut<Assets<M>>,
+		mut originals: Local<HashMap<AssetId<M>, (f32, LinearRgba)>>,
+	) {
+		for (flicker, handle) in &q {
+			let Some(material) = materials.get_mut(handle) else {
+				continue;
+			};
+			let material = material.standard_mut();
+			let &mut (alpha, emissive) = originals
+				.entry(handle.id())
+				.or_insert_with(|| (material.base_color.alpha(), material.emissive));
+			if flicker.alpha {
+				material.base_color.set_alpha(alpha * flicker.intensity);
+			}
+			if flicker.emissive {
+				material.emissive = emissive * flicker.intensity;
+			}
+		}
+	}
+}
+
+/// A material whose `StandardMaterial` properties a [Flicker] can modulate.
+#[cfg(feature = "render")]
+pub trait FlickerMaterial: Material {
+	/// Whether the material passes [ParticleUserData](crate::ParticleUserData) on to its
+	/// shaders, so [MaterialVariants](crate::color::MaterialVariants) are made for it.
+	const USER_DATA: bool = false;
+
+	fn standard(&self) -> &StandardMaterial;
+
+	fn standard_mut(&mut self) -> &mut StandardMaterial;
+
+	fn set_user_data(&mut self, _data: Vec4) {}
+}
+
+#[cfg(feature = "render")]
+impl FlickerMaterial for StandardMaterial {
+	fn standard(&self) -> &StandardMaterial {
+		self
+	}
+
+	fn standard_mut(&mut self) -> &mut StandardMaterial {
+		self
+	}
+}
+
+#[cfg(feature = "render")]
+impl FlickerMaterial for crate::material::ParticleMaterial {
+	const USER_DATA: bool = true;
+
+	fn standard(&self) -> &StandardMaterial {
+		&self.base
+	}
+
+	fn standard_mut(&mut self) -> &mut StandardMaterial {
+		&mut self.base
+	}
+
+	fn set_user_data(&mut self, data: Vec4) {
+		self.extension.user_data = data;
+	}
+}
@@ -0,0 +1,85 @@
+//! Rollback support for [bevy_ggrs].
+
+use bevy::prelude::*;
+use bevy_ggrs::{GgrsApp, Strategy};
+
+#[cfg(feature = "collision")]
+use crate::collision::ParticleCollision;
+use crate::{
+	color::{ColorOverLifetime, ParticleColor},
+	flicker::Flicker,
+	lifecycle::*,
+	update::*,
+	Deterministic, InitialGlobalTransform, InitialTransform, Lifetime, ParticleLayers,
+	PreviousGlobalTransform, PreviousTransform, Spewer, SpewerState, TimeCreated,
+};
+
+/// Rolls back the [SpewerState] of a [Spewer] in place, keeping its factory.
+///
+/// If a rolled-back spewer was removed in the meantime, it is restored with the
+/// default factory.
+pub struct SpewerStrategy;
+
+impl Strategy for SpewerStrategy {
+	type Target = Spewer;
+	type Stored = SpewerState;
+
+	fn store(target: &Spewer) -> SpewerState {
+		target.state()
+	}
+
+	fn load(stored: &SpewerState) -> Spewer {
+		let mut spewer = Spewer::default();
+		spewer.set_state(stored.clone());
+		spewer
+	}
+
+	fn update(target: &mut Spewer, stored: &SpewerState) {
+		target.set_state(stored.clone());
+	}
+}
+
+/// Registers spewers and the built-in particle components for rollback.
+///
+/// `Transform` and `GlobalTransform` are not registered, since most games already roll
+/// them back themselves.
+/// Particles must be given `bevy_ggrs::Rollback` by their factory (`add_rollback()`).
+pub struct RollbackParticlesPlugin;
+
+impl Plugin for RollbackParticlesPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_plugins(bevy_ggrs::ComponentSnapshotPlugin::<SpewerStrategy>::default())
+			.rollback_component_with_copy::<Deterministic>()
+			.rollback_component_with_copy::<PreviousTransform>()
+			.rollback_component_with_copy::<PreviousGlobalTransform>()
+			.rollback_component_with_copy::<TimeCreated>()
+			.rollback_component_with_clone::<Lifetime>()
+			.rollback_component_with_copy::<InitialTransform>()
+			.rollback_component_with_copy::<InitialGlobalTransform>()
+			.rollback_component_with_copy::<Velocity>()
+			.rollback_component_with_copy::<Linear>()
+			.rollback_component_with_copy::<AngularVelocity>()
+			.rollback_component_with_copy::<MulScale>()
+			.rollback_component_with_copy::<AddScale>()
+			.rollback_component_with_copy::<TargetScale>()
+			.rollback_component_with_copy::<SizeOverLifetime>()
+			.rollback_component_with_copy::<TargetTransform>()
+			.rollback_component_with_copy::<MorphTarget>()
+			.rollback_component_with_copy::<FollowTarget>()
+			.rollback_component_with_copy::<GravityScale>()
+			.rollback_component_with_copy::<Drag>()
+			.rollback_component_with_copy::<Sleep>()
+			.rollback_component_with_copy::<Sleeping>()
+			.rollback_component_with_copy::<DistanceLifetime>()
+			.rollback_component_with_copy::<InheritVelocityOverLifetime>()
+			.rollback_component_with_copy::<Alignment>()
+			.rollback_component_with_copy::<Flicker>()
+			.rollback_component_with_copy::<ParticleColor>()
+			.rollback_component_with_copy::<ColorOverLifetime>()
+			.rollback_component_with_copy::<ParticleLayers>()
+			.rollback_component_with_copy::<ParticleOf>()
+			.rollback_component_with_copy::<DespawnWithSpewer>();
+		#[cfg(feature = "collision")]
+		app.rollback_component_with_clone::<ParticleCollision>();
+	}
+}
@@ -1,37 +1,413 @@
 #![allow(clippy::type_complexity)]
+#[cfg(feature = "render")]
+use bevy::render::view::{RenderLayers, VisibilitySystems};
 use bevy::{
-	ecs::{query::QueryData, system::EntityCommands},
+	ecs::{
+		component::{ComponentHooks, StorageType},
+		entity::EntityHashMap,
+		intern::Interned,
+		query::{QueryData, QueryFilter, QueryItem},
+		schedule::{ScheduleLabel, SystemConfigs},
+		system::{EntityCommand, EntityCommands},
+	},
 	prelude::*,
-	utils::{Duration, Instant},
+	utils::Duration,
 };
 use nanorand::{Rng, WyRand};
+use std::{borrow::Cow, cmp::Reverse, collections::BinaryHeap, ops::RangeInclusive};
 
+#[cfg(feature = "assets")]
+pub mod baked;
+#[cfg(feature = "trails")]
+pub mod beam;
+pub mod budget;
+pub mod buffer;
+#[cfg(feature = "assets")]
+pub mod cache;
+#[cfg(feature = "capture")]
+pub mod capture;
+#[cfg(feature = "render")]
+pub mod collect;
+#[cfg(feature = "collision")]
+pub mod collision;
+pub mod color;
+#[cfg(feature = "cursor")]
+pub mod cursor;
+#[cfg(feature = "text")]
+pub mod damage;
+pub mod dilation;
+pub mod emission;
+#[cfg(feature = "export")]
+pub mod export;
+pub mod flicker;
+#[cfg(feature = "ggrs")]
+pub mod ggrs;
+pub mod library;
+pub mod lifecycle;
+#[cfg(feature = "render")]
+pub mod lod;
+#[cfg(feature = "render")]
+pub mod material;
+pub mod math;
+#[cfg(feature = "render")]
+pub mod mesh;
+pub mod noise;
+#[cfg(feature = "render")]
+pub mod overdraw;
+pub mod rate;
+pub mod seek;
+pub mod sky;
+pub mod snapshot;
+#[cfg(feature = "render")]
+pub mod template;
+pub mod time;
+#[cfg(feature = "trails")]
+pub mod trail;
 pub mod update;
+pub mod validate;
+#[cfg(feature = "assets")]
+pub mod vector_field;
+pub mod water;
+pub mod weather;
+use lifecycle::*;
+use time::*;
 use update::*;
+#[cfg(feature = "assets")]
+use vector_field::*;
 
+/// Simulates and renders particles. Spewers and particles marked [Deterministic] are
+/// left to [DeterministicParticlesPlugin]; everything else is treated as cosmetic and
+/// simulated in [ParticlePreUpdate] and [ParticleUpdate], on the [ParticleTime] clock.
+///
+/// Particles are simulated on the CPU and drawn as regular meshes, which Bevy batches
+/// into instanced draws. No compute shaders are used, so this also works on WebGL2.
 pub struct ParticlesPlugin;
 
 impl Plugin for ParticlesPlugin {
 	fn build(&self, app: &mut App) {
-		app.add_systems(PreUpdate, spawn_particles)
+		app.add_plugins(time::ParticleTimePlugin);
+		if !app.is_plugin_added::<seek::SeekPlugin>() {
+			app.add_plugins(seek::SeekPlugin);
+		}
+		#[cfg(feature = "render")]
+		app.add_plugins((
+			material::ParticleMaterialPlugin,
+			buffer::ParticleBufferRenderPlugin,
+		))
+		.add_systems(
+			ParticleUpdate,
+			(
+				baked::BakedLoopPlayer::<StandardMaterial>::tick,
+				baked::BakedLoopPlayer::<material::ParticleMaterial>::tick,
+				collect::CollectToScreen::tick,
+			),
+		)
+		.add_systems(ParticlePreUpdate, VectorFieldTexture::bake)
+		.add_systems(
+			PostUpdate,
+			(
+				lod::ParticleLod::tick
+					.after(TransformSystem::TransformPropagate)
+					.before(color::apply_material_variants::<StandardMaterial>)
+					.before(color::apply_material_variants::<material::ParticleMaterial>),
+				flicker::Flicker::apply_to_materials::<StandardMaterial>,
+				flicker::Flicker::apply_to_materials::<material::ParticleMaterial>,
+				(
+					color::apply_material_variants::<StandardMaterial>,
+					color::MaterialVariants::<StandardMaterial>::clean_up,
+					overdraw::OverdrawView::apply::<StandardMaterial>,
+				)
+					.chain(),
+				(
+					color::apply_material_variants::<material::ParticleMaterial>,
+					color::MaterialVariants::<material::ParticleMaterial>::clean_up,
+					overdraw::OverdrawView::apply::<material::ParticleMaterial>,
+				)
+					.chain(),
+				mesh::ParticleMeshes::clean_up_unused,
+				SpewerVisibility::hide_particles
+					.after(VisibilitySystems::VisibilityPropagate)
+					.before(VisibilitySystems::CheckVisibility),
+			),
+		)
+		.add_systems(
+			ParticlePostUpdate,
+			weather::WeatherOccluder::tick.after(sky::SkyParallax::tick),
+		)
+		.init_resource::<color::MaterialVariants<StandardMaterial>>()
+		.init_resource::<color::MaterialVariants<material::ParticleMaterial>>()
+		.init_resource::<mesh::ParticleMeshes>()
+		.init_resource::<overdraw::OverdrawView>()
+		.init_resource::<overdraw::OverdrawMaterials<StandardMaterial>>()
+		.init_resource::<overdraw::OverdrawMaterials<material::ParticleMaterial>>()
+		.register_type::<mesh::ParticleMesh>()
+		.register_type::<SpewerVisibility>()
+		.register_type::<collect::CollectToScreen>()
+		.register_type::<VectorFieldTexture>()
+		.register_type::<color::ParticleFade>()
+		.register_type::<lod::ParticleLod>()
+		.register_type::<lod::LodImposter>()
+		.register_type::<overdraw::OverdrawView>()
+		.register_type::<template::MaterialOverrides>();
+		#[cfg(feature = "trails")]
+		app.add_systems(
+			ParticlePostUpdate,
+			(
+				(trail::Trail::record, trail::Trail::update_meshes).chain(),
+				(trail::SweepTrail::record, trail::SweepTrail::update_meshes).chain(),
+				(beam::Beam::tick, beam::Beam::update_meshes).chain(),
+			)
+				.after(sky::SkyParallax::tick),
+		)
+		.register_type::<trail::Trail>()
+		.register_type::<trail::SweepTrail>()
+		.register_type::<beam::Beam>();
+		#[cfg(feature = "cursor")]
+		app.add_systems(
+			ParticlePreUpdate,
+			cursor::FollowCursor::tick.before(spawn_particles::<Without<Deterministic>>),
+		)
+		.register_type::<cursor::FollowCursor>();
+		#[cfg(feature = "text")]
+		app.observe(damage::spawn_damage_numbers)
+			.add_systems(ParticleUpdate, damage::DamageNumber::fade)
 			.add_systems(
-				Update,
+				PostUpdate,
 				(
-					Linear::tick,
-					Angular::tick,
-					MulScale::tick,
-					AddScale::tick,
-					TargetScale::tick,
-					TargetTransform::tick,
-					DynParticleUpdate::tick,
-					handle_lifetimes,
+					damage::DamageNumberCache::<Image>::clean_up_unused,
+					damage::DamageNumberCache::<Mesh>::clean_up_unused,
+					damage::DamageNumberCache::<material::ParticleMaterial>::clean_up_unused,
 				),
 			)
+			.init_resource::<damage::DamageNumberCache<Image>>()
+			.init_resource::<damage::DamageNumberCache<Mesh>>()
+			.init_resource::<damage::DamageNumberCache<material::ParticleMaterial>>()
+			.register_type::<damage::DamageNumber>();
+		app.add_plugins(rate::SimulationRatePlugin)
+			.add_systems(
+				ParticlePreUpdate,
+				(
+					weather::WeatherVolume::fill,
+					spawn_particles::<Without<Deterministic>>,
+					budget::ParticleBudget::enforce,
+				)
+					.chain(),
+			)
+			.observe(handle_emit_burst)
+			.observe(handle_clear_particles)
+			.add_systems(
+				ParticleUpdate,
+				simulation_systems::<(Without<Deterministic>, Without<rate::ReducedRate>)>(),
+			)
+			.add_systems(
+				ParticleUpdate,
+				(
+					buffer::ParticleBuffer::tick,
+					ParticleCount::update,
+					despawn_with_spewers,
+					despawn_finished_spewers,
+				),
+			)
+			.add_systems(
+				ParticlePostUpdate,
+				(
+					weather::WeatherVolume::tick,
+					sky::SkyParallax::tick,
+					PositionNoise::tick,
+				)
+					.chain(),
+			)
+			.init_resource::<ParticleGravity>()
+			.init_resource::<library::ParticleEffectLibrary>()
+			.register_type::<Spewer>()
+			.register_type::<TimeCreated>()
+			.register_type::<Lifetime>()
+			.register_type::<InitialTransform>()
+			.register_type::<InitialGlobalTransform>()
+			.register_type::<Deterministic>()
+			.register_type::<ParticleGravity>()
+			.register_type::<color::ParticleColor>()
+			.register_type::<color::SpewerTint>()
+			.register_type::<EmissionOffset>()
+			.register_type::<ParticleLayers>()
+			.register_type::<ParticleUserData>()
+			.register_type::<EffectName>()
+			.register_type::<Linear>()
+			.register_type::<AngularVelocity>()
+			.register_type::<MulScale>()
+			.register_type::<AddScale>()
+			.register_type::<TargetScale>()
+			.register_type::<TargetTransform>()
+			.register_type::<Velocity>()
+			.register_type::<GravityScale>()
+			.register_type::<Drag>()
+			.register_type::<MorphTarget>()
+			.register_type::<FollowTarget>()
+			.register_type::<InheritVelocityOverLifetime>()
+			.register_type::<Alignment>()
+			.register_type::<PositionNoise>()
+			.register_type::<Sleep>()
+			.register_type::<Sleeping>()
+			.register_type::<DistanceLifetime>()
+			.register_type::<dilation::TimeDilation>()
+			.register_type::<dilation::ParticleTimeScale>()
+			.register_type::<flicker::Flicker>()
+			.register_type::<water::WaterSurface>()
+			.register_type::<seek::Seekable>()
+			.register_type::<buffer::ParticleBuffer>()
+			.register_type::<buffer::PromotedParticle>()
+			.register_type::<sky::SkyParallax>()
+			.register_type::<weather::WeatherVolume>()
+			.register_type::<weather::WeatherOccluder>()
+			.register_type::<DespawnLimit>()
+			.register_type::<budget::ParticleBudget>()
+			.register_type::<budget::ParticlePriority>()
+			.register_type::<ParticleOf>()
+			.register_type::<ParticleCount>()
+			.register_type::<DespawnWithSpewer>()
+			.register_type::<Finishing>()
 			.register_type::<PreviousTransform>()
-			.register_type::<PreviousGlobalTransform>();
+			.register_type::<PreviousGlobalTransform>()
+			.init_resource::<validate::ValidationLimits>()
+			.register_type::<validate::ValidationLimits>();
+		// So scenes saved with it still load.
+		#[allow(deprecated)]
+		app.register_type::<Angular>();
+		#[cfg(debug_assertions)]
+		app.add_systems(
+			ParticlePreUpdate,
+			(
+				(
+					validate::ValidationLimits::validate_spewers,
+					validate::ValidationLimits::validate_buffers,
+				)
+					.before(spawn_particles::<Without<Deterministic>>),
+				validate::ValidationLimits::validate_particles
+					.after(budget::ParticleBudget::enforce),
+			),
+		);
+		#[cfg(feature = "assets")]
+		app.add_systems(ParticleUpdate, baked::LoopRecorder::record)
+			.init_asset::<baked::BakedLoop>()
+			.add_event::<baked::LoopBaked>()
+			.init_asset::<VectorField>()
+			.register_type::<baked::LoopRecorder>()
+			.register_type::<VectorFieldVolume>()
+			.register_type::<VectorFieldAdvection>();
+		#[cfg(feature = "collision")]
+		app.register_type::<collision::ParticleCollider>()
+			.register_type::<collision::ParticleCollision>();
+	}
+}
+
+/// Simulates [Deterministic] spewers and particles in `schedule`, e.g. a rollback schedule
+/// such as `bevy_ggrs::GgrsSchedule`, which may run several times per frame.
+///
+/// All simulation state lives in components, and time is read from that schedule's
+/// `Time`, so registering the particle components for rollback is enough to resimulate.
+/// Use [Spewer::seeded] for spewers that must behave identically on every peer.
+///
+/// Spewers emit from their `GlobalTransform`, so transforms must be propagated in
+/// `schedule` before [DeterministicParticleSystems] if spewers move during it.
+pub struct DeterministicParticlesPlugin {
+	pub schedule: Interned<dyn ScheduleLabel>,
+}
+
+impl DeterministicParticlesPlugin {
+	pub fn new(schedule: impl ScheduleLabel) -> Self {
+		Self {
+			schedule: schedule.intern(),
+		}
 	}
 }
 
+impl Plugin for DeterministicParticlesPlugin {
+	fn build(&self, app: &mut App) {
+		// Seekable deterministic effects are resimulated without the rest of the plugins.
+		if !app.is_plugin_added::<seek::SeekPlugin>() {
+			app.add_plugins(seek::SeekPlugin);
+		}
+		app.add_systems(
+			self.schedule,
+			(
+				spawn_particles_ordered::<With<Deterministic>>,
+				// Ordered so every resimulation applies the behaviors in the same order.
+				behavior_systems::<(With<Deterministic>, Without<Sleeping>)>().chain(),
+				handle_lifetimes::<With<Deterministic>>,
+			)
+				.chain()
+				.in_set(DeterministicParticleSystems),
+		)
+		.init_resource::<ParticleGravity>();
+	}
+}
+
+/// All systems added by [DeterministicParticlesPlugin], for ordering gameplay systems around them.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeterministicParticleSystems;
+
+/// Marks a [Spewer] as part of the deterministic gameplay simulation. Particles it spawns
+/// are marked as well. See [DeterministicParticlesPlugin].
+#[derive(Default, Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct Deterministic;
+
+/// Particle behavior and lifetime systems for particles matching `F`. Behaviors skip
+/// [Sleeping] particles, but they still expire.
+///
+/// Behaviors aren't ordered with each other, except where one must see another's result.
+/// Chain [behavior_systems] instead for a fixed order.
+pub fn simulation_systems<F: QueryFilter + 'static>() -> SystemConfigs {
+	(
+		dilation::TimeDilation::tick::<F>,
+		behavior_systems::<(F, Without<Sleeping>)>(),
+		handle_lifetimes::<F>,
+	)
+		.chain()
+}
+
+/// Particle behavior systems for particles matching `F`, without [handle_lifetimes].
+pub fn behavior_systems<F: QueryFilter + 'static>() -> SystemConfigs {
+	(
+		Linear::tick::<F>,
+		AngularVelocity::tick::<F>,
+		MulScale::tick::<F>,
+		AddScale::tick::<F>,
+		TargetScale::tick::<F>,
+		SizeOverLifetime::tick::<F>,
+		TargetTransform::tick::<F>,
+		flicker::Flicker::tick::<F>,
+		color::ColorOverLifetime::tick::<F>,
+		MorphTarget::tick::<F>,
+		DynParticleUpdate::tick::<F>,
+		#[cfg(feature = "assets")]
+		VectorFieldAdvection::tick::<F>.before(Drag::tick::<F>),
+		FollowTarget::tick::<F>.before(Velocity::tick::<F>),
+		GravityScale::tick::<F>.before(Velocity::tick::<F>),
+		Drag::tick::<F>
+			.after(GravityScale::tick::<F>)
+			.after(FollowTarget::tick::<F>)
+			.before(Velocity::tick::<F>),
+		InheritVelocityOverLifetime::tick::<F>,
+		Velocity::tick::<F>,
+		(
+			water::WaterSurface::tick::<F>,
+			#[cfg(feature = "collision")]
+			collision::ParticleCollision::tick::<F>,
+			DistanceLifetime::tick::<F>,
+			Sleep::tick::<F>,
+			// Overrides the rotation set by other behaviors, including bounces.
+			Alignment::tick::<F>
+				.after(AngularVelocity::tick::<F>)
+				.after(TargetTransform::tick::<F>),
+		)
+			.chain()
+			.after(Velocity::tick::<F>),
+	)
+		.into_configs()
+}
+
+#[cfg(feature = "render")]
 #[derive(Default, Clone, Bundle)]
 pub struct ParticleBundle<M: Material = StandardMaterial> {
 	pub mesh_bundle: MaterialMeshBundle<M>,
@@ -41,37 +417,52 @@ pub struct ParticleBundle<M: Material = StandardMaterial> {
 	pub initial_global_transform: InitialGlobalTransform,
 }
 
+/// Simulation-only particle, without the `render` feature.
+#[cfg(not(feature = "render"))]
+#[derive(Default, Clone, Bundle)]
+pub struct ParticleBundle {
+	pub transform: TransformBundle,
+	pub lifetime: Lifetime,
+	pub time_created: TimeCreated,
+	pub initial_transform: InitialTransform,
+	pub initial_global_transform: InitialGlobalTransform,
+}
+
 #[derive(QueryData, Reflect)]
 #[query_data(mutable)]
 pub struct ParticleData<'w> {
+	#[cfg(feature = "render")]
 	pub mesh: &'w mut Handle<Mesh>,
 	// pub material: &'w mut Handle<M>, // Material is generic. Should we just assume StandardMaterial?
 	pub transform: &'w mut Transform,
 	pub global_transform: &'w mut GlobalTransform,
 	pub initial_transform: &'w mut InitialTransform,
 	pub initial_global_transform: &'w mut InitialGlobalTransform,
+	#[cfg(feature = "render")]
 	pub visibility: &'w mut Visibility,
+	#[cfg(feature = "render")]
 	pub computed_visibility: &'w mut InheritedVisibility,
 	pub time_created: &'w mut TimeCreated,
 	pub lifetime: &'w mut Lifetime,
 }
 
-#[derive(Debug, Clone, Copy, Component, Deref, DerefMut, Reflect)]
-pub struct TimeCreated(pub Instant);
-
-impl Default for TimeCreated {
-	fn default() -> Self {
-		Self(Instant::now())
-	}
-}
+/// When the particle was created, as elapsed time on the `Time` clock of the schedule
+/// simulating it: [ParticleTime] for cosmetic particles. Moved while the particle is in a
+/// [TimeDilation](dilation::TimeDilation) bubble, so the time since it always is its age.
+#[derive(Default, Debug, Clone, Copy, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
+pub struct TimeCreated(pub Duration);
 
 #[derive(Default, Debug, Clone, Copy, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
 pub struct InitialTransform(pub Transform);
 
 #[derive(Default, Debug, Clone, Copy, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
 pub struct InitialGlobalTransform(pub GlobalTransform);
 
 #[derive(Debug, Clone, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
 pub struct Lifetime(pub Duration);
 
 impl Default for Lifetime {
@@ -80,20 +471,109 @@ impl Default for Lifetime {
 	}
 }
 
-pub fn handle_lifetimes(
+impl Lifetime {
+	/// Never expires, e.g. for accumulated snow or placed decals. Such particles live until
+	/// they are despawned, e.g. with [KillParticles].
+	pub const INFINITE: Self = Self(Duration::MAX);
+
+	pub fn is_infinite(&self) -> bool {
+		self.0 == Duration::MAX
+	}
+
+	/// When a particle created at `created` expires, or `None` if it never does.
+	pub fn expiry(&self, created: &TimeCreated) -> Option<Duration> {
+		created.0.checked_add(self.0)
+	}
+}
+
+/// Caps how many particles [handle_lifetimes] despawns per frame, to spread the cost of
+/// many particles expiring at once over several frames. Particles over the limit live
+/// on until a later frame.
+///
+/// Not suitable for rollback, since the particles left over aren't part of any component.
+#[derive(Resource, Debug, Clone, Copy, Reflect)]
+#[reflect(Resource)]
+pub struct DespawnLimit(pub usize);
+
+/// Despawns particles whose [Lifetime] has passed.
+///
+/// Particles are queued by expiry time when their [TimeCreated] or [Lifetime] is added or
+/// changed, so each frame only looks at the particles that are actually due.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_lifetimes<F: QueryFilter>(
 	mut cmds: Commands,
-	mut q: Query<(Entity, &TimeCreated, &Lifetime)>,
-	t: Res<Time<Real>>,
+	mut expiring: Local<BinaryHeap<Reverse<(Duration, Entity)>>>,
+	// The earliest expiry queued for each particle.
+	mut queued: Local<EntityHashMap<Duration>>,
+	changed: Query<
+		(Entity, &TimeCreated, &Lifetime),
+		(F, Or<(Changed<TimeCreated>, Changed<Lifetime>)>),
+	>,
+	scaled: Query<(Entity, &TimeCreated, &Lifetime), (F, With<dilation::ParticleTimeScale>)>,
+	q: Query<(&TimeCreated, &Lifetime), F>,
+	limit: Option<Res<DespawnLimit>>,
+	t: Res<Time>,
 ) {
-	for (id, created, lifetime) in &mut q {
-		if let Some(update) = t.last_update() {
-			if update.duration_since(created.0) > lifetime.0 {
-				cmds.entity(id).despawn();
+	for (id, created, lifetime) in &changed {
+		if let Some(expiry) = lifetime.expiry(created) {
+			expiring.push(Reverse((expiry, id)));
+			queued.insert(id, expiry);
+		}
+	}
+	// `TimeDilation` moves `TimeCreated` without triggering change detection, so particles
+	// it speeds up are only queued again once their expiry comes before the queued one.
+	for (id, created, lifetime) in &scaled {
+		if let Some(expiry) = lifetime.expiry(created) {
+			if queued.get(&id).is_none_or(|&queued| expiry < queued) {
+				expiring.push(Reverse((expiry, id)));
+				queued.insert(id, expiry);
 			}
 		}
 	}
+
+	let now = t.elapsed();
+	let limit = limit.map_or(usize::MAX, |limit| limit.0);
+	let mut despawned = 0;
+	while despawned < limit {
+		let Some(&Reverse((expiry, id))) = expiring.peek() else {
+			break;
+		};
+		if now <= expiry {
+			break;
+		}
+		expiring.pop();
+		if queued.get(&id) == Some(&expiry) {
+			queued.remove(&id);
+		}
+		// Entries go stale when the particle is despawned some other way or its lifetime
+		// changes, in which case a newer entry was queued.
+		let Ok((created, lifetime)) = q.get(id) else {
+			continue;
+		};
+		match lifetime.expiry(created) {
+			Some(current) if current == expiry => {}
+			// Pushed back without being queued again, e.g. by a `TimeDilation` bubble.
+			Some(current) if current > expiry => {
+				expiring.push(Reverse((current, id)));
+				queued.insert(id, current);
+				continue;
+			}
+			_ => continue,
+		}
+		// Entries that were queued more than once have the same expiry and pop in a row.
+		if expiring.peek() == Some(&Reverse((expiry, id))) {
+			continue;
+		}
+		cmds.entity(id).despawn();
+		despawned += 1;
+	}
 }
 
+/// Spawns a particle at the given transform and time.
+///
+/// The transform is in the space the particle is spawned in: world space for spewers with
+/// `use_global_coords`, and the spewer's own space otherwise, since their particles are
+/// spawned as its children. Factories can use it as the particle's `Transform` either way.
 pub trait ParticleFactory
 where
 	for<'w, 's, 'a> Self: FnMut(&'a mut Commands<'w, 's>, &GlobalTransform, TimeCreated) -> EntityCommands<'a>
@@ -110,39 +590,219 @@ impl<F> ParticleFactory for F where
 {
 }
 
-#[derive(Component, Reflect)]
-#[reflect(from_reflect = false)]
+/// Emits particles spawned by its `factory`.
+///
+/// Adding a spewer also adds the components it needs to emit that the entity is missing,
+/// i.e. a default [SpewerBundle], so inserting a bare `Spewer` on its own works too.
+#[derive(Reflect)]
+#[reflect(Component, from_reflect = false)]
 pub struct Spewer {
 	#[reflect(ignore)]
 	pub factory: Box<dyn ParticleFactory>,
+	/// Average time between interval spawns.
 	pub interval: Duration,
+	/// Maximum random offset of each interval spawn, earlier or later, for
+	/// [SpawnTiming::Interval]. Clamped to `interval`.
 	pub jitter: Duration,
-	pub last_spawn: Instant,
+	pub timing: SpawnTiming,
+	/// Particles emitted together at each interval spawn, picked uniformly from the range.
+	/// Much cheaper than shrinking `interval` when combined with a factory that scatters
+	/// particles over a shape.
+	pub particles_per_spawn: RangeInclusive<u32>,
+	pub catch_up: CatchUp,
+	/// Time from the last interval spawn to the next one, sampled when the last one happened.
+	pub next_interval: Option<Duration>,
+	/// Elapsed time of the last interval spawn. Set to the current time when the spewer
+	/// is added, unless it is already set, e.g. by a rollback re-inserting the spewer.
+	pub last_spawn: Duration,
+	/// Spawn particles as root entities in world space instead of as children of the spewer.
+	///
+	/// World-space particles have no parent or children, so Bevy's transform propagation
+	/// only copies the `Transform` of those that changed this frame into their
+	/// `GlobalTransform`, without walking any hierarchy. They are not skipped by it
+	/// entirely: that would mean hiding every `Transform` change from change detection,
+	/// which other systems rely on, so moving particles still cost one copy each per frame.
 	pub use_global_coords: bool,
 	#[reflect(ignore)]
 	pub rng: nanorand::WyRand,
+	/// Particles to emit at once on the next update, on top of the regular interval.
+	/// Usually queued with [EmitBurst].
+	pub pending_burst: u32,
+	/// Number of frames the [SpewerMotion] estimate is smoothed over, so jittery parent
+	/// motion doesn't scatter interval spawns. `0` or `1` uses only the latest frame.
+	pub motion_smoothing: u32,
+	/// Estimated motion of the spewer, updated every frame from its `GlobalTransform` and
+	/// [PreviousGlobalTransform].
+	pub motion: SpewerMotion,
+	/// How the transforms of particles spawned between two frames are interpolated.
+	pub spawn_path: SpawnPath,
 }
 
+/// How the times between interval spawns are chosen.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpawnTiming {
+	/// Every `interval`, each spawn offset uniformly by up to `±jitter`.
+	#[default]
+	Interval,
+	/// Randomly, as a Poisson process averaging one spawn per `interval`. Good for
+	/// irregular effects like embers and drips.
+	Poisson,
+}
+
+impl SpawnTiming {
+	/// Samples the time until the next spawn.
+	pub fn sample(self, interval: Duration, jitter: Duration, rng: &mut WyRand) -> Duration {
+		match self {
+			// Kept as a `Duration`, so unjittered spawns don't drift from f32 rounding.
+			SpawnTiming::Interval => {
+				let jitter = jitter.min(interval);
+				interval - jitter + jitter.mul_f32(rng.generate::<f32>() * 2.0)
+			}
+			SpawnTiming::Poisson => {
+				let secs = -(1.0 - rng.generate::<f32>()).ln() * interval.as_secs_f32();
+				Duration::from_secs_f32(secs.max(0.0))
+			}
+		}
+	}
+}
+
+/// Limits on how many interval spawns a [Spewer] catches up on after a long frame, e.g. a
+/// hitch or a suspended browser tab. Unlimited by default.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Reflect)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct CatchUp {
+	/// Spawns that were due longer ago than this are skipped.
+	pub max_backlog: Option<Duration>,
+	/// Most interval spawns in one frame. See `overflow` for what happens to the rest.
+	pub max_spawns_per_frame: Option<u32>,
+	pub overflow: CatchUpOverflow,
+}
+
+/// What happens to interval spawns over [CatchUp::max_spawns_per_frame].
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum CatchUpOverflow {
+	/// They are skipped.
+	#[default]
+	Drop,
+	/// They happen on the following frames, with the times they were originally due.
+	Spread,
+}
+
+/// Path that interval spawns between two frames are spread along.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum SpawnPath {
+	/// Straight steps along the spewer's linear velocity.
+	#[default]
+	Linear,
+	/// Follow the arc implied by the spewer's angular velocity, as if it were rotating
+	/// around a pivot, e.g. a sparkler spun on a stick. Only suitable for spewers whose
+	/// rotation comes from orbiting that pivot, not ones that spin while moving straight.
+	Arc,
+}
+
+/// World-space rates of change of a [Spewer]'s `GlobalTransform`.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Reflect)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpewerMotion {
+	/// Units per second.
+	pub linear: Vec3,
+	/// Rotation axis scaled by radians per second.
+	pub angular: Vec3,
+	/// Change in scale per second.
+	pub scale: Vec3,
+}
+
+impl SpewerMotion {
+	/// Motion from `prev` to `curr` over `dt` seconds.
+	pub fn between(prev: &Transform, curr: &Transform, dt: f32) -> Self {
+		if dt <= 0.0 {
+			return Self::default();
+		}
+		let mut rotation = curr.rotation * prev.rotation.inverse();
+		// Take the short way around.
+		if rotation.w < 0.0 {
+			rotation = -rotation;
+		}
+		Self {
+			linear: (curr.translation - prev.translation) / dt,
+			angular: rotation.to_scaled_axis() / dt,
+			scale: (curr.scale - prev.scale) / dt,
+		}
+	}
+
+	/// Exponential moving average over roughly `frames` frames.
+	pub fn smoothed(self, latest: Self, frames: u32) -> Self {
+		let alpha = 2.0 / (frames.max(1) as f32 + 1.0);
+		Self {
+			linear: self.linear.lerp(latest.linear, alpha),
+			angular: self.angular.lerp(latest.angular, alpha),
+			scale: self.scale.lerp(latest.scale, alpha),
+		}
+	}
+
+	/// Advances `xform` by this motion over `secs` seconds.
+	pub fn advance(&self, xform: &Transform, secs: f32) -> Transform {
+		Transform {
+			translation: xform.translation + self.linear * secs,
+			rotation: (Quat::from_scaled_axis(self.angular * secs) * xform.rotation).normalize(),
+			scale: xform.scale + self.scale * secs,
+		}
+	}
+
+	/// Like [advance](Self::advance), but moves the translation around the pivot that
+	/// the linear and angular velocities imply, plus any motion along the rotation axis.
+	pub fn advance_along_arc(&self, xform: &Transform, secs: f32) -> Transform {
+		let omega_sq = self.angular.length_squared();
+		if omega_sq < 1e-8 {
+			return self.advance(xform, secs);
+		}
+		let pivot = xform.translation + self.angular.cross(self.linear) / omega_sq;
+		let axial = self.angular * (self.angular.dot(self.linear) / omega_sq);
+		let rotation = Quat::from_scaled_axis(self.angular * secs);
+		Transform {
+			translation: pivot + rotation * (xform.translation - pivot) + axial * secs,
+			..self.advance(xform, secs)
+		}
+	}
+}
+
+/// Runtime state of a [Spewer], for rolling it back without having to clone its factory.
+#[derive(Debug, Clone)]
+pub struct SpewerState {
+	pub last_spawn: Duration,
+	pub rng: WyRand,
+	pub pending_burst: u32,
+	pub motion: SpewerMotion,
+	pub next_interval: Option<Duration>,
+}
+
+/// Everything a [Spewer] needs. Optional, since adding a `Spewer` adds whatever of it is
+/// missing, but still handy for setting its transform and visibility up front.
 #[derive(Default, Bundle)]
 pub struct SpewerBundle {
 	pub spewer: Spewer,
 	pub transform: TransformBundle,
 	pub prev_xform: PreviousTransform,
 	pub prev_global_xform: PreviousGlobalTransform,
+	#[cfg(feature = "render")]
 	pub visibility: VisibilityBundle,
 }
 
-#[derive(Default, Component, Deref, DerefMut, Reflect)]
+#[derive(Default, Debug, Clone, Copy, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
 pub struct PreviousTransform(pub Transform);
-#[derive(Default, Component, Deref, DerefMut, Reflect)]
+#[derive(Default, Debug, Clone, Copy, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
 pub struct PreviousGlobalTransform(pub GlobalTransform);
 
-fn default_factory<'w, 's, 'a>(
+pub(crate) fn default_factory<'w, 's, 'a>(
 	cmds: &'a mut Commands<'w, 's>,
 	_: &GlobalTransform,
 	_: TimeCreated,
 ) -> EntityCommands<'a> {
-	cmds.spawn(ParticleBundle::<StandardMaterial>::default())
+	cmds.spawn(<ParticleBundle>::default())
 }
 
 impl Default for Spewer {
@@ -152,13 +812,60 @@ impl Default for Spewer {
 			factory: Box::new(default_factory),
 			interval,
 			jitter: Duration::ZERO,
-			last_spawn: Instant::now(),
+			timing: SpawnTiming::Interval,
+			particles_per_spawn: 1..=1,
+			catch_up: CatchUp::default(),
+			next_interval: None,
+			last_spawn: Duration::ZERO,
 			use_global_coords: false,
 			rng: WyRand::new(),
+			pending_burst: 0,
+			motion_smoothing: 1,
+			motion: SpewerMotion::default(),
+			spawn_path: SpawnPath::Linear,
 		}
 	}
 }
 
+impl Component for Spewer {
+	const STORAGE_TYPE: StorageType = StorageType::Table;
+
+	fn register_component_hooks(hooks: &mut ComponentHooks) {
+		hooks.on_add(|mut world, id, _| {
+			world.commands().add(move |world: &mut World| {
+				let Some(mut entity) = world.get_entity_mut(id) else {
+					return;
+				};
+				// Only what's missing, so components inserted after the spewer aren't replaced.
+				if !entity.contains::<Transform>() {
+					entity.insert(Transform::default());
+				}
+				if !entity.contains::<GlobalTransform>() {
+					entity.insert(GlobalTransform::default());
+				}
+				if !entity.contains::<PreviousTransform>() {
+					entity.insert(PreviousTransform::default());
+				}
+				if !entity.contains::<PreviousGlobalTransform>() {
+					entity.insert(PreviousGlobalTransform::default());
+				}
+				#[cfg(feature = "render")]
+				{
+					if !entity.contains::<Visibility>() {
+						entity.insert(Visibility::default());
+					}
+					if !entity.contains::<InheritedVisibility>() {
+						entity.insert(InheritedVisibility::default());
+					}
+					if !entity.contains::<ViewVisibility>() {
+						entity.insert(ViewVisibility::default());
+					}
+				}
+			});
+		});
+	}
+}
+
 impl Spewer {
 	pub fn new(factory: impl ParticleFactory) -> Self {
 		Self {
@@ -174,105 +881,598 @@ impl Spewer {
 		}
 	}
 
+	/// The parts of this spewer that change while it runs.
+	pub fn state(&self) -> SpewerState {
+		SpewerState {
+			last_spawn: self.last_spawn,
+			rng: self.rng.clone(),
+			pending_burst: self.pending_burst,
+			motion: self.motion,
+			next_interval: self.next_interval,
+		}
+	}
+
+	pub fn set_state(&mut self, state: SpewerState) {
+		self.last_spawn = state.last_spawn;
+		self.rng = state.rng;
+		self.pending_burst = state.pending_burst;
+		self.motion = state.motion;
+		self.next_interval = state.next_interval;
+	}
+
 	pub fn instance(&self, factory: impl ParticleFactory) -> Self {
 		Self {
 			factory: Box::new(factory),
 			interval: self.interval,
 			jitter: self.jitter,
+			timing: self.timing,
+			particles_per_spawn: self.particles_per_spawn.clone(),
+			catch_up: self.catch_up,
+			next_interval: None,
 			last_spawn: self.last_spawn,
 			use_global_coords: self.use_global_coords,
 			rng: self.rng.clone(),
+			pending_burst: 0,
+			motion_smoothing: self.motion_smoothing,
+			motion: SpewerMotion::default(),
+			spawn_path: self.spawn_path,
 		}
 	}
 }
 
-pub fn spawn_particles(
+type SpewerData = (
+	Entity,
+	&'static mut Spewer,
+	&'static Transform,
+	&'static GlobalTransform,
+	Option<&'static mut PreviousTransform>,
+	Option<&'static mut PreviousGlobalTransform>,
+	SpewerLayers,
+	Option<&'static mut EmissionGate>,
+	Option<&'static EmissionOffset>,
+	Option<&'static ParticleLayers>,
+	Has<Deterministic>,
+	Has<DespawnWithSpewer>,
+	(SpewerShown, SpanTarget),
+	SpewerCopied,
+);
+
+/// Components copied as they are from a spewer onto each particle it spawns.
+type SpewerCopied = (
+	Option<&'static ParticleUserData>,
+	Option<&'static color::SpewerTint>,
+	Option<&'static budget::ParticlePriority>,
+	Option<&'static EffectName>,
+);
+
+/// Emits particles from every [Spewer] matching `F`.
+///
+/// Spewers are updated in parallel, each recording its factories' commands into a
+/// thread-local queue, so many spewers bursting in the same frame don't all run their
+/// factories on one thread. The order the queues are applied in is unspecified; use
+/// [spawn_particles_ordered] where entities must be spawned in a stable order.
+pub fn spawn_particles<F: QueryFilter>(
+	par_cmds: ParallelCommands,
+	mut q: Query<SpewerData, F>,
+	t: Res<Time>,
+) {
+	let now = t.elapsed();
+	let delta = t.delta();
+	q.par_iter_mut().for_each(|item| {
+		par_cmds.command_scope(|mut cmds| update_spewer(&mut cmds, item, now, delta));
+	});
+}
+
+/// Like [spawn_particles], but updates spewers one at a time in query order, so their
+/// particles are spawned in the same order on every run, as rollback networking needs.
+pub fn spawn_particles_ordered<F: QueryFilter>(
 	mut cmds: Commands,
-	mut q: Query<(
-		Entity,
-		&mut Spewer,
-		&Transform,
-		&GlobalTransform,
-		Option<&mut PreviousTransform>,
-		Option<&mut PreviousGlobalTransform>,
-	)>,
-	t: Res<Time<Real>>,
+	mut q: Query<SpewerData, F>,
+	t: Res<Time>,
 ) {
-	let dt = t.delta_seconds();
-	for (id, mut spewer, xform, global_xform, prev_xform, prev_global_xform) in &mut q {
-		let Spewer {
-			interval,
-			jitter,
-			use_global_coords,
-			ref mut factory,
-			ref mut last_spawn,
-			ref mut rng,
-		} = *spewer;
-		let vel = if let Some(prev_global_xform) = &prev_global_xform {
-			let prev_xform = prev_global_xform.compute_transform();
-			let xform = global_xform.compute_transform();
-			Transform {
-				translation: (xform.translation - prev_xform.translation) / dt,
-				rotation: (xform.rotation - prev_xform.rotation) / dt,
-				scale: (xform.scale - prev_xform.scale) / dt,
-			}
-		} else {
-			Transform {
-				scale: Vec3::ZERO,
-				..Transform::IDENTITY
+	let now = t.elapsed();
+	let delta = t.delta();
+	for item in &mut q {
+		update_spewer(&mut cmds, item, now, delta);
+	}
+}
+
+fn update_spewer(
+	cmds: &mut Commands,
+	(
+		id,
+		mut spewer,
+		xform,
+		global_xform,
+		prev_xform,
+		prev_global_xform,
+		layers,
+		gate,
+		offset,
+		particle_layers,
+		deterministic,
+		despawn_with_spewer,
+		(shown, span),
+		(user_data, tint, priority, effect),
+	): QueryItem<SpewerData>,
+	now: Duration,
+	delta: Duration,
+) {
+	#[cfg(feature = "trace")]
+	let _span = {
+		let (entity, name) = span;
+		bevy::log::info_span!("spewer", ?entity, name = span_name(name)).entered()
+	};
+	#[cfg(not(feature = "trace"))]
+	let () = span;
+	// Spewers re-inserted with their state, e.g. by a rollback, carry on from it.
+	if spewer.is_added() && spewer.last_spawn == Duration::ZERO {
+		spewer.last_spawn = now;
+	}
+	let Spewer {
+		interval,
+		jitter,
+		timing,
+		ref particles_per_spawn,
+		catch_up,
+		ref mut next_interval,
+		use_global_coords,
+		ref mut factory,
+		ref mut last_spawn,
+		ref mut rng,
+		ref mut pending_burst,
+		motion_smoothing,
+		ref mut motion,
+		spawn_path,
+	} = *spewer;
+	if let Some(prev_global_xform) = &prev_global_xform {
+		let latest = SpewerMotion::between(
+			&prev_global_xform.compute_transform(),
+			&global_xform.compute_transform(),
+			delta.as_secs_f32(),
+		);
+		*motion = motion.smoothed(latest, motion_smoothing);
+	}
+	let motion = *motion;
+	let emits = spewer_emits(shown);
+	if !emits {
+		*pending_burst = 0;
+	}
+	let rate = if !emits {
+		0.0
+	} else {
+		gate.map_or(1.0, |mut gate| {
+			(gate.0)(EmissionContext {
+				entity: id,
+				transform: global_xform,
+				velocity: motion.linear,
+			})
+		})
+	};
+	// A gate gone NaN or infinite can't be trusted to mean "emit normally".
+	let rate = if rate.is_finite() { rate } else { 0.0 };
+	// Dividing rounds through an `f32`, which would make ungated spewers drift.
+	let scaled = if rate > 0.0 && rate != 1.0 {
+		Duration::try_from_secs_f32(interval.as_secs_f32() / rate).ok()
+	} else {
+		Some(interval)
+	};
+	// A rate so small the interval overflows a `Duration` emits nothing, like `0.0`.
+	let (rate, interval) = match scaled {
+		Some(interval) => (rate, interval),
+		None => (0.0, Duration::ZERO),
+	};
+	// Interval spawns are placed along the spewer's path since the start of the frame.
+	let frame_start = now.saturating_sub(delta);
+	let start_xform = prev_global_xform
+		.as_deref()
+		.map_or(*global_xform, |prev| **prev)
+		.compute_transform();
+
+	let emitter = Emitter {
+		id,
+		local: (!use_global_coords).then(|| global_xform.affine().inverse()),
+		layers,
+		offset: offset.map(|offset| offset.0),
+		particle_layers: particle_layers.copied(),
+		deterministic,
+		despawn_with_spewer,
+		user_data: user_data.copied(),
+		tint: tint.copied(),
+		priority: priority.copied(),
+		effect,
+	};
+	for _ in 0..std::mem::take(pending_burst) {
+		spawn_one(cmds, factory, global_xform, TimeCreated(now), &emitter);
+	}
+
+	if rate <= 0.0 {
+		// Don't build up a backlog to catch up on once emission resumes.
+		*last_spawn = now;
+		*next_interval = None;
+	}
+	if let Some(max_backlog) = catch_up.max_backlog {
+		*last_spawn = (*last_spawn).max(now.saturating_sub(max_backlog));
+	}
+
+	let mut spawns = 0;
+	while !interval.is_zero() {
+		let wait = *next_interval.get_or_insert_with(|| timing.sample(interval, jitter, rng));
+		if now.saturating_sub(*last_spawn) < wait {
+			break;
+		}
+		// Only once another spawn is due, so reaching the limit exactly doesn't drop the
+		// time towards the next one.
+		if catch_up
+			.max_spawns_per_frame
+			.is_some_and(|max| spawns >= max)
+		{
+			if catch_up.overflow == CatchUpOverflow::Drop {
+				*last_spawn = now;
+				*next_interval = None;
 			}
+			break;
+		}
+		*last_spawn += wait;
+		*next_interval = None;
+		spawns += 1;
+
+		let secs = last_spawn.saturating_sub(frame_start).as_secs_f32();
+		let spawn_xform = match spawn_path {
+			SpawnPath::Linear => motion.advance(&start_xform, secs),
+			SpawnPath::Arc => motion.advance_along_arc(&start_xform, secs),
 		};
-		let interval_secs = interval.as_secs_f32();
-		let step = Transform {
-			translation: vel.translation * interval_secs,
-			rotation: vel.rotation * interval_secs,
-			scale: vel.scale * interval_secs,
-		};
-		let mut curr_xform = if let Some(prev_global_xform) = &prev_global_xform {
-			***prev_global_xform
+		let count = if particles_per_spawn.start() < particles_per_spawn.end() {
+			rng.generate_range(particles_per_spawn.clone())
 		} else {
-			*global_xform
+			*particles_per_spawn.start()
 		};
+		for _ in 0..count {
+			spawn_one(
+				cmds,
+				factory,
+				&spawn_xform.into(),
+				TimeCreated(*last_spawn),
+				&emitter,
+			);
+		}
+	}
+	if let Some(mut prev_xform) = prev_xform {
+		if **prev_xform != *xform {
+			**prev_xform = *xform;
+		}
+	}
+	if let Some(mut prev_global_xform) = prev_global_xform {
+		if **prev_global_xform != *global_xform {
+			**prev_global_xform = *global_xform;
+		}
+	}
+}
 
-		let Some(remaining) = t.last_update() else {
-			continue;
+/// What an [EmissionGate] can base its decision on.
+pub struct EmissionContext<'a> {
+	pub entity: Entity,
+	pub transform: &'a GlobalTransform,
+	/// Estimated world-space linear velocity of the spewer.
+	pub velocity: Vec3,
+}
+
+pub trait EmissionGateFn: FnMut(EmissionContext) -> f32 + Send + Sync + 'static {}
+impl<F> EmissionGateFn for F where F: FnMut(EmissionContext) -> f32 + Send + Sync + 'static {}
+
+/// Evaluated every frame for its [Spewer] to scale the emission rate. `1.0` emits
+/// normally, `2.0` twice as often, and `0.0` or less suppresses interval emission
+/// entirely (bursts still happen). So do NaN and infinite rates.
+#[derive(Component)]
+pub struct EmissionGate(pub Box<dyn EmissionGateFn>);
+
+impl EmissionGate {
+	pub fn new(f: impl EmissionGateFn) -> Self {
+		Self(Box::new(f))
+	}
+
+	/// Only emits while the spewer moves faster than `speed`.
+	pub fn min_speed(speed: f32) -> Self {
+		Self::new(move |ctx: EmissionContext| {
+			if ctx.velocity.length_squared() > speed * speed {
+				1.0
+			} else {
+				0.0
+			}
+		})
+	}
+}
+
+/// Offset of the point particles are emitted from, relative to the [Spewer], e.g. to move
+/// a muzzle flash to the tip of the barrel without adding another entity under the gun bone.
+///
+/// Applied to the transform passed to the spewer's factory, before any shape sampling the
+/// factory does.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
+pub struct EmissionOffset(pub Transform);
+
+/// Which influences, such as [VectorFieldVolume]s, a particle interacts with, e.g. so wind
+/// affects snow but not sparks. Particles only interact with influences sharing a layer
+/// with them. Entities without `ParticleLayers` are on [ParticleLayers::DEFAULT].
+///
+/// Copied from the spewer onto each particle it spawns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component, Reflect)]
+#[reflect(Component)]
+pub struct ParticleLayers(pub u32);
+
+impl Default for ParticleLayers {
+	fn default() -> Self {
+		Self::DEFAULT
+	}
+}
+
+impl ParticleLayers {
+	/// Layer `0` only.
+	pub const DEFAULT: Self = Self(1);
+	pub const ALL: Self = Self(u32::MAX);
+	pub const NONE: Self = Self(0);
+
+	/// Only layer `layer`, which must be less than `32`.
+	pub const fn layer(layer: u32) -> Self {
+		Self(1 << layer)
+	}
+
+	/// These layers and layer `layer`, which must be less than `32`.
+	pub const fn with(self, layer: u32) -> Self {
+		Self(self.0 | 1 << layer)
+	}
+
+	pub const fn intersects(self, other: Self) -> bool {
+		self.0 & other.0 != 0
+	}
+
+	/// Whether entities with the optional layers `a` and `b` interact.
+	pub fn interact(a: Option<&Self>, b: Option<&Self>) -> bool {
+		let a = a.copied().unwrap_or_default();
+		let b = b.copied().unwrap_or_default();
+		a.intersects(b)
+	}
+}
+
+/// Arbitrary data for a particle's behaviors and shaders to read, e.g. a team color or a
+/// damage type, so gameplay state can change how particles look without a custom factory.
+///
+/// [ParticleMaterial](material::ParticleMaterial) particles get a variant of their
+/// material with it as `particle_extension.user_data` in the shaders, shared by every
+/// particle with the same material and data, like a [ParticleColor](color::ParticleColor).
+/// Each distinct value creates a variant, so keep the values few. For values unique to each
+/// particle, use a [ParticleBuffer](buffer::ParticleBuffer), whose instances carry their
+/// own [attributes](buffer::ParticleBuffer::attributes), age, and seed to the shader.
+///
+/// Copied from the spewer onto each particle it spawns, replacing any the factory gave it.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
+pub struct ParticleUserData(pub Vec4);
+
+/// A human-readable name for an effect, like `"torch_fire"`, to tell its spewers and
+/// particles apart in profiling spans, [ParticleBudget](budget::ParticleBudget) stats, and
+/// inspectors, instead of by entity ID.
+///
+/// Copied from the spewer onto each particle it spawns, so observers of particle events
+/// like [WaterCrossing](water::WaterCrossing) can look up which effect the
+/// particle belongs to. Effects spawned with
+/// [spawn_effect_by_name](library::SpawnEffectExt::spawn_effect_by_name) are given their
+/// name in the library. Prefer `&'static str` names, which are copied for free.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Component, Deref, Reflect)]
+#[reflect(Component)]
+pub struct EffectName(pub Cow<'static, str>);
+
+impl EffectName {
+	pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+		Self(name.into())
+	}
+
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
+}
+
+impl std::fmt::Display for EffectName {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+/// An effect's entity and names, for its profiling spans.
+#[cfg(feature = "trace")]
+pub(crate) type SpanTarget = (Entity, (Option<&'static EffectName>, Option<&'static Name>));
+#[cfg(not(feature = "trace"))]
+pub(crate) type SpanTarget = ();
+
+/// The name a [SpanTarget] is shown by, preferring its [EffectName].
+#[cfg(feature = "trace")]
+pub(crate) fn span_name<'a>(
+	(effect, name): (Option<&'a EffectName>, Option<&'a Name>),
+) -> Option<&'a str> {
+	effect
+		.map(EffectName::as_str)
+		.or_else(|| name.map(Name::as_str))
+}
+
+/// Render layers copied from a spewer to its particles.
+#[cfg(feature = "render")]
+type SpewerLayers = Option<&'static RenderLayers>;
+#[cfg(not(feature = "render"))]
+type SpewerLayers = ();
+
+/// What a spewer passes on to each particle it spawns.
+pub(crate) struct Emitter<'a> {
+	id: Entity,
+	/// Spawn particles as children of the spewer, moving the world-space transforms they are
+	/// emitted at into its space with this.
+	local: Option<bevy::math::Affine3A>,
+	layers: QueryItem<'a, SpewerLayers>,
+	offset: Option<Transform>,
+	particle_layers: Option<ParticleLayers>,
+	deterministic: bool,
+	despawn_with_spewer: bool,
+	user_data: Option<ParticleUserData>,
+	tint: Option<color::SpewerTint>,
+	priority: Option<budget::ParticlePriority>,
+	effect: Option<&'a EffectName>,
+}
+
+impl<'a> Emitter<'a> {
+	/// Reads what `spewer` passes on straight from `world`, for particles spawned outside of
+	/// [update_spewer], like those restored from a [snapshot::SpewerSnapshot].
+	pub(crate) fn of(world: &'a World, spewer: Entity) -> Option<Self> {
+		let entity = world.get_entity(spewer)?;
+		let use_global_coords = entity.get::<Spewer>()?.use_global_coords;
+		let global_xform = entity.get::<GlobalTransform>().copied().unwrap_or_default();
+		Some(Self {
+			id: spewer,
+			local: (!use_global_coords).then(|| global_xform.affine().inverse()),
+			#[cfg(feature = "render")]
+			layers: entity.get::<RenderLayers>(),
+			#[cfg(not(feature = "render"))]
+			layers: (),
+			offset: entity.get::<EmissionOffset>().map(|offset| offset.0),
+			particle_layers: entity.get::<ParticleLayers>().copied(),
+			deterministic: entity.contains::<Deterministic>(),
+			despawn_with_spewer: entity.contains::<DespawnWithSpewer>(),
+			user_data: entity.get::<ParticleUserData>().copied(),
+			tint: entity.get::<color::SpewerTint>().copied(),
+			priority: entity.get::<budget::ParticlePriority>().copied(),
+			effect: entity.get::<EffectName>(),
+		})
+	}
+
+	/// Gives a particle its factory just spawned everything the spewer passes on, and parents
+	/// it to the spewer if it emits in local space. `initial` is the transform the particle
+	/// started at, if that isn't its current one.
+	pub(crate) fn adopt(&self, particle: &mut EntityCommands, initial: Option<Transform>) {
+		particle.insert(ParticleOf(self.id));
+		#[cfg(feature = "render")]
+		if let Some(layers) = self.layers {
+			particle.insert(layers.clone());
+		}
+		#[cfg(not(feature = "render"))]
+		let () = self.layers;
+		if let Some(layers) = self.particle_layers {
+			particle.insert(layers);
+		}
+		if self.deterministic {
+			particle.insert(Deterministic);
+		}
+		if self.despawn_with_spewer {
+			particle.insert(DespawnWithSpewer);
+		}
+		if let Some(user_data) = self.user_data {
+			particle.insert(user_data);
+		}
+		if let Some(tint) = self.tint {
+			particle.insert(tint);
+		}
+		if let Some(priority) = self.priority {
+			particle.insert(priority);
+		}
+		if let Some(effect) = self.effect {
+			particle.insert(effect.clone());
+		}
+		let parent = self.local.map(|_| self.id);
+		particle.add(init_global_transform(parent, initial));
+		if let Some(parent) = parent {
+			particle.set_parent(parent);
+		}
+	}
+}
+
+fn spawn_one(
+	cmds: &mut Commands,
+	factory: &mut Box<dyn ParticleFactory>,
+	xform: &GlobalTransform,
+	time_created: TimeCreated,
+	emitter: &Emitter,
+) {
+	let xform = match emitter.offset {
+		Some(offset) => xform.mul_transform(offset),
+		None => *xform,
+	};
+	let xform = match emitter.local {
+		Some(to_local) => GlobalTransform::from(to_local * xform.affine()),
+		None => xform,
+	};
+	let mut particle: EntityCommands = (factory)(cmds, &xform, time_created);
+	emitter.adopt(&mut particle, None);
+}
+
+/// Makes a new particle's `GlobalTransform` and [InitialGlobalTransform] match the
+/// `Transform` its factory gave it right away, instead of after the next transform
+/// propagation, so it doesn't flash at the origin for a frame. [InitialGlobalTransform]
+/// is taken from `initial` instead when that is given.
+fn init_global_transform(parent: Option<Entity>, initial: Option<Transform>) -> impl EntityCommand {
+	move |id: Entity, world: &mut World| {
+		let Some(&xform) = world.get::<Transform>(id) else {
+			return;
 		};
-		let Some(mut remaining) = remaining.checked_duration_since(*last_spawn) else {
-			continue;
+		let parent_xform = parent.and_then(|parent| world.get::<GlobalTransform>(parent).copied());
+		let to_global = |xform: Transform| match parent_xform {
+			Some(parent_xform) => parent_xform.mul_transform(xform),
+			None => GlobalTransform::from(xform),
 		};
+		let global_xform = to_global(xform);
+		let initial_global_xform = initial.map_or(global_xform, to_global);
+		let mut particle = world.entity_mut(id);
+		particle.insert(global_xform);
+		if let Some(mut initial) = particle.get_mut::<InitialGlobalTransform>() {
+			initial.0 = initial_global_xform;
+		}
+	}
+}
 
-		while remaining >= interval {
-			remaining = remaining.saturating_sub(
-				interval
-					+ Duration::new(
-						rng.generate_range(0..=jitter.as_secs()),
-						rng.generate_range(0..=jitter.subsec_nanos()),
-					),
-			);
-			*last_spawn += interval;
+/// Emits `count` particles at once from a spewer, e.g. from an observer or in response
+/// to an animation event:
+///
+/// ```ignore
+/// commands.trigger_targets(EmitBurst::new(8).from_spewer("footstep_dust"), character);
+/// ```
+///
+/// Without a spewer name, the targeted entity's own [Spewer] emits. With a name, every
+/// spewer on the target or its descendants with a matching [Name] emits.
+/// Spewers that are [Finishing] ignore bursts.
+#[derive(Event, Debug, Clone)]
+pub struct EmitBurst {
+	pub count: u32,
+	pub spewer: Option<Cow<'static, str>>,
+}
 
-			let particle: EntityCommands =
-				(factory)(&mut cmds, &curr_xform, TimeCreated(*last_spawn));
-			let particle_id = particle.id();
-			if !use_global_coords {
-				cmds.entity(id).add_child(particle_id);
-			}
-			let tmp = curr_xform.compute_transform();
-			curr_xform = Transform {
-				translation: tmp.translation + step.translation,
-				rotation: tmp.rotation + step.rotation,
-				scale: tmp.scale + step.scale,
-			}
-			.into();
+impl EmitBurst {
+	pub fn new(count: u32) -> Self {
+		Self {
+			count,
+			spewer: None,
 		}
-		if let Some(mut prev_xform) = prev_xform {
-			if **prev_xform != *xform {
-				**prev_xform = *xform;
-			}
+	}
+
+	pub fn from_spewer(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+		self.spewer = Some(name.into());
+		self
+	}
+}
+
+pub fn handle_emit_burst(
+	trigger: Trigger<EmitBurst>,
+	mut spewers: Query<(&mut Spewer, Option<&Name>), Without<Finishing>>,
+	children: Query<&Children>,
+) {
+	let target = trigger.entity();
+	let burst = trigger.event();
+	let Some(spewer_name) = &burst.spewer else {
+		if let Ok((mut spewer, _)) = spewers.get_mut(target) {
+			spewer.pending_burst = spewer.pending_burst.saturating_add(burst.count);
 		}
-		if let Some(mut prev_global_xform) = prev_global_xform {
-			if **prev_global_xform != *global_xform {
-				**prev_global_xform = *global_xform;
+		return;
+	};
+	for id in std::iter::once(target).chain(children.iter_descendants(target)) {
+		if let Ok((mut spewer, Some(name))) = spewers.get_mut(id) {
+			if name.as_str() == spewer_name {
+				spewer.pending_burst = spewer.pending_burst.saturating_add(burst.count);
 			}
 		}
 	}
@@ -0,0 +1,148 @@
+//! Effects registered by name, so gameplay code can spawn them without holding on to their
+//! handles or factories.
+
+use std::{borrow::Cow, hash::Hash, sync::Arc};
+
+use bevy::{
+	ecs::{system::EntityCommands, world::CommandQueue},
+	prelude::*,
+	utils::HashMap,
+};
+
+use crate::EffectName;
+#[cfg(feature = "render")]
+use crate::{template::ParticleTemplate, Spewer, SpewerBundle};
+
+pub trait EffectSpawnFn: Fn(&mut EntityCommands, Transform) + Send + Sync + 'static {}
+impl<F> EffectSpawnFn for F where F: Fn(&mut EntityCommands, Transform) + Send + Sync + 'static {}
+
+/// Effects by key, spawned with [SpawnEffectExt]:
+///
+/// ```ignore
+/// library.insert("hit_spark", |effect: &mut EntityCommands, transform| {
+///     effect.insert(SpewerBundle { .. });
+/// });
+/// // Elsewhere:
+/// commands.spawn_effect_by_name("hit_spark", transform);
+/// ```
+///
+/// Keys are names by default, but can be any type, e.g. an enum of the game's effects, in
+/// a `ParticleEffectLibrary<MyEffect>` inserted by the app.
+#[derive(Resource)]
+pub struct ParticleEffectLibrary<K = Cow<'static, str>> {
+	effects: HashMap<K, Arc<dyn EffectSpawnFn>>,
+}
+
+impl<K> Default for ParticleEffectLibrary<K> {
+	fn default() -> Self {
+		Self {
+			effects: HashMap::default(),
+		}
+	}
+}
+
+impl<K: Hash + Eq + Send + Sync + 'static> ParticleEffectLibrary<K> {
+	/// Registers an effect that `spawn` adds to a new entity, which should be given
+	/// `transform`. Replaces any effect already registered as `key`.
+	pub fn insert(&mut self, key: impl Into<K>, spawn: impl EffectSpawnFn) -> &mut Self {
+		self.effects.insert(key.into(), Arc::new(spawn));
+		self
+	}
+
+	/// Registers an effect spawning a spewer with the settings of `spewer`, emitting
+	/// `template`.
+	#[cfg(feature = "render")]
+	pub fn insert_template<M: Material>(
+		&mut self,
+		key: impl Into<K>,
+		spewer: Spewer,
+		template: ParticleTemplate<M>,
+	) -> &mut Self {
+		self.insert(key, move |effect: &mut EntityCommands, transform| {
+			effect.insert(SpewerBundle {
+				spewer: spewer.instance(template.clone().factory()),
+				transform: TransformBundle::from_transform(transform),
+				..default()
+			});
+		})
+	}
+
+	pub fn remove(&mut self, key: &K) -> bool {
+		self.effects.remove(key).is_some()
+	}
+
+	pub fn contains(&self, key: &K) -> bool {
+		self.effects.contains_key(key)
+	}
+
+	pub fn keys(&self) -> impl Iterator<Item = &K> {
+		self.effects.keys()
+	}
+}
+
+/// Spawns effects from a [ParticleEffectLibrary].
+pub trait SpawnEffectExt {
+	/// Spawns the effect registered as `key` at `transform`, returning its entity. If there
+	/// is no such effect, a warning is logged and the entity is despawned again.
+	fn spawn_effect<K: Hash + Eq + std::fmt::Debug + Send + Sync + 'static>(
+		&mut self,
+		key: K,
+		transform: Transform,
+	) -> Entity;
+
+	/// Spawns the effect named `name` in the default [ParticleEffectLibrary], giving it an
+	/// [EffectName] of `name` unless the effect gives itself another.
+	fn spawn_effect_by_name(
+		&mut self,
+		name: impl Into<Cow<'static, str>>,
+		transform: Transform,
+	) -> Entity;
+}
+
+impl SpawnEffectExt for Commands<'_, '_> {
+	fn spawn_effect<K: Hash + Eq + std::fmt::Debug + Send + Sync + 'static>(
+		&mut self,
+		key: K,
+		transform: Transform,
+	) -> Entity {
+		let id = self.spawn_empty().id();
+		spawn_effect_on(self, id, key, transform);
+		id
+	}
+
+	fn spawn_effect_by_name(
+		&mut self,
+		name: impl Into<Cow<'static, str>>,
+		transform: Transform,
+	) -> Entity {
+		let name = name.into();
+		let id = self.spawn(EffectName(name.clone())).id();
+		spawn_effect_on(self, id, name, transform);
+		id
+	}
+}
+
+fn spawn_effect_on<K: Hash + Eq + std::fmt::Debug + Send + Sync + 'static>(
+	cmds: &mut Commands,
+	id: Entity,
+	key: K,
+	transform: Transform,
+) {
+	cmds.add(move |world: &mut World| {
+		let spawn = world
+			.get_resource::<ParticleEffectLibrary<K>>()
+			.and_then(|library| library.effects.get(&key))
+			.cloned();
+		let Some(spawn) = spawn else {
+			warn!("no particle effect {key:?} in the library");
+			if let Some(entity) = world.get_entity_mut(id) {
+				entity.despawn_recursive();
+			}
+			return;
+		};
+		let mut queue = CommandQueue::default();
+		let mut cmds = Commands::new(&mut queue, world);
+		spawn(&mut cmds.entity(id), transform);
+		queue.apply(world);
+	});
+}
@@ -0,0 +1,340 @@
+//! Links between spewers and the particles they spawned, independent of the hierarchy,
+//! since world-space particles aren't children of their spewer.
+
+#[cfg(feature = "render")]
+use bevy::ecs::query::QueryItem;
+use bevy::{
+	ecs::world::Command,
+	prelude::*,
+	utils::{Duration, HashMap, HashSet},
+};
+
+use crate::{
+	buffer::ParticleBuffer,
+	update::{Linear, Velocity},
+	InitialTransform, Lifetime, Spewer,
+};
+
+/// The spewer that spawned this particle. Added to every particle a [Spewer] spawns,
+/// whether or not it is a child of the spewer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component, Reflect)]
+#[reflect(Component)]
+pub struct ParticleOf(pub Entity);
+
+/// Number of live particles spawned by this spewer, updated every frame. Add it to
+/// spewers whose particles should be counted, e.g. for debug overlays.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Component, Deref, Reflect)]
+#[reflect(Component)]
+pub struct ParticleCount(pub usize);
+
+impl ParticleCount {
+	pub fn update(mut spewers: Query<(Entity, &mut Self)>, particles: Query<&ParticleOf>) {
+		if spewers.is_empty() {
+			return;
+		}
+		let mut counts = HashMap::<Entity, usize>::default();
+		for &ParticleOf(spewer) in &particles {
+			*counts.entry(spewer).or_default() += 1;
+		}
+		for (id, mut count) in &mut spewers {
+			count.set_if_neq(Self(counts.get(&id).copied().unwrap_or(0)));
+		}
+	}
+}
+
+/// Despawns all live particles of the targeted spewer, wherever they are in the hierarchy:
+///
+/// ```ignore
+/// commands.trigger_targets(ClearParticles, spewer);
+/// ```
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ClearParticles;
+
+pub fn handle_clear_particles(
+	trigger: Trigger<ClearParticles>,
+	mut cmds: Commands,
+	particles: Query<(Entity, &ParticleOf)>,
+) {
+	let spewer = trigger.entity();
+	for (id, _) in particles.iter().filter(|(_, of)| of.0 == spewer) {
+		cmds.entity(id).despawn_recursive();
+	}
+}
+
+/// Which particles a [KillParticles] despawns, by their position in world space.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum ParticleRegion {
+	Sphere { center: Vec3, radius: f32 },
+	Box { min: Vec3, max: Vec3 },
+}
+
+impl ParticleRegion {
+	pub fn contains(&self, point: Vec3) -> bool {
+		match *self {
+			Self::Sphere { center, radius } => point.distance_squared(center) <= radius * radius,
+			Self::Box { min, max } => point.cmpge(min).all() && point.cmple(max).all(),
+		}
+	}
+}
+
+/// Despawns every particle (anything with a [Lifetime]) matching all of its filters, e.g. to
+/// clear [Lifetime::INFINITE] particles deliberately:
+///
+/// ```ignore
+/// commands.add(KillParticles::all().of_spewer(snowfall).in_region(region));
+/// ```
+#[derive(Default)]
+pub struct KillParticles {
+	/// Only particles spawned by this spewer, wherever they are in the hierarchy.
+	pub spewer: Option<Entity>,
+	pub region: Option<ParticleRegion>,
+	pub predicate: Option<Box<dyn Fn(EntityRef) -> bool + Send + Sync>>,
+}
+
+impl KillParticles {
+	pub fn all() -> Self {
+		Self::default()
+	}
+
+	pub fn of_spewer(self, spewer: Entity) -> Self {
+		Self {
+			spewer: Some(spewer),
+			..self
+		}
+	}
+
+	pub fn in_region(self, region: ParticleRegion) -> Self {
+		Self {
+			region: Some(region),
+			..self
+		}
+	}
+
+	pub fn matching(self, predicate: impl Fn(EntityRef) -> bool + Send + Sync + 'static) -> Self {
+		Self {
+			predicate: Some(Box::new(predicate)),
+			..self
+		}
+	}
+}
+
+impl Command for KillParticles {
+	fn apply(self, world: &mut World) {
+		let mut particles = world.query_filtered::<EntityRef, With<Lifetime>>();
+		let killed = particles
+			.iter(world)
+			.filter(|particle| {
+				self.spewer.is_none() || particle.get::<ParticleOf>().map(|of| of.0) == self.spewer
+			})
+			.filter(|particle| match self.region {
+				Some(region) => particle
+					.get::<GlobalTransform>()
+					.is_some_and(|xform| region.contains(xform.translation())),
+				None => true,
+			})
+			.filter(|&particle| self.predicate.as_ref().is_none_or(|f| f(particle)))
+			.map(|particle| particle.id())
+			.collect::<Vec<_>>();
+		for id in killed {
+			if let Some(particle) = world.get_entity_mut(id) {
+				particle.despawn_recursive();
+			}
+		}
+	}
+}
+
+/// Despawns this spewer's particles along with it, including world-space ones. Without it,
+/// world-space particles live out their lifetimes after their spewer is despawned.
+///
+/// Copied from the spewer onto each particle it spawns.
+#[derive(Default, Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct DespawnWithSpewer;
+
+pub fn despawn_with_spewers(
+	mut cmds: Commands,
+	mut removed: RemovedComponents<Spewer>,
+	particles: Query<(Entity, &ParticleOf), With<DespawnWithSpewer>>,
+	spewers: Query<(), With<Spewer>>,
+) {
+	let removed = removed
+		.read()
+		.filter(|&id| !spewers.contains(id))
+		.collect::<HashSet<_>>();
+	if removed.is_empty() {
+		return;
+	}
+	for (id, of) in &particles {
+		if removed.contains(&of.0) {
+			cmds.entity(id).despawn_recursive();
+		}
+	}
+}
+
+/// How this spewer's particles follow its visibility. Without it, hiding a spewer only
+/// hides the local-space particles that inherit its visibility as its children, and
+/// world-space ones keep showing.
+#[cfg(feature = "render")]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Component, Reflect)]
+#[reflect(Component)]
+pub enum SpewerVisibility {
+	/// Hides all of its particles while the spewer is hidden, e.g. to hide an effect at
+	/// once. They keep simulating, and show again along with the spewer.
+	#[default]
+	HideParticles,
+	/// Stops emitting, including bursts, while the spewer is hidden, letting live particles
+	/// finish, e.g. to fade an effect out instead of cutting it off.
+	StopEmitting,
+}
+
+#[cfg(feature = "render")]
+impl SpewerVisibility {
+	/// Makes world-space particles of [SpewerVisibility::HideParticles] spewers inherit
+	/// their spewer's visibility, as local-space ones do through the hierarchy. Runs
+	/// between visibility propagation and visibility checks.
+	pub fn hide_particles(
+		spewers: Query<(Entity, &Self, &InheritedVisibility)>,
+		mut particles: Query<
+			(&ParticleOf, &Visibility, &mut InheritedVisibility),
+			(Without<Parent>, Without<Self>),
+		>,
+	) {
+		let spewers = spewers
+			.iter()
+			.filter(|(_, mode, _)| **mode == Self::HideParticles)
+			.map(|(id, _, inherited)| (id, inherited.get()))
+			.collect::<HashMap<_, _>>();
+		if spewers.is_empty() {
+			return;
+		}
+		particles
+			.par_iter_mut()
+			.for_each(|(of, visibility, mut inherited)| {
+				let Some(&spewer_visible) = spewers.get(&of.0) else {
+					return;
+				};
+				// World-space particles are roots, so their own `Visibility` is all they'd
+				// otherwise inherit.
+				let visible = spewer_visible && *visibility != Visibility::Hidden;
+				inherited.set_if_neq(if visible {
+					InheritedVisibility::VISIBLE
+				} else {
+					InheritedVisibility::HIDDEN
+				});
+			});
+	}
+}
+
+/// Visibility of a spewer that stops emitting while hidden.
+#[cfg(feature = "render")]
+pub(crate) type SpewerShown = Option<(&'static SpewerVisibility, &'static InheritedVisibility)>;
+#[cfg(not(feature = "render"))]
+pub(crate) type SpewerShown = ();
+
+/// Whether a spewer may emit, i.e. isn't stopped by its [SpewerVisibility].
+#[cfg(feature = "render")]
+pub(crate) fn spewer_emits(shown: QueryItem<SpewerShown>) -> bool {
+	match shown {
+		Some((SpewerVisibility::StopEmitting, inherited)) => inherited.get(),
+		_ => true,
+	}
+}
+#[cfg(not(feature = "render"))]
+pub(crate) fn spewer_emits((): ()) -> bool {
+	true
+}
+
+/// Marks a spewer that stopped emitting with [stop_emitting_and_finish] and is despawned
+/// once all of its particles have expired.
+#[derive(Default, Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct Finishing;
+
+/// Stops a spewer from emitting and lets its particles finish naturally, e.g. when the
+/// object carrying an effect is destroyed:
+///
+/// ```ignore
+/// commands.entity(spewer).add(stop_emitting_and_finish);
+/// ```
+///
+/// Its local-space particles are moved to world space, keeping their global transforms
+/// along with their [InitialTransform], [Velocity], and [Linear] velocity, so the spewer's
+/// parent can be despawned right away. Child [ParticleBuffer]s stop emitting too. The
+/// spewer itself is despawned once all of its particles have expired, including those in
+/// its child buffers. Later [EmitBurst](crate::EmitBurst)s no longer make it emit.
+pub fn stop_emitting_and_finish(id: Entity, world: &mut World) {
+	let Some(mut spewer) = world.get_mut::<Spewer>(id) else {
+		return;
+	};
+	spewer.interval = Duration::ZERO;
+	spewer.pending_burst = 0;
+	// The spewer may be about to lose its parent, and its particles must not move with it.
+	detach_in_place(world.entity_mut(id).insert(Finishing));
+
+	let parent_xform = world
+		.get::<GlobalTransform>(id)
+		.copied()
+		.unwrap_or_default();
+	let parent = parent_xform.affine();
+	let children = world
+		.get::<Children>(id)
+		.map(|children| children.to_vec())
+		.unwrap_or_default();
+	for child in children {
+		// Buffer particles are already simulated in world space.
+		if let Some(mut buffer) = world.get_mut::<ParticleBuffer>(child) {
+			buffer.interval = Duration::ZERO;
+			continue;
+		}
+		if world.get::<ParticleOf>(child) != Some(&ParticleOf(id)) {
+			continue;
+		}
+		let mut particle = world.entity_mut(child);
+		detach_in_place(&mut particle);
+		if let Some(mut initial) = particle.get_mut::<InitialTransform>() {
+			initial.0 = parent_xform.mul_transform(initial.0).compute_transform();
+		}
+		if let Some(mut vel) = particle.get_mut::<Velocity>() {
+			vel.0 = parent.transform_vector3(vel.0);
+		}
+		if let Some(mut linear) = particle.get_mut::<Linear>() {
+			linear.velocity = parent.transform_vector3(linear.velocity);
+		}
+	}
+}
+
+/// Removes the entity's parent, with its `Transform` set to keep it where it was.
+fn detach_in_place(entity: &mut EntityWorldMut) {
+	if let Some(&global_xform) = entity.get::<GlobalTransform>() {
+		entity.insert(global_xform.compute_transform());
+	}
+	entity.remove_parent();
+}
+
+/// Despawns [Finishing] spewers without any particles left, neither particle entities nor
+/// particles in child [ParticleBuffer]s.
+pub fn despawn_finished_spewers(
+	mut cmds: Commands,
+	spewers: Query<Entity, With<Finishing>>,
+	particles: Query<&ParticleOf>,
+	buffers: Query<(&Parent, &ParticleBuffer)>,
+) {
+	if spewers.is_empty() {
+		return;
+	}
+	let alive = particles
+		.iter()
+		.map(|of| of.0)
+		.chain(
+			buffers
+				.iter()
+				.filter(|(_, buffer)| !buffer.is_empty())
+				.map(|(parent, _)| parent.get()),
+		)
+		.collect::<HashSet<_>>();
+	for id in &spewers {
+		if !alive.contains(&id) {
+			cmds.entity(id).despawn_recursive();
+		}
+	}
+}
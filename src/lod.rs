@@ -0,0 +1,161 @@
+//! Swapping distant mesh particles for cheap imposter billboards.
+
+use bevy::{prelude::*, render::view::RenderLayers};
+
+use crate::{
+	color::{ParticleFade, SpewerTint},
+	material::ParticleMaterial,
+	update::{main_camera, Cameras},
+};
+
+/// Draws the particle as an imposter billboard instead of its mesh beyond `distance` from
+/// the main camera, e.g. for dense debris fields that would be too expensive to draw as
+/// meshes at range.
+///
+/// The imposter is a child of the particle with `imposter_mesh` and `imposter_material`,
+/// usually a [ParticleMesh::Quad](crate::mesh::ParticleMesh::Quad) with a
+/// [Billboard](crate::material::Billboard) material textured with a pre-rendered image of
+/// the mesh. Within `crossfade` around `distance`, both are drawn and crossfade through
+/// their [ParticleFade]s. Beyond it, the particle's mesh is taken off it until it comes
+/// back into range.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub struct ParticleLod {
+	pub distance: f32,
+	pub crossfade: f32,
+	pub imposter_mesh: Handle<Mesh>,
+	pub imposter_material: Handle<ParticleMaterial>,
+	/// Scale of the imposter relative to the particle, to match the size of its mesh.
+	pub imposter_scale: f32,
+	imposter: Option<Entity>,
+	/// The particle's mesh while it is taken off.
+	mesh: Option<Handle<Mesh>>,
+}
+
+/// Marks the imposter of a [ParticleLod] particle.
+#[derive(Default, Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct LodImposter;
+
+impl ParticleLod {
+	pub fn new(
+		distance: f32,
+		imposter_mesh: Handle<Mesh>,
+		imposter_material: Handle<ParticleMaterial>,
+	) -> Self {
+		Self {
+			distance,
+			crossfade: distance * 0.1,
+			imposter_mesh,
+			imposter_material,
+			imposter_scale: 1.0,
+			imposter: None,
+			mesh: None,
+		}
+	}
+
+	pub fn with_crossfade(self, crossfade: f32) -> Self {
+		Self { crossfade, ..self }
+	}
+
+	pub fn with_imposter_scale(self, imposter_scale: f32) -> Self {
+		Self {
+			imposter_scale,
+			..self
+		}
+	}
+
+	/// How far the particle is blended towards its imposter at `distance` from the camera,
+	/// from `0.0` for only the mesh to `1.0` for only the imposter.
+	pub fn blend(&self, distance: f32) -> f32 {
+		if self.crossfade <= 0.0 {
+			return if distance > self.distance { 1.0 } else { 0.0 };
+		}
+		((distance - self.distance) / self.crossfade + 0.5).clamp(0.0, 1.0)
+	}
+
+	pub fn tick(
+		mut cmds: Commands,
+		mut q: Query<(
+			Entity,
+			&mut Self,
+			&GlobalTransform,
+			Option<&Handle<Mesh>>,
+			Option<&mut ParticleFade>,
+			Option<&SpewerTint>,
+			Option<&RenderLayers>,
+		)>,
+		mut imposters: Query<&mut ParticleFade, (With<LodImposter>, Without<Self>)>,
+		orphans: Query<(Entity, &Parent), With<LodImposter>>,
+		cameras: Cameras,
+	) {
+		// Particles are despawned without their children when they expire.
+		for (id, parent) in &orphans {
+			if !q.contains(parent.get()) {
+				cmds.entity(id).despawn();
+			}
+		}
+		let Some((.., camera)) = main_camera(&cameras) else {
+			return;
+		};
+		let camera = camera.translation();
+		for (id, mut lod, xform, mesh, fade, tint, layers) in &mut q {
+			let blend = lod.blend(xform.translation().distance(camera));
+
+			let mesh_fade = ParticleFade(1.0 - blend);
+			match fade {
+				Some(mut fade) => {
+					fade.set_if_neq(mesh_fade);
+				}
+				None if blend > 0.0 => {
+					cmds.entity(id).insert(mesh_fade);
+				}
+				None => {}
+			}
+			if blend >= 1.0 {
+				if let Some(mesh) = mesh.filter(|_| lod.mesh.is_none()) {
+					lod.mesh = Some(mesh.clone());
+					cmds.entity(id).remove::<Handle<Mesh>>();
+				}
+			} else if let Some(mesh) = lod.mesh.take() {
+				cmds.entity(id).insert(mesh);
+			}
+
+			let imposter_fade = ParticleFade(blend);
+			match lod.imposter {
+				Some(imposter) if blend <= 0.0 => {
+					cmds.entity(imposter).despawn();
+					lod.imposter = None;
+				}
+				Some(imposter) => {
+					if let Ok(mut fade) = imposters.get_mut(imposter) {
+						fade.set_if_neq(imposter_fade);
+					}
+				}
+				None if blend > 0.0 => {
+					let local = Transform::from_scale(Vec3::splat(lod.imposter_scale));
+					let mut imposter = cmds.spawn((
+						MaterialMeshBundle {
+							mesh: lod.imposter_mesh.clone(),
+							material: lod.imposter_material.clone(),
+							transform: local,
+							global_transform: xform.mul_transform(local),
+							..default()
+						},
+						imposter_fade,
+						LodImposter,
+					));
+					if let Some(tint) = tint {
+						imposter.insert(*tint);
+					}
+					if let Some(layers) = layers {
+						imposter.insert(layers.clone());
+					}
+					let imposter = imposter.set_parent(id).id();
+					lod.imposter = Some(imposter);
+				}
+				None => {}
+			}
+		}
+	}
+}
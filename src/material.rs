@@ -0,0 +1,187 @@
+use bevy::{
+	asset::load_internal_asset,
+	pbr::{ExtendedMaterial, MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline},
+	prelude::*,
+	render::{
+		mesh::MeshVertexBufferLayoutRef,
+		render_resource::{
+			AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+		},
+	},
+};
+
+pub const PARTICLE_MATERIAL_SHADER_HANDLE: Handle<Shader> =
+	Handle::weak_from_u128(292113296738667452581535082117880073441);
+pub const PARTICLE_PREPASS_SHADER_HANDLE: Handle<Shader> =
+	Handle::weak_from_u128(88913153971882657698620363437116382750);
+pub const BILLBOARD_SHADER_HANDLE: Handle<Shader> =
+	Handle::weak_from_u128(214337487663615311158555226030303603330);
+
+/// `StandardMaterial` with particle-specific vertex and fragment effects.
+///
+/// Skinned and morphed meshes are not supported.
+///
+/// With an opaque or masked `alpha_mode`, billboarded particles are drawn into the
+/// camera's prepasses too, writing motion vectors from their previous transforms for TAA
+/// and motion blur. Blended particles, like all transparent meshes, write no motion vectors.
+pub type ParticleMaterial = ExtendedMaterial<StandardMaterial, ParticleExtension>;
+
+pub struct ParticleMaterialPlugin;
+
+impl Plugin for ParticleMaterialPlugin {
+	fn build(&self, app: &mut App) {
+		load_internal_asset!(
+			app,
+			BILLBOARD_SHADER_HANDLE,
+			"billboard.wgsl",
+			Shader::from_wgsl
+		);
+		load_internal_asset!(
+			app,
+			PARTICLE_PREPASS_SHADER_HANDLE,
+			"prepass.wgsl",
+			Shader::from_wgsl
+		);
+		load_internal_asset!(
+			app,
+			PARTICLE_MATERIAL_SHADER_HANDLE,
+			"material.wgsl",
+			Shader::from_wgsl
+		);
+		app.add_plugins(MaterialPlugin::<ParticleMaterial>::default())
+			.register_asset_reflect::<ParticleMaterial>();
+	}
+}
+
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+#[bind_group_data(ParticleExtensionKey)]
+pub struct ParticleExtension {
+	/// How particles are oriented relative to the camera. Computed in the vertex
+	/// shader, so it is correct for every camera (including orthographic ones)
+	/// that renders the particle.
+	pub billboard: Billboard,
+	/// Distance (in world units) over which fragments fade out as they approach
+	/// opaque geometry behind them ("soft particles").
+	///
+	/// Requires a `DepthPrepass` on the camera, and is skipped with MSAA on devices that
+	/// can't read multisampled depth in shaders, like WebGL2. `0.0` disables the fade.
+	#[uniform(100)]
+	pub soft_fade_distance: f32,
+	/// Distance (in world units) past the camera's near plane over which whole
+	/// particles fade out as they approach the camera, based on the view depth
+	/// of the particle's origin. Avoids quads popping when flying through an effect.
+	///
+	/// `0.0` disables the fade.
+	#[uniform(100)]
+	pub near_fade_distance: f32,
+	/// Size in pixels of [Billboard::PointSprite] particles with a mesh one unit across,
+	/// like [ParticleMesh::Quad](crate::mesh::ParticleMesh::Quad), and a scale of `1.0`.
+	#[uniform(100)]
+	pub point_size: f32,
+	/// Distance from the camera at which [Billboard::PointSprite] particles are
+	/// `point_size` pixels across, shrinking further away and growing closer, as in
+	/// perspective.
+	///
+	/// `0.0` keeps them the same size at every distance, as do orthographic cameras.
+	#[uniform(100)]
+	pub point_attenuation_distance: f32,
+	/// Read by the shaders as `particle_extension.user_data`. Set per particle by
+	/// [ParticleUserData](crate::ParticleUserData).
+	#[uniform(100)]
+	pub user_data: Vec4,
+}
+
+impl Default for ParticleExtension {
+	fn default() -> Self {
+		Self {
+			billboard: Billboard::None,
+			soft_fade_distance: 0.0,
+			near_fade_distance: 0.0,
+			point_size: 4.0,
+			point_attenuation_distance: 0.0,
+			user_data: Vec4::ZERO,
+		}
+	}
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum Billboard {
+	/// Use the particle's own rotation.
+	#[default]
+	None,
+	/// The mesh's XY plane always faces the camera. The particle's rotation is
+	/// ignored, but its scale is kept.
+	FaceCamera,
+	/// The mesh rotates only around the particle's local Y axis to face the camera.
+	/// Combined with a Y scale and an alignment along velocity, this gives
+	/// stretched billboards.
+	AxisAligned,
+	/// Faces the camera like [Billboard::FaceCamera], but is sized in pixels, by
+	/// [ParticleExtension::point_size] times the particle's scale. Cheaper and simpler than
+	/// sizing quads in world units for starfields and sparkles.
+	///
+	/// Particles are still culled by their mesh's bounds, so sprites much larger on screen
+	/// than their mesh may need `NoFrustumCulling`.
+	PointSprite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParticleExtensionKey {
+	pub billboard: Billboard,
+}
+
+impl From<&ParticleExtension> for ParticleExtensionKey {
+	fn from(ext: &ParticleExtension) -> Self {
+		Self {
+			billboard: ext.billboard,
+		}
+	}
+}
+
+impl MaterialExtension for ParticleExtension {
+	fn vertex_shader() -> ShaderRef {
+		PARTICLE_MATERIAL_SHADER_HANDLE.into()
+	}
+
+	fn fragment_shader() -> ShaderRef {
+		PARTICLE_MATERIAL_SHADER_HANDLE.into()
+	}
+
+	fn prepass_vertex_shader() -> ShaderRef {
+		PARTICLE_PREPASS_SHADER_HANDLE.into()
+	}
+
+	fn deferred_vertex_shader() -> ShaderRef {
+		PARTICLE_PREPASS_SHADER_HANDLE.into()
+	}
+
+	fn specialize(
+		pipeline: &MaterialExtensionPipeline,
+		descriptor: &mut RenderPipelineDescriptor,
+		_layout: &MeshVertexBufferLayoutRef,
+		key: MaterialExtensionKey<Self>,
+	) -> Result<(), SpecializedMeshPipelineError> {
+		let defs: &[&str] = match key.bind_group_data.billboard {
+			Billboard::None => &[],
+			Billboard::FaceCamera => &["BILLBOARD"],
+			Billboard::AxisAligned => &["BILLBOARD", "BILLBOARD_AXIS_ALIGNED"],
+			Billboard::PointSprite => &["BILLBOARD", "BILLBOARD_POINT_SPRITE"],
+		};
+		descriptor
+			.vertex
+			.shader_defs
+			.extend(defs.iter().map(|&def| def.into()));
+		// Bevy only falls back to uniform buffers on devices without storage buffers, like
+		// WebGL2, which can't read multisampled depth in shaders either.
+		if pipeline
+			.mesh_pipeline
+			.per_object_buffer_batch_size
+			.is_some()
+		{
+			if let Some(fragment) = descriptor.fragment.as_mut() {
+				fragment.shader_defs.push("NO_MULTISAMPLED_DEPTH".into());
+			}
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,159 @@
+#import bevy_pbr::{
+	forward_io::{Vertex, VertexOutput, FragmentOutput},
+	mesh_functions,
+	mesh_functions::get_world_from_local,
+	pbr_fragment::pbr_input_from_standard_material,
+	pbr_functions::{alpha_discard, apply_pbr_lighting, main_pass_post_lighting_processing},
+	pbr_types::STANDARD_MATERIAL_FLAGS_UNLIT_BIT,
+	view_transformations::{
+		depth_ndc_to_view_z, perspective_camera_near, position_world_to_clip, position_world_to_view,
+	},
+}
+#import sond_bevy_particles::billboard::{billboard_position, billboard_rotation, point_sprite_position}
+
+#ifdef DEPTH_PREPASS
+#import bevy_pbr::prepass_utils::prepass_depth
+#endif
+
+struct ParticleExtension {
+	soft_fade_distance: f32,
+	near_fade_distance: f32,
+	point_size: f32,
+	point_attenuation_distance: f32,
+	user_data: vec4<f32>,
+}
+
+@group(2) @binding(100)
+var<uniform> particle_extension: ParticleExtension;
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+	var out: VertexOutput;
+	let world_from_local = get_world_from_local(vertex.instance_index);
+
+#ifdef BILLBOARD
+	let rotation = billboard_rotation(world_from_local);
+#endif
+
+#ifdef VERTEX_NORMALS
+#ifdef BILLBOARD
+	out.world_normal = normalize(rotation * vertex.normal);
+#else
+	out.world_normal = mesh_functions::mesh_normal_local_to_world(vertex.normal, vertex.instance_index);
+#endif
+#endif
+
+#ifdef VERTEX_POSITIONS
+#ifdef BILLBOARD_POINT_SPRITE
+	out.world_position = point_sprite_position(
+		world_from_local,
+		vertex.position,
+		particle_extension.point_size,
+		particle_extension.point_attenuation_distance,
+	);
+#else ifdef BILLBOARD
+	out.world_position = billboard_position(world_from_local, rotation, vertex.position);
+#else
+	out.world_position = mesh_functions::mesh_position_local_to_world(world_from_local, vec4(vertex.position, 1.0));
+#endif
+	out.position = position_world_to_clip(out.world_position.xyz);
+#endif
+
+#ifdef VERTEX_UVS_A
+	out.uv = vertex.uv;
+#endif
+#ifdef VERTEX_UVS_B
+	out.uv_b = vertex.uv_b;
+#endif
+
+#ifdef VERTEX_TANGENTS
+#ifdef BILLBOARD
+	out.world_tangent = vec4(normalize(rotation * vertex.tangent.xyz), vertex.tangent.w);
+#else
+	out.world_tangent = mesh_functions::mesh_tangent_local_to_world(
+		world_from_local,
+		vertex.tangent,
+		vertex.instance_index
+	);
+#endif
+#endif
+
+#ifdef VERTEX_COLORS
+	out.color = vertex.color;
+#endif
+
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+	out.instance_index = vertex.instance_index;
+#endif
+
+#ifdef VISIBILITY_RANGE_DITHER
+	out.visibility_range_dither = mesh_functions::get_visibility_range_dither_level(
+		vertex.instance_index, world_from_local[3]);
+#endif
+
+	return out;
+}
+
+#ifdef DEPTH_PREPASS
+// Soft particles: fade out as the fragment approaches the opaque scene behind it.
+fn depth_soft_fade(in: VertexOutput) -> f32 {
+	let view_z = position_world_to_view(in.world_position.xyz).z;
+	let scene_z = depth_ndc_to_view_z(prepass_depth(in.position, 0u));
+	return saturate((view_z - scene_z) / particle_extension.soft_fade_distance);
+}
+#endif
+
+fn soft_fade(in: VertexOutput) -> f32 {
+#ifdef DEPTH_PREPASS
+#ifdef NO_MULTISAMPLED_DEPTH
+#ifdef MULTISAMPLED
+	// The device can't read multisampled depth, so there is no prepass depth to read.
+	return 1.0;
+#else
+	return depth_soft_fade(in);
+#endif
+#else
+	return depth_soft_fade(in);
+#endif
+#else
+	return 1.0;
+#endif
+}
+
+@fragment
+fn fragment(
+	in: VertexOutput,
+	@builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+	var pbr_input = pbr_input_from_standard_material(in, is_front);
+
+	var fade = 1.0;
+
+	if particle_extension.soft_fade_distance > 0.0 {
+		fade *= soft_fade(in);
+	}
+
+	// Fade whole particles out as their origin approaches the camera's near plane.
+	if particle_extension.near_fade_distance > 0.0 {
+#ifdef VIEW_PROJECTION_PERSPECTIVE
+		let near = perspective_camera_near();
+#else
+		let near = 0.0;
+#endif
+		let origin = get_world_from_local(in.instance_index)[3].xyz;
+		let origin_depth = -position_world_to_view(origin).z - near;
+		fade *= saturate(origin_depth / particle_extension.near_fade_distance);
+	}
+
+	pbr_input.material.base_color.a *= fade;
+	pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+	var out: FragmentOutput;
+	if (pbr_input.material.flags & STANDARD_MATERIAL_FLAGS_UNLIT_BIT) == 0u {
+		out.color = apply_pbr_lighting(pbr_input);
+	} else {
+		out.color = pbr_input.material.base_color;
+	}
+	out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+	return out;
+}
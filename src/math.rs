@@ -0,0 +1,347 @@
+//! Pure particle math, independent of the ECS and rendering, shared by the behaviors and
+//! the [ParticleBuffer](crate::buffer::ParticleBuffer). See [noise](crate::noise) for
+//! seedable noise.
+//!
+//! Only depends on glam, and on `bevy_reflect` so [Easing] can be edited in inspectors.
+//! It still uses `std`'s float functions, so it isn't `no_std`. Emission shape sampling
+//! stays in [emission](crate::emission), since it samples images and reads the spewer's
+//! RNG, and nothing here is shared with shaders: the GPU only receives the progress that
+//! [lifetime_progress] computed on the CPU.
+
+use bevy::{math::Vec3, reflect::Reflect};
+
+/// How far through a `lifetime` of seconds a particle of `age` seconds is, from `0.0` when
+/// it spawns to `1.0` when it expires. Clamped, so curves are never sampled past either
+/// end, and `1.0` for zero lifetimes.
+pub fn lifetime_progress(age: f32, lifetime: f32) -> f32 {
+	if lifetime > 0.0 {
+		(age / lifetime).clamp(0.0, 1.0)
+	} else {
+		1.0
+	}
+}
+
+/// Fraction of a quantity left after decaying exponentially at `rate` per second for `dt`
+/// seconds. Exact for any `dt`, so long frames never overshoot past zero.
+pub fn decay(rate: f32, dt: f32) -> f32 {
+	(-rate * dt).exp()
+}
+
+/// Steps a particle under constant `acceleration` by `dt` seconds with semi-implicit
+/// Euler, returning its new position and velocity.
+pub fn integrate(position: Vec3, velocity: Vec3, acceleration: Vec3, dt: f32) -> (Vec3, Vec3) {
+	let velocity = velocity + acceleration * dt;
+	(position + velocity * dt, velocity)
+}
+
+/// A curve remapping progress of an interpolation from `0.0` to `1.0`, e.g. for
+/// [TargetScale](crate::update::TargetScale) to pop in past its target and settle back.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Reflect)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum Easing {
+	#[default]
+	Linear,
+	QuadIn,
+	QuadOut,
+	QuadInOut,
+	CubicIn,
+	CubicOut,
+	CubicInOut,
+	SineIn,
+	SineOut,
+	SineInOut,
+	/// Pulls back before starting, undershooting below `0.0`.
+	BackIn,
+	/// Overshoots past `1.0` before settling, e.g. for a scale pop.
+	BackOut,
+	/// Springs past `1.0` and oscillates until it settles.
+	ElasticOut,
+	/// Bounces off `1.0` like a dropped ball.
+	BounceOut,
+	/// A CSS-style cubic Bézier from `(0, 0)` to `(1, 1)` with control points `(x1, y1)`
+	/// and `(x2, y2)`. `x1` and `x2` are clamped to `0.0..=1.0`, so there is only one
+	/// value for each progress, but `y1` and `y2` can go outside it to overshoot.
+	CubicBezier {
+		x1: f32,
+		y1: f32,
+		x2: f32,
+		y2: f32,
+	},
+}
+
+impl Easing {
+	/// The eased value of progress `s`, which is clamped to `0.0..=1.0`. Always `0.0` at
+	/// `0.0` and `1.0` at `1.0`.
+	pub fn ease(self, s: f32) -> f32 {
+		use std::f32::consts::{FRAC_PI_2, PI, TAU};
+		const BACK: f32 = 1.70158;
+
+		let s = s.clamp(0.0, 1.0);
+		if s == 0.0 || s == 1.0 {
+			return s;
+		}
+		match self {
+			Easing::Linear => s,
+			Easing::QuadIn => s * s,
+			Easing::QuadOut => 1.0 - (1.0 - s) * (1.0 - s),
+			Easing::QuadInOut if s < 0.5 => 2.0 * s * s,
+			Easing::QuadInOut => 1.0 - 2.0 * (1.0 - s) * (1.0 - s),
+			Easing::CubicIn => s * s * s,
+			Easing::CubicOut => 1.0 - (1.0 - s).powi(3),
+			Easing::CubicInOut if s < 0.5 => 4.0 * s * s * s,
+			Easing::CubicInOut => 1.0 - 4.0 * (1.0 - s).powi(3),
+			Easing::SineIn => 1.0 - (s * FRAC_PI_2).cos(),
+			Easing::SineOut => (s * FRAC_PI_2).sin(),
+			Easing::SineInOut => 0.5 - 0.5 * (s * PI).cos(),
+			Easing::BackIn => s * s * ((BACK + 1.0) * s - BACK),
+			Easing::BackOut => {
+				let s = s - 1.0;
+				1.0 + s * s * ((BACK + 1.0) * s + BACK)
+			}
+			Easing::ElasticOut => 1.0 + (-10.0 * s).exp2() * ((s * 10.0 - 0.75) * TAU / 3.0).sin(),
+			Easing::BounceOut => bounce_out(s),
+			Easing::CubicBezier { x1, y1, x2, y2 } => {
+				cubic_bezier(x1.clamp(0.0, 1.0), y1, x2.clamp(0.0, 1.0), y2, s)
+			}
+		}
+	}
+}
+
+fn bounce_out(s: f32) -> f32 {
+	const N: f32 = 7.5625;
+	const D: f32 = 2.75;
+	if s < 1.0 / D {
+		N * s * s
+	} else if s < 2.0 / D {
+		let s = s - 1.5 / D;
+		N * s * s + 0.75
+	} else if s < 2.5 / D {
+		let s = s - 2.25 / D;
+		N * s * s + 0.9375
+	} else {
+		let s = s - 2.625 / D;
+		N * s * s + 0.984375
+	}
+}
+
+/// The `y` of the Bézier curve through `(0, 0)`, `(x1, y1)`, `(x2, y2)` and `(1, 1)` where
+/// its `x` is `x`.
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, x: f32) -> f32 {
+	let bezier = |a: f32, b: f32, t: f32| {
+		let u = 1.0 - t;
+		3.0 * u * u * t * a + 3.0 * u * t * t * b + t * t * t
+	};
+	// `x` only ever increases along the curve, so bisection always finds it. Newton steps
+	// first, since they usually converge in a few.
+	let mut t = x;
+	for _ in 0..4 {
+		let u = 1.0 - t;
+		let slope = 3.0 * u * u * x1 + 6.0 * u * t * (x2 - x1) + 3.0 * t * t * (1.0 - x2);
+		if slope.abs() < 1e-6 {
+			break;
+		}
+		t = (t - (bezier(x1, x2, t) - x) / slope).clamp(0.0, 1.0);
+	}
+	if (bezier(x1, x2, t) - x).abs() > 1e-7 {
+		let (mut lo, mut hi) = (0.0, 1.0);
+		for _ in 0..24 {
+			t = 0.5 * (lo + hi);
+			if bezier(x1, x2, t) < x {
+				lo = t;
+			} else {
+				hi = t;
+			}
+		}
+	}
+	bezier(y1, y2, t)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn lifetime_progress_is_clamped() {
+		assert_eq!(lifetime_progress(0.0, 2.0), 0.0);
+		assert_eq!(lifetime_progress(0.5, 2.0), 0.25);
+		assert_eq!(lifetime_progress(2.0, 2.0), 1.0);
+		assert_eq!(lifetime_progress(5.0, 2.0), 1.0);
+		assert_eq!(lifetime_progress(-1.0, 2.0), 0.0);
+	}
+
+	#[test]
+	fn lifetime_progress_of_empty_lifetimes_is_one() {
+		for age in [-1.0, 0.0, 1.0] {
+			assert_eq!(lifetime_progress(age, 0.0), 1.0);
+			assert_eq!(lifetime_progress(age, -1.0), 1.0);
+		}
+	}
+
+	#[test]
+	fn decay_matches_exp() {
+		for rate in [0.0, 0.5, 3.0, 40.0] {
+			for dt in [0.0, 1.0 / 240.0, 1.0 / 30.0, 0.5, 4.0] {
+				assert_eq!(decay(rate, dt), (-rate * dt).exp());
+			}
+		}
+	}
+
+	#[test]
+	fn decay_is_the_same_over_split_steps() {
+		for rate in [0.5, 3.0, 40.0] {
+			let whole = decay(rate, 1.0);
+			for steps in [2, 30, 240] {
+				let split = (0..steps).fold(1.0, |left, _| left * decay(rate, 1.0 / steps as f32));
+				assert!(
+					(split - whole).abs() <= whole * 1e-4 + 1e-9,
+					"rate {rate}, {steps} steps: {split} != {whole}"
+				);
+			}
+		}
+	}
+
+	const BEZIERS: [Easing; 4] = [
+		Easing::CubicBezier {
+			x1: 0.25,
+			y1: 0.1,
+			x2: 0.25,
+			y2: 1.0,
+		},
+		Easing::CubicBezier {
+			x1: 0.9,
+			y1: 0.0,
+			x2: 0.1,
+			y2: 1.0,
+		},
+		Easing::CubicBezier {
+			x1: 1.0,
+			y1: 0.0,
+			x2: 0.0,
+			y2: 1.0,
+		},
+		Easing::CubicBezier {
+			x1: 0.3,
+			y1: -0.5,
+			x2: 0.7,
+			y2: 1.5,
+		},
+	];
+
+	fn all_easings() -> impl Iterator<Item = Easing> {
+		[
+			Easing::Linear,
+			Easing::QuadIn,
+			Easing::QuadOut,
+			Easing::QuadInOut,
+			Easing::CubicIn,
+			Easing::CubicOut,
+			Easing::CubicInOut,
+			Easing::SineIn,
+			Easing::SineOut,
+			Easing::SineInOut,
+			Easing::BackIn,
+			Easing::BackOut,
+			Easing::ElasticOut,
+			Easing::BounceOut,
+		]
+		.into_iter()
+		.chain(BEZIERS)
+	}
+
+	/// `x` of the Bézier curve of `easing` at curve parameter `t`.
+	fn bezier_x(easing: Easing, t: f32) -> f32 {
+		let Easing::CubicBezier { x1, x2, .. } = easing else {
+			unreachable!()
+		};
+		let u = 1.0 - t;
+		3.0 * u * u * t * x1 + 3.0 * u * t * t * x2 + t * t * t
+	}
+
+	fn bezier_y(easing: Easing, t: f32) -> f32 {
+		let Easing::CubicBezier { y1, y2, .. } = easing else {
+			unreachable!()
+		};
+		let u = 1.0 - t;
+		3.0 * u * u * t * y1 + 3.0 * u * t * t * y2 + t * t * t
+	}
+
+	#[test]
+	fn easings_keep_their_ends() {
+		for easing in all_easings() {
+			assert_eq!(easing.ease(0.0), 0.0, "{easing:?}");
+			assert_eq!(easing.ease(1.0), 1.0, "{easing:?}");
+			assert_eq!(easing.ease(-1.0), 0.0, "{easing:?}");
+			assert_eq!(easing.ease(2.0), 1.0, "{easing:?}");
+		}
+	}
+
+	#[test]
+	fn easings_are_continuous_at_their_ends() {
+		for easing in all_easings() {
+			assert!(easing.ease(1e-4).abs() < 1e-2, "{easing:?}");
+			assert!((easing.ease(1.0 - 1e-4) - 1.0).abs() < 1e-2, "{easing:?}");
+		}
+	}
+
+	#[test]
+	fn straight_bezier_is_linear() {
+		let straight = Easing::CubicBezier {
+			x1: 0.0,
+			y1: 0.0,
+			x2: 1.0,
+			y2: 1.0,
+		};
+		for i in 0..=1000 {
+			let s = i as f32 / 1000.0;
+			assert!(
+				(straight.ease(s) - Easing::Linear.ease(s)).abs() < 1e-4,
+				"{s}: {}",
+				straight.ease(s)
+			);
+		}
+	}
+
+	#[test]
+	fn bezier_solver_converges() {
+		for easing in BEZIERS {
+			for i in 0..=1000 {
+				let t = i as f32 / 1000.0;
+				let (x, y) = (bezier_x(easing, t), bezier_y(easing, t));
+				// Where the curve is nearly vertical, `y` changes a lot for a tiny error in
+				// `x`, so it is checked against the curve's `y` along a small range of `x`.
+				let (lo, hi) = ((t - 5e-3).max(0.0), (t + 5e-3).min(1.0));
+				let (y_lo, y_hi) = (bezier_y(easing, lo), bezier_y(easing, hi));
+				let eased = easing.ease(x);
+				let (min, max) = (y.min(y_lo).min(y_hi), y.max(y_lo).max(y_hi));
+				assert!(
+					eased >= min - 1e-4 && eased <= max + 1e-4,
+					"{easing:?} at x {x}: {eased}, expected about {y}"
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn integrate_under_constant_acceleration() {
+		let (x0, v0, a) = (
+			Vec3::new(1.0, 2.0, 3.0),
+			Vec3::new(0.0, 5.0, -1.0),
+			Vec3::NEG_Y * 9.8,
+		);
+		for steps in [1u32, 10, 1000] {
+			let dt = 1.0 / steps as f32;
+			let (mut x, mut v) = (x0, v0);
+			for _ in 0..steps {
+				(x, v) = integrate(x, v, a, dt);
+			}
+			// Velocity is exact, and semi-implicit Euler gains `a * dt * t / 2` on the exact
+			// position.
+			assert!(v.abs_diff_eq(v0 + a, 1e-3), "{steps} steps: {v}");
+			let exact = x0 + v0 + a * 0.5;
+			assert!(
+				x.abs_diff_eq(exact + a * dt * 0.5, 1e-3),
+				"{steps} steps: {x} != {exact}"
+			);
+		}
+	}
+}
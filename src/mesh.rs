@@ -0,0 +1,110 @@
+//! Meshes for common particles, so basic effects don't need to build their own.
+
+use std::f32::consts::PI;
+
+use bevy::{
+	prelude::*,
+	render::{
+		mesh::{Indices, PrimitiveTopology},
+		render_asset::RenderAssetUsages,
+	},
+};
+
+use crate::cache::AssetCache;
+
+/// A common particle mesh, one unit across. Particles are sized by their scale:
+///
+/// ```ignore
+/// let mesh = ParticleMesh::Quad.get_or_add(&mut particle_meshes, &mut meshes);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum ParticleMesh {
+	/// A square in the XY plane, facing +Z, for sprites that are billboarded or oriented by
+	/// their particle.
+	Quad,
+	/// A single triangle around a circle in the XY plane, facing +Z, with UVs mapping the
+	/// circle's bounds to the whole texture. Half the vertices and triangles of a quad, for
+	/// round sprites drawn with [Billboard::FaceCamera](crate::material::Billboard) in large
+	/// numbers. The texture must be transparent at its edges, which are stretched over the
+	/// corners of the triangle.
+	PointSprite,
+	/// An icosphere, where `0` subdivisions is an icosahedron of 20 faces. At most 79.
+	Sphere { subdivisions: u8 },
+	/// Vertical quads crossing at the origin, evenly rotated around the Y axis, e.g. for
+	/// grass and other foliage seen from the side. Their bottom edge is on the origin, and
+	/// both of their faces are drawn.
+	CrossQuads { planes: u8 },
+}
+
+/// Meshes created from [ParticleMesh]es, shared by every particle using them.
+pub type ParticleMeshes = AssetCache<ParticleMesh, Mesh>;
+
+impl ParticleMesh {
+	/// The shared handle of this mesh, adding it to `meshes` if it isn't in `cache`.
+	pub fn get_or_add(self, cache: &mut ParticleMeshes, meshes: &mut Assets<Mesh>) -> Handle<Mesh> {
+		cache.get_or_add(self, meshes, || self.mesh())
+	}
+
+	pub fn mesh(self) -> Mesh {
+		match self {
+			Self::Quad => Rectangle::from_length(1.0).into(),
+			Self::PointSprite => point_sprite(),
+			Self::Sphere { subdivisions } => Sphere::new(0.5)
+				.mesh()
+				.ico(subdivisions.min(79) as usize)
+				.expect("at most 79 subdivisions"),
+			Self::CrossQuads { planes } => cross_quads(planes.max(1) as usize),
+		}
+	}
+}
+
+impl From<ParticleMesh> for Mesh {
+	fn from(mesh: ParticleMesh) -> Self {
+		mesh.mesh()
+	}
+}
+
+fn point_sprite() -> Mesh {
+	// The triangle's incircle is the sprite, so its corners are twice the radius away.
+	let positions =
+		[PI / 2.0, PI * 7.0 / 6.0, PI * 11.0 / 6.0].map(|angle| [angle.cos(), angle.sin(), 0.0]);
+	let uvs = positions.map(|[x, y, _]| [x + 0.5, 0.5 - y]);
+	Mesh::new(
+		PrimitiveTopology::TriangleList,
+		RenderAssetUsages::default(),
+	)
+	.with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions.to_vec())
+	.with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; 3])
+	.with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs.to_vec())
+	.with_inserted_indices(Indices::U32(vec![0, 1, 2]))
+}
+
+fn cross_quads(planes: usize) -> Mesh {
+	let mut positions = Vec::with_capacity(planes * 8);
+	let mut normals = Vec::with_capacity(planes * 8);
+	let mut uvs = Vec::with_capacity(planes * 8);
+	let mut indices = Vec::with_capacity(planes * 12);
+	for plane in 0..planes {
+		let rotation = Quat::from_rotation_y(PI * plane as f32 / planes as f32);
+		let right = rotation * Vec3::X * 0.5;
+		let normal = rotation * Vec3::Z;
+		for side in [1.0, -1.0] {
+			let i = positions.len() as u32;
+			let right = right * side;
+			positions.extend(
+				[-right, right, right + Vec3::Y, -right + Vec3::Y].map(|pos| pos.to_array()),
+			);
+			normals.extend([(normal * side).to_array(); 4]);
+			uvs.extend([[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]]);
+			indices.extend([i, i + 1, i + 2, i, i + 2, i + 3]);
+		}
+	}
+	Mesh::new(
+		PrimitiveTopology::TriangleList,
+		RenderAssetUsages::default(),
+	)
+	.with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+	.with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+	.with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+	.with_inserted_indices(Indices::U32(indices))
+}
@@ -0,0 +1,29 @@
+//! Cheap, seedable noise for animating particles.
+
+/// Hashes `x` with `seed` into a well-mixed `u32`.
+pub fn hash(seed: u32, x: u32) -> u32 {
+	let mut h = seed ^ x.wrapping_mul(0x9E37_79B9);
+	h ^= h >> 16;
+	h = h.wrapping_mul(0x7FEB_352D);
+	h ^= h >> 15;
+	h = h.wrapping_mul(0x846C_A68B);
+	h ^ (h >> 16)
+}
+
+/// Random value in `-1.0..=1.0` for each integer `x`.
+pub fn random_step(seed: u32, x: i32) -> f32 {
+	hash(seed, x as u32) as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+/// Smooth 1D gradient noise, roughly in `-1.0..=1.0`, with features about `1.0` apart.
+/// It is `0.0` at every integer, so noise sampled from `0.0` starts out at rest.
+pub fn gradient_noise(seed: u32, x: f32) -> f32 {
+	let i = x.floor();
+	let f = x - i;
+	let i = i as i32;
+	let g0 = random_step(seed, i);
+	let g1 = random_step(seed, i.wrapping_add(1));
+	let fade = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+	// Peaks at ±0.5 halfway between opposite gradients.
+	2.0 * (g0 * f + (g1 * (f - 1.0) - g0 * f) * fade)
+}
@@ -0,0 +1,124 @@
+//! A debug view of how many particles overlap each pixel, to find the effects costing the
+//! most fill rate.
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{color::MaterialVariants, flicker::FlickerMaterial, lod::LodImposter, TimeCreated};
+
+/// While `enabled`, draws every particle with an additive, unlit heat color instead of
+/// its material, so pixels get brighter the more particle fragments overlap them: red at
+/// about `1 / step` fragments, yellow at about `2.5 / step`, and white at about
+/// `10 / step`, before tonemapping.
+///
+/// Only approximate: each particle keeps its mesh, billboarding, and base color texture,
+/// so transparent parts of a texture count for less, and fragments discarded by a mask
+/// count for more. Particles in a [ParticleBuffer](crate::buffer::ParticleBuffer) keep
+/// their look.
+///
+/// ```ignore
+/// fn toggle_overdraw(keys: Res<ButtonInput<KeyCode>>, mut view: ResMut<OverdrawView>) {
+///     if keys.just_pressed(KeyCode::F3) {
+///         view.enabled = !view.enabled;
+///     }
+/// }
+/// ```
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct OverdrawView {
+	pub enabled: bool,
+	pub step: f32,
+}
+
+impl Default for OverdrawView {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			step: 0.05,
+		}
+	}
+}
+
+/// Heat materials made for [OverdrawView] from the materials of particles, by source
+/// material.
+#[derive(Resource)]
+pub struct OverdrawMaterials<M: Material> {
+	heat: HashMap<AssetId<M>, Handle<M>>,
+	/// The material each heat material was made from.
+	sources: HashMap<AssetId<M>, Handle<M>>,
+	step: f32,
+}
+
+impl<M: Material> Default for OverdrawMaterials<M> {
+	fn default() -> Self {
+		Self {
+			heat: default(),
+			sources: default(),
+			step: 0.0,
+		}
+	}
+}
+
+impl OverdrawView {
+	/// The additive color of one particle fragment.
+	pub fn heat(&self) -> LinearRgba {
+		LinearRgba::rgb(self.step, self.step * 0.4, self.step * 0.1)
+	}
+
+	/// Swaps the materials of particles for heat materials while the view is enabled, and
+	/// back once it is disabled.
+	pub fn apply<M: FlickerMaterial>(
+		view: Res<Self>,
+		mut cache: ResMut<OverdrawMaterials<M>>,
+		mut variants: ResMut<MaterialVariants<M>>,
+		mut materials: ResMut<Assets<M>>,
+		mut q: Query<&mut Handle<M>, Or<(With<TimeCreated>, With<LodImposter>)>>,
+	) {
+		let cache = &mut *cache;
+		if !view.enabled || view.step != cache.step {
+			if cache.sources.is_empty() {
+				cache.step = view.step;
+			} else {
+				for mut handle in &mut q {
+					if let Some(source) = cache.sources.get(&handle.id()) {
+						*handle = source.clone();
+					}
+				}
+				cache.heat.clear();
+				cache.sources.clear();
+				cache.step = view.step;
+			}
+		}
+		if !view.enabled {
+			return;
+		}
+		for mut handle in &mut q {
+			if cache.sources.contains_key(&handle.id()) {
+				continue;
+			}
+			let heat = match cache.heat.get(&handle.id()) {
+				Some(heat) => heat.clone(),
+				None => {
+					// Tried again next frame if the material hasn't loaded yet.
+					let Some(mut material) = materials.get(&*handle).cloned() else {
+						continue;
+					};
+					let standard = material.standard_mut();
+					standard.base_color = view.heat().into();
+					standard.emissive = LinearRgba::BLACK;
+					standard.emissive_texture = None;
+					standard.alpha_mode = AlphaMode::Add;
+					standard.unlit = true;
+					standard.fog_enabled = false;
+					let heat = materials.add(material);
+					// Particles given a new color in the view are given a variant of
+					// their real material, not of the heat one.
+					variants.add_derived(&heat, &handle);
+					cache.heat.insert(handle.id(), heat.clone());
+					cache.sources.insert(heat.id(), handle.clone());
+					heat
+				}
+			};
+			*handle = heat;
+		}
+	}
+}
@@ -0,0 +1,105 @@
+// Prepass vertex shader for `ParticleMaterial`, so depth, normals, and motion vectors match
+// the billboarded geometry drawn in the main pass. The fragment stage is the default one.
+
+#import bevy_pbr::{
+	prepass_io::{Vertex, VertexOutput},
+	mesh_functions,
+	view_transformations::position_world_to_clip,
+}
+#import sond_bevy_particles::billboard::{billboard_position, billboard_rotation, point_sprite_position}
+
+// Matches `ParticleExtension` in material.wgsl.
+struct ParticleExtension {
+	soft_fade_distance: f32,
+	near_fade_distance: f32,
+	point_size: f32,
+	point_attenuation_distance: f32,
+	user_data: vec4<f32>,
+}
+
+@group(2) @binding(100)
+var<uniform> particle_extension: ParticleExtension;
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+	var out: VertexOutput;
+	let world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+
+#ifdef BILLBOARD
+	let rotation = billboard_rotation(world_from_local);
+#endif
+#ifdef BILLBOARD_POINT_SPRITE
+	out.world_position = point_sprite_position(
+		world_from_local,
+		vertex.position,
+		particle_extension.point_size,
+		particle_extension.point_attenuation_distance,
+	);
+#else ifdef BILLBOARD
+	out.world_position = billboard_position(world_from_local, rotation, vertex.position);
+#else
+	out.world_position = mesh_functions::mesh_position_local_to_world(world_from_local, vec4(vertex.position, 1.0));
+#endif
+	out.position = position_world_to_clip(out.world_position.xyz);
+#ifdef DEPTH_CLAMP_ORTHO
+	out.clip_position_unclamped = out.position;
+	out.position.z = min(out.position.z, 1.0);
+#endif
+
+#ifdef VERTEX_UVS_A
+	out.uv = vertex.uv;
+#endif
+#ifdef VERTEX_UVS_B
+	out.uv_b = vertex.uv_b;
+#endif
+
+#ifdef NORMAL_PREPASS_OR_DEFERRED_PREPASS
+#ifdef BILLBOARD
+	out.world_normal = normalize(rotation * vertex.normal);
+#else
+	out.world_normal = mesh_functions::mesh_normal_local_to_world(vertex.normal, vertex.instance_index);
+#endif
+#ifdef VERTEX_TANGENTS
+#ifdef BILLBOARD
+	out.world_tangent = vec4(normalize(rotation * vertex.tangent.xyz), vertex.tangent.w);
+#else
+	out.world_tangent = mesh_functions::mesh_tangent_local_to_world(
+		world_from_local,
+		vertex.tangent,
+		vertex.instance_index
+	);
+#endif
+#endif
+#endif
+
+#ifdef VERTEX_COLORS
+	out.color = vertex.color;
+#endif
+
+#ifdef MOTION_VECTOR_PREPASS
+	// Where the vertex was last frame, from the particle's previous transform. Billboards
+	// keep this frame's orientation, so camera rotation alone doesn't smear them.
+	let previous_world_from_local = mesh_functions::get_previous_world_from_local(vertex.instance_index);
+#ifdef BILLBOARD_POINT_SPRITE
+	out.previous_world_position = point_sprite_position(
+		previous_world_from_local,
+		vertex.position,
+		particle_extension.point_size,
+		particle_extension.point_attenuation_distance,
+	);
+#else ifdef BILLBOARD
+	out.previous_world_position = billboard_position(previous_world_from_local, rotation, vertex.position);
+#else
+	out.previous_world_position = mesh_functions::mesh_position_local_to_world(
+		previous_world_from_local,
+		vec4(vertex.position, 1.0)
+	);
+#endif
+#endif
+
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+	out.instance_index = vertex.instance_index;
+#endif
+
+	return out;
+}
@@ -0,0 +1,194 @@
+use bevy::{
+	ecs::{query::QueryFilter, schedule::ScheduleLabel},
+	prelude::*,
+	transform::TransformSystem,
+	utils::Duration,
+};
+
+use crate::{
+	simulation_systems, time::ParticleUpdate, Deterministic, DeterministicParticleSystems,
+	TimeCreated,
+};
+
+/// Simulates particles marked [ReducedRate] only every `every` frames, e.g. for distant
+/// or background effects, and interpolates their transforms in between.
+///
+/// Interpolated particles are shown one reduced-rate tick behind their simulation.
+#[derive(Resource, Debug, Clone, Copy, Reflect)]
+#[reflect(Resource)]
+pub struct SimulationRate {
+	pub every: u32,
+	frame: u32,
+	accumulated: Duration,
+}
+
+impl SimulationRate {
+	pub fn every(every: u32) -> Self {
+		Self {
+			every,
+			frame: 0,
+			accumulated: Duration::ZERO,
+		}
+	}
+
+	/// How far the next reduced-rate tick is, from `0.0` right after one to almost `1.0`.
+	pub fn fraction(&self) -> f32 {
+		self.frame as f32 / self.every.max(1) as f32
+	}
+}
+
+impl Default for SimulationRate {
+	fn default() -> Self {
+		Self::every(2)
+	}
+}
+
+/// Marks a particle to be simulated at the reduced [SimulationRate]. Usually inserted by
+/// the spewer's factory, for every particle of an effect.
+#[derive(Default, Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct ReducedRate;
+
+/// The simulated transforms of a particle at its last two simulation steps, when it
+/// isn't simulated every frame. Its `Transform` is interpolated between them for rendering.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct SimulatedTransforms {
+	pub previous: Transform,
+	pub current: Transform,
+}
+
+impl SimulatedTransforms {
+	pub fn new(xform: Transform) -> Self {
+		Self {
+			previous: xform,
+			current: xform,
+		}
+	}
+
+	pub fn interpolate(&self, s: f32) -> Transform {
+		Transform {
+			translation: self.previous.translation.lerp(self.current.translation, s),
+			rotation: self.previous.rotation.slerp(self.current.rotation, s),
+			scale: self.previous.scale.lerp(self.current.scale, s),
+		}
+	}
+}
+
+/// Runs the particle simulation for [ReducedRate] particles, with a `Time` whose delta
+/// covers all the frames since the last reduced-rate tick.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReducedRateSimulation;
+
+type ReducedFilter = (With<ReducedRate>, Without<Deterministic>);
+
+pub(crate) struct SimulationRatePlugin;
+
+impl Plugin for SimulationRatePlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<SimulationRate>()
+			.add_systems(
+				ReducedRateSimulation,
+				(
+					restore_simulated::<ReducedFilter>,
+					simulation_systems::<ReducedFilter>(),
+					record_simulated::<ReducedFilter>,
+				)
+					.chain(),
+			)
+			.add_systems(ParticleUpdate, (tick_reduced_rate, interpolate).chain())
+			.register_type::<SimulationRate>()
+			.register_type::<ReducedRate>()
+			.register_type::<SimulatedTransforms>();
+	}
+}
+
+fn tick_reduced_rate(world: &mut World) {
+	let real = *world.resource::<Time>();
+	let mut rate = world.resource_mut::<SimulationRate>();
+	rate.accumulated += real.delta();
+	rate.frame += 1;
+	if rate.frame < rate.every {
+		return;
+	}
+	rate.frame = 0;
+	let delta = std::mem::take(&mut rate.accumulated);
+
+	let mut reduced = Time::<()>::default();
+	reduced.advance_to(real.elapsed().saturating_sub(delta));
+	reduced.advance_to(real.elapsed());
+	world.insert_resource(reduced);
+	world.run_schedule(ReducedRateSimulation);
+	world.insert_resource(real);
+}
+
+/// Puts the particles back where they were simulated to before behaviors run again.
+fn restore_simulated<F: QueryFilter>(mut q: Query<(&mut Transform, &SimulatedTransforms), F>) {
+	q.par_iter_mut().for_each(|(mut xform, sim)| {
+		xform.set_if_neq(sim.current);
+	});
+}
+
+fn record_simulated<F: QueryFilter>(
+	mut cmds: Commands,
+	mut q: Query<(Entity, &Transform, Option<&mut SimulatedTransforms>), F>,
+) {
+	for (id, xform, sim) in &mut q {
+		match sim {
+			Some(mut sim) => {
+				sim.previous = sim.current;
+				sim.current = *xform;
+			}
+			None => {
+				cmds.entity(id).insert(SimulatedTransforms::new(*xform));
+			}
+		}
+	}
+}
+
+fn interpolate(
+	mut q: Query<(&mut Transform, &SimulatedTransforms), ReducedFilter>,
+	rate: Res<SimulationRate>,
+) {
+	let s = rate.fraction();
+	q.par_iter_mut().for_each(|(mut xform, sim)| {
+		xform.set_if_neq(sim.interpolate(s));
+	});
+}
+
+/// Interpolates the `Transform`s of [Deterministic] particles simulated in `FixedUpdate`
+/// by [DeterministicParticlesPlugin](crate::DeterministicParticlesPlugin), so they move
+/// smoothly when the display refreshes faster than the fixed timestep.
+///
+/// Particles are shown up to one fixed step behind their simulation. Spewers aren't
+/// interpolated, since their transforms belong to gameplay.
+pub struct FixedInterpolationPlugin;
+
+type FixedFilter = (With<Deterministic>, With<TimeCreated>);
+
+impl Plugin for FixedInterpolationPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			FixedUpdate,
+			(
+				restore_simulated::<FixedFilter>.before(DeterministicParticleSystems),
+				record_simulated::<FixedFilter>.after(DeterministicParticleSystems),
+			),
+		)
+		.add_systems(
+			PostUpdate,
+			interpolate_fixed.before(TransformSystem::TransformPropagate),
+		)
+		.register_type::<SimulatedTransforms>();
+	}
+}
+
+fn interpolate_fixed(
+	mut q: Query<(&mut Transform, &SimulatedTransforms), FixedFilter>,
+	t: Res<Time<Fixed>>,
+) {
+	let s = t.overstep_fraction();
+	q.par_iter_mut().for_each(|(mut xform, sim)| {
+		xform.set_if_neq(sim.interpolate(s));
+	});
+}
@@ -0,0 +1,276 @@
+//! Seeking authored effects to any point in time by resimulating them from their seed,
+//! e.g. for a timeline scrubber in an editor.
+
+use bevy::{
+	ecs::{schedule::ScheduleLabel, system::EntityCommand},
+	prelude::*,
+	utils::Duration,
+};
+use nanorand::WyRand;
+
+use crate::{
+	behavior_systems, handle_lifetimes, lifecycle::ParticleOf, snapshot::SpewerSnapshot,
+	spawn_particles_ordered, time::ParticleTime, update::Sleeping, Deterministic, Spewer,
+	SpewerState, TimeCreated,
+};
+
+/// Makes the [Spewer] on the same entity seekable with [SeekEffect].
+///
+/// The effect is resimulated from `seed` in fixed steps, so seeking to the same time always
+/// gives the same particles, as long as nothing outside the effect changes between seeks.
+/// The spewer shouldn't move while being sought, since only its current transform is known.
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct Seekable {
+	pub seed: u64,
+	/// Particles burst at the start of the effect.
+	pub burst: u32,
+	/// Fixed timestep the effect is resimulated with.
+	pub step: Duration,
+	/// How often to keep a [SpewerSnapshot] while resimulating, so later seeks resume from
+	/// the latest one before their target instead of from the start.
+	///
+	/// Snapshots only keep part of each particle's state (see
+	/// [ParticleSnapshot](crate::snapshot::ParticleSnapshot)), so only use keyframes for
+	/// effects whose behaviors don't depend on the rest.
+	pub keyframe_interval: Option<Duration>,
+	/// Sorted by effect time.
+	#[reflect(ignore)]
+	keyframes: Vec<(Duration, SpewerSnapshot)>,
+}
+
+impl Seekable {
+	pub fn new(seed: u64) -> Self {
+		Self {
+			seed,
+			burst: 0,
+			step: Duration::from_secs_f64(1.0 / 60.0),
+			keyframe_interval: None,
+			keyframes: Vec::new(),
+		}
+	}
+
+	pub fn with_burst(self, burst: u32) -> Self {
+		Self { burst, ..self }
+	}
+
+	pub fn with_step(self, step: Duration) -> Self {
+		Self { step, ..self }
+	}
+
+	pub fn with_keyframes(self, interval: Duration) -> Self {
+		Self {
+			keyframe_interval: Some(interval),
+			..self
+		}
+	}
+
+	pub fn keyframe_count(&self) -> usize {
+		self.keyframes.len()
+	}
+
+	/// Drops all keyframes, which must be done whenever the effect is changed, e.g. while
+	/// authoring it, or its seed, step, or keyframe interval are.
+	pub fn clear_keyframes(&mut self) {
+		self.keyframes.clear();
+	}
+}
+
+/// Seeks the effect of a [Seekable] spewer to a time since its start:
+///
+/// ```ignore
+/// commands.entity(effect).add(SeekEffect(Duration::from_secs_f32(scrubber.value)));
+/// ```
+///
+/// The effect's particles are replaced by the ones it has at that time, with their ages
+/// carried over to the spewer's clock ([ParticleTime], or `Time` for [Deterministic]
+/// spewers), so the effect carries on from there unless that clock is paused. Like
+/// [SpewerSnapshot::restore], ages can't go back further than the clock's start.
+#[derive(Debug, Clone, Copy)]
+pub struct SeekEffect(pub Duration);
+
+impl EntityCommand for SeekEffect {
+	fn apply(self, id: Entity, world: &mut World) {
+		seek(world, id, self.0);
+	}
+}
+
+/// Runs one step of the effects being sought, with a `Time` on the effect's own clock.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct SeekSimulation;
+
+/// Marks the spewer being sought and its particles.
+#[derive(Component)]
+struct Seeking;
+
+fn seek(world: &mut World, spewer: Entity, target: Duration) {
+	if world.get::<Spewer>(spewer).is_none() {
+		return;
+	}
+	let registered = world
+		.get_resource::<Schedules>()
+		.is_some_and(|schedules| schedules.contains(SeekSimulation));
+	if !registered {
+		warn!(
+			"Can't seek effect {spewer:?} without `ParticlesPlugin` or \
+			 `DeterministicParticlesPlugin`"
+		);
+		return;
+	}
+	let Some(mut seekable) = world.get_mut::<Seekable>(spewer) else {
+		return;
+	};
+	let mut keyframes = std::mem::take(&mut seekable.keyframes);
+	let (seed, burst, step) = (seekable.seed, seekable.burst, seekable.step);
+	let keyframe_interval = seekable
+		.keyframe_interval
+		.filter(|interval| !interval.is_zero());
+	let app_time = world.get_resource::<Time>().copied();
+	let app_now = match world.get_resource::<ParticleTime>() {
+		Some(particle_time) if world.get::<Deterministic>(spewer).is_none() => {
+			particle_time.elapsed()
+		}
+		_ => app_time.map(|t| t.elapsed()).unwrap_or_default(),
+	};
+
+	despawn_particles(world, spewer);
+	let mut clock = Time::<()>::default();
+	match keyframes.iter().rposition(|&(time, _)| time <= target) {
+		Some(i) => {
+			let (time, snapshot) = &keyframes[i];
+			clock.advance_to(*time);
+			world.insert_resource(clock);
+			snapshot.restore(world, spewer);
+			world.entity_mut(spewer).insert(Seeking);
+		}
+		None => {
+			world.insert_resource(clock);
+			if let Some(mut state) = world.get_mut::<Spewer>(spewer) {
+				state.set_state(SpewerState {
+					last_spawn: Duration::ZERO,
+					rng: WyRand::new_seed(seed),
+					pending_burst: burst,
+					motion: default(),
+					next_interval: None,
+				});
+			}
+			world.entity_mut(spewer).insert(Seeking);
+			// Spawns the burst, and starts the spewer at `0` however it has been sought before.
+			world.run_schedule(SeekSimulation);
+		}
+	}
+
+	while clock.elapsed() < target {
+		let before = clock.elapsed();
+		let delta = if step.is_zero() {
+			target - before
+		} else {
+			step.min(target - before)
+		};
+		clock.advance_by(delta);
+		world.insert_resource(clock);
+		world.run_schedule(SeekSimulation);
+
+		// The last step can be shorter, which would put keyframes after it off the grid of
+		// steps that resuming from them continues on.
+		let Some(interval) = keyframe_interval.filter(|_| delta == step) else {
+			continue;
+		};
+		let now = clock.elapsed();
+		if before.as_nanos() / interval.as_nanos() == now.as_nanos() / interval.as_nanos() {
+			continue;
+		}
+		// Capturing reseeds the spewer, so it is captured every time it passes a keyframe to
+		// keep resimulations identical, whether or not the keyframe is kept already.
+		let Some(snapshot) = SpewerSnapshot::capture(world, spewer) else {
+			continue;
+		};
+		if let Err(i) = keyframes.binary_search_by_key(&now, |&(time, _)| time) {
+			keyframes.insert(i, (now, snapshot));
+		}
+	}
+
+	rebase(world, spewer, target, app_now);
+	match app_time {
+		Some(app_time) => world.insert_resource(app_time),
+		None => {
+			world.remove_resource::<Time>();
+		}
+	}
+	if let Some(mut seekable) = world.get_mut::<Seekable>(spewer) {
+		seekable.keyframes = keyframes;
+	}
+}
+
+fn particles_of(world: &mut World, spewer: Entity) -> Vec<Entity> {
+	let mut q = world.query::<(Entity, &ParticleOf)>();
+	let mut particles = q
+		.iter(world)
+		.filter(|(_, of)| of.0 == spewer)
+		.map(|(id, _)| id)
+		.collect::<Vec<_>>();
+	if let Some(children) = world.get::<Children>(spewer) {
+		particles.extend(
+			children
+				.iter()
+				.filter(|&&child| world.get::<TimeCreated>(child).is_some())
+				.filter(|&&child| world.get::<ParticleOf>(child).is_none()),
+		);
+	}
+	particles
+}
+
+fn despawn_particles(world: &mut World, spewer: Entity) {
+	for id in particles_of(world, spewer) {
+		world.entity_mut(id).despawn_recursive();
+	}
+}
+
+/// Moves the effect from its own clock, at `target`, onto the app's clock, at `now`.
+fn rebase(world: &mut World, spewer: Entity, target: Duration, now: Duration) {
+	let rebase = |time: Duration| (time + now).saturating_sub(target);
+	for id in particles_of(world, spewer) {
+		let mut particle = world.entity_mut(id);
+		particle.remove::<Seeking>();
+		if let Some(mut created) = particle.get_mut::<TimeCreated>() {
+			created.0 = rebase(created.0);
+		}
+	}
+	let mut spewer = world.entity_mut(spewer);
+	spewer.remove::<Seeking>();
+	if let Some(mut state) = spewer.get_mut::<Spewer>() {
+		state.last_spawn = rebase(state.last_spawn);
+	}
+}
+
+fn mark_seeking(
+	mut cmds: Commands,
+	q: Query<(Entity, &ParticleOf), Without<Seeking>>,
+	spewers: Query<(), With<Seeking>>,
+) {
+	for (id, of) in &q {
+		if spewers.contains(of.0) {
+			cmds.entity(id).insert(Seeking);
+		}
+	}
+}
+
+/// Added by both [ParticlesPlugin](crate::ParticlesPlugin) and
+/// [DeterministicParticlesPlugin](crate::DeterministicParticlesPlugin), whichever comes first.
+pub(crate) struct SeekPlugin;
+
+impl Plugin for SeekPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			SeekSimulation,
+			(
+				mark_seeking,
+				spawn_particles_ordered::<With<Seeking>>,
+				mark_seeking,
+				behavior_systems::<(With<Seeking>, Without<Sleeping>)>().chain(),
+				handle_lifetimes::<With<Seeking>>,
+			)
+				.chain(),
+		);
+	}
+}
@@ -0,0 +1,90 @@
+//! Particles that look infinitely far away, like a skybox, e.g. for starfields and space
+//! dust.
+
+use bevy::prelude::*;
+
+use crate::update::{main_camera, parent_global_transform, translate_now, Cameras, NotCamera};
+
+/// Moves the particle along with the main camera, so that it seems further away than it
+/// is. Stars spawned around the camera with a parallax of `0.0` stay put on screen as the
+/// camera moves, like a skybox, and layers of dust or nebulae with larger ones drift past
+/// it at different speeds. They should be spawned within the camera's far plane.
+///
+/// The particle is moved after transform propagation, so it never lags behind the camera,
+/// and its behaviors keep working on top of the movement.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct SkyParallax {
+	/// How much the particle moves relative to the camera as the camera moves: `0.0` as if
+	/// it were infinitely far away, and `1.0` as if it were as near as it is.
+	pub parallax: f32,
+	/// Size of a box centered on the camera to wrap the particle around in, so that a layer
+	/// of dust keeps surrounding the camera as it moves through it. Wrapping particles jump
+	/// to the other side, so shouldn't have trails.
+	pub wrap: Option<Vec3>,
+	/// The camera's translation on the last update.
+	pub last_camera: Option<Vec3>,
+}
+
+impl Default for SkyParallax {
+	fn default() -> Self {
+		Self::infinite()
+	}
+}
+
+impl SkyParallax {
+	/// Follows the camera completely.
+	pub fn infinite() -> Self {
+		Self::layer(0.0)
+	}
+
+	pub fn layer(parallax: f32) -> Self {
+		Self {
+			parallax,
+			wrap: None,
+			last_camera: None,
+		}
+	}
+
+	pub fn with_wrap(self, size: Vec3) -> Self {
+		Self {
+			wrap: Some(size),
+			..self
+		}
+	}
+
+	pub fn tick(
+		mut q: Query<
+			(
+				&mut Self,
+				&mut Transform,
+				&mut GlobalTransform,
+				Option<&Parent>,
+			),
+			NotCamera,
+		>,
+		parents: Query<&GlobalTransform, Without<Self>>,
+		cameras: Cameras,
+	) {
+		let Some(camera) = main_camera(&cameras).map(|(.., xform)| xform.translation()) else {
+			return;
+		};
+		q.par_iter_mut()
+			.for_each(|(mut item, mut xform, mut global_xform, parent)| {
+				let last = item.last_camera.replace(camera);
+				let mut delta =
+					last.map_or(Vec3::ZERO, |last| (camera - last) * (1.0 - item.parallax));
+				if let Some(size) = item.wrap {
+					let size = size.max(Vec3::splat(f32::EPSILON));
+					let relative = global_xform.translation() + delta - camera;
+					let wrapped = (relative + size * 0.5).rem_euclid(size) - size * 0.5;
+					delta += wrapped - relative;
+				}
+				if delta == Vec3::ZERO {
+					return;
+				}
+				let parent = parent_global_transform(parent, &parents);
+				translate_now(&mut xform, &mut global_xform, &parent, delta);
+			});
+	}
+}
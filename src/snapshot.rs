@@ -0,0 +1,161 @@
+use bevy::{ecs::world::CommandQueue, prelude::*, utils::Duration};
+use nanorand::{Rng, SeedableRng};
+use std::ops::RangeInclusive;
+
+use crate::{
+	lifecycle::ParticleOf, update::Velocity, CatchUp, InitialTransform, Lifetime, SpawnTiming,
+	Spewer, SpewerMotion, TimeCreated,
+};
+
+/// Runtime state of a [Spewer] and its live particles, for save games or rollback. Times
+/// are stored relative to the moment of capture.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpewerSnapshot {
+	pub interval: Duration,
+	pub jitter: Duration,
+	pub timing: SpawnTiming,
+	pub particles_per_spawn: RangeInclusive<u32>,
+	pub catch_up: CatchUp,
+	pub use_global_coords: bool,
+	pub since_last_spawn: Duration,
+	pub rng_seed: u64,
+	pub pending_burst: u32,
+	pub motion: SpewerMotion,
+	pub next_interval: Option<Duration>,
+	pub particles: Vec<ParticleSnapshot>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParticleSnapshot {
+	pub age: Duration,
+	pub lifetime: Duration,
+	/// In world space for spewers with `use_global_coords`, and in the spewer's space
+	/// otherwise.
+	pub transform: Transform,
+	pub initial_transform: Transform,
+	pub velocity: Option<Vec3>,
+}
+
+fn now(world: &World) -> Duration {
+	world
+		.get_resource::<Time>()
+		.map(Time::elapsed)
+		.unwrap_or_default()
+}
+
+/// The particles `spewer` spawned, whether they are its children or in world space.
+fn particles_of(world: &mut World, spewer: Entity) -> Vec<Entity> {
+	let mut q = world.query::<(Entity, &ParticleOf)>();
+	q.iter(world)
+		.filter(|(_, of)| of.0 == spewer)
+		.map(|(id, _)| id)
+		.collect()
+}
+
+impl SpewerSnapshot {
+	/// Captures the state of `spewer`. Returns `None` if it has no [Spewer].
+	///
+	/// The spewer's RNG state can't be read directly, so it is reseeded with a value drawn
+	/// from itself, which is stored in the snapshot. The live spewer and one restored from
+	/// the snapshot produce the same random sequence afterwards.
+	pub fn capture(world: &mut World, spewer: Entity) -> Option<Self> {
+		let now = now(world);
+		let particles = particles_of(world, spewer);
+		let mut q = world.query::<(
+			&Transform,
+			&InitialTransform,
+			&TimeCreated,
+			&Lifetime,
+			Option<&Velocity>,
+		)>();
+		let particles = particles
+			.into_iter()
+			.filter_map(|particle| q.get(world, particle).ok())
+			.map(
+				|(xform, init_xform, created, lifetime, vel)| ParticleSnapshot {
+					age: now.saturating_sub(**created),
+					lifetime: **lifetime,
+					transform: *xform,
+					initial_transform: **init_xform,
+					velocity: vel.map(|vel| **vel),
+				},
+			)
+			.collect();
+
+		let mut spewer = world.get_mut::<Spewer>(spewer)?;
+		let rng_seed = spewer.rng.generate::<u64>();
+		spewer.rng.reseed(rng_seed.to_ne_bytes());
+		Some(Self {
+			interval: spewer.interval,
+			jitter: spewer.jitter,
+			timing: spewer.timing,
+			particles_per_spawn: spewer.particles_per_spawn.clone(),
+			catch_up: spewer.catch_up,
+			use_global_coords: spewer.use_global_coords,
+			since_last_spawn: now.saturating_sub(spewer.last_spawn),
+			rng_seed,
+			pending_burst: spewer.pending_burst,
+			motion: spewer.motion,
+			next_interval: spewer.next_interval,
+			particles,
+		})
+	}
+
+	/// Restores this state onto `spewer`, which must already have a [Spewer] with the
+	/// desired factory. Its current particles are despawned and the snapshot's particles
+	/// are respawned through the factory before their state is overwritten, then given
+	/// everything the spewer passes on to the particles it emits, as children of the spewer
+	/// unless it has `use_global_coords`.
+	pub fn restore(&self, world: &mut World, spewer: Entity) {
+		let now = now(world);
+		for particle in particles_of(world, spewer) {
+			world.entity_mut(particle).despawn_recursive();
+		}
+
+		let Some(mut state) = world.get_mut::<Spewer>(spewer) else {
+			return;
+		};
+		state.interval = self.interval;
+		state.jitter = self.jitter;
+		state.timing = self.timing;
+		state.particles_per_spawn = self.particles_per_spawn.clone();
+		state.catch_up = self.catch_up;
+		state.next_interval = self.next_interval;
+		state.motion = self.motion;
+		state.use_global_coords = self.use_global_coords;
+		state.last_spawn = now.saturating_sub(self.since_last_spawn);
+		state.rng = nanorand::WyRand::new_seed(self.rng_seed);
+		state.pending_burst = self.pending_burst;
+		let mut factory = std::mem::replace(&mut state.factory, Box::new(crate::default_factory));
+
+		let mut queue = CommandQueue::default();
+		let mut cmds = Commands::new(&mut queue, world);
+		if let Some(emitter) = crate::Emitter::of(world, spewer) {
+			for particle in &self.particles {
+				let created = TimeCreated(now.saturating_sub(particle.age));
+				let mut entity = factory(
+					&mut cmds,
+					&GlobalTransform::from(particle.transform),
+					created,
+				);
+				entity.insert((
+					particle.transform,
+					InitialTransform(particle.initial_transform),
+					created,
+					Lifetime(particle.lifetime),
+				));
+				if let Some(vel) = particle.velocity {
+					entity.insert(Velocity(vel));
+				}
+				emitter.adopt(&mut entity, Some(particle.initial_transform));
+			}
+		}
+		queue.apply(world);
+
+		if let Some(mut state) = world.get_mut::<Spewer>(spewer) {
+			state.factory = factory;
+		}
+	}
+}
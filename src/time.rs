@@ -0,0 +1,165 @@
+//! A clock for all cosmetic particle simulation, which can be paused, stepped, and scaled
+//! independently of the rest of the app.
+
+use bevy::{
+	ecs::schedule::ScheduleLabel, prelude::*, time::TimeSystem, transform::TransformSystem,
+	utils::Duration,
+};
+
+/// The clock of every spewer and particle not marked [Deterministic](crate::Deterministic),
+/// e.g. for stepping through an effect frame by frame while debugging it:
+///
+/// ```ignore
+/// fn debug_controls(keys: Res<ButtonInput<KeyCode>>, mut time: ResMut<ParticleTime>) {
+///     if keys.just_pressed(KeyCode::KeyP) {
+///         time.toggle_pause();
+///     }
+///     if keys.just_pressed(KeyCode::Period) {
+///         time.step();
+///     }
+/// }
+/// ```
+///
+/// `Time` is replaced by this clock while [ParticlePreUpdate], [ParticleUpdate], and
+/// [ParticlePostUpdate] run, so the [TimeCreated](crate::TimeCreated) of cosmetic particles
+/// is on this clock as well. Code spawning them elsewhere should use [ParticleTime::elapsed].
+/// Deterministic particles keep the time of the schedule simulating them.
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct ParticleTime {
+	pub paused: bool,
+	/// Speed of the clock relative to its `source`.
+	pub scale: f32,
+	pub source: ParticleClockSource,
+	/// How far each [ParticleTime::step] advances the clock while paused.
+	pub step_size: Duration,
+	/// Steps queued while paused, taken one per frame.
+	steps: u32,
+	time: Time,
+}
+
+impl Default for ParticleTime {
+	fn default() -> Self {
+		Self {
+			paused: false,
+			scale: 1.0,
+			source: ParticleClockSource::Virtual,
+			step_size: Duration::from_secs_f64(1.0 / 60.0),
+			steps: 0,
+			time: Time::default(),
+		}
+	}
+}
+
+impl ParticleTime {
+	pub fn pause(&mut self) {
+		self.paused = true;
+	}
+
+	pub fn unpause(&mut self) {
+		self.paused = false;
+	}
+
+	pub fn toggle_pause(&mut self) {
+		self.paused = !self.paused;
+	}
+
+	/// Advances the paused clock by one `step_size` on the next frame. Does nothing unless
+	/// paused.
+	pub fn step(&mut self) {
+		if self.paused {
+			self.steps += 1;
+		}
+	}
+
+	pub fn with_scale(self, scale: f32) -> Self {
+		Self { scale, ..self }
+	}
+
+	pub fn with_source(self, source: ParticleClockSource) -> Self {
+		Self { source, ..self }
+	}
+
+	pub fn elapsed(&self) -> Duration {
+		self.time.elapsed()
+	}
+
+	pub fn delta(&self) -> Duration {
+		self.time.delta()
+	}
+
+	/// The clock as a `Time`, as particle systems see it.
+	pub fn time(&self) -> &Time {
+		&self.time
+	}
+
+	fn advance(mut particle_time: ResMut<Self>, virt: Res<Time<Virtual>>, real: Res<Time<Real>>) {
+		let particle_time = &mut *particle_time;
+		let delta = if particle_time.paused {
+			match particle_time.steps.checked_sub(1) {
+				Some(steps) => {
+					particle_time.steps = steps;
+					particle_time.step_size
+				}
+				None => Duration::ZERO,
+			}
+		} else {
+			particle_time.steps = 0;
+			let source = match particle_time.source {
+				ParticleClockSource::Virtual => virt.delta(),
+				ParticleClockSource::Real => real.delta(),
+			};
+			source.mul_f32(particle_time.scale.max(0.0))
+		};
+		particle_time.time.advance_by(delta);
+	}
+}
+
+/// What a [ParticleTime] follows while it isn't paused.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum ParticleClockSource {
+	/// The app's virtual time, so particles also pause and speed up along with it.
+	#[default]
+	Virtual,
+	/// Real time, so particles keep moving while virtual time is paused or scaled.
+	Real,
+}
+
+/// Runs in `PreUpdate` with [ParticleTime] as `Time`. Spewers emit cosmetic particles here.
+#[derive(ScheduleLabel, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParticlePreUpdate;
+
+/// Runs in `Update` with [ParticleTime] as `Time`. Cosmetic particle behaviors and
+/// lifetimes are simulated here.
+#[derive(ScheduleLabel, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParticleUpdate;
+
+/// Runs in `PostUpdate` after transform propagation, with [ParticleTime] as `Time`.
+/// Trails and other effects following propagated transforms are updated here.
+#[derive(ScheduleLabel, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParticlePostUpdate;
+
+fn run_in_particle_time<L: ScheduleLabel + Default>(world: &mut World) {
+	let app_time = *world.resource::<Time>();
+	let particle_time = *world.resource::<ParticleTime>().time();
+	world.insert_resource(particle_time);
+	world.try_run_schedule(L::default()).ok();
+	world.insert_resource(app_time);
+}
+
+pub(crate) struct ParticleTimePlugin;
+
+impl Plugin for ParticleTimePlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<ParticleTime>()
+			.add_systems(First, ParticleTime::advance.after(TimeSystem))
+			.add_systems(PreUpdate, run_in_particle_time::<ParticlePreUpdate>)
+			.add_systems(Update, run_in_particle_time::<ParticleUpdate>)
+			.add_systems(
+				PostUpdate,
+				run_in_particle_time::<ParticlePostUpdate>
+					.after(TransformSystem::TransformPropagate),
+			)
+			.register_type::<ParticleTime>();
+	}
+}
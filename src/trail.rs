@@ -0,0 +1,465 @@
+//! Ribbons left behind by moving entities, e.g. particles, projectiles, or spewers.
+//!
+//! A [Trail] lives on its own world-space entity, records the positions of its target, and
+//! rebuilds its camera-facing ribbon mesh every frame.
+
+use bevy::{
+	prelude::*,
+	render::{
+		mesh::{Indices, PrimitiveTopology},
+		render_asset::RenderAssetUsages,
+		view::NoFrustumCulling,
+	},
+	utils::Duration,
+};
+
+use crate::{
+	math::lifetime_progress,
+	update::{main_camera, Cameras},
+};
+
+/// A recorded position of a [Trail]'s target.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct TrailPoint {
+	pub position: Vec3,
+	/// Elapsed time when the point was recorded.
+	pub time: Duration,
+	/// Speed of the target when the point was recorded, in units per second.
+	pub speed: f32,
+	/// Distance the target had travelled along the trail when the point was recorded.
+	pub distance: f32,
+}
+
+/// How the V texture coordinate runs along a [Trail]. U goes from `0.0` to `1.0` across it.
+///
+/// The tiling modes need a texture whose sampler repeats (`ImageAddressMode::Repeat`).
+#[derive(Default, Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum TrailUvMode {
+	/// Stretch one copy of the texture from the oldest point (`0.0`) to the newest (`1.0`).
+	#[default]
+	Stretch,
+	/// Repeat the texture every `length` units travelled. The texture stays in place
+	/// in the world as the trail grows, e.g. for tire tracks.
+	TilePerDistance { length: f32 },
+	/// Repeat the texture once per segment between recorded points.
+	TilePerSegment,
+}
+
+/// Records the world-space positions of `target` and draws a ribbon through them.
+///
+/// Width and color can follow the speed the target had at each point, so fast sweeps
+/// leave brighter or wider streaks than slow ones. Points fade out over `lifetime`.
+/// Once the target is despawned, the trail despawns itself after its last point expires.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component, from_reflect = false)]
+pub struct Trail {
+	pub target: Entity,
+	/// How long each point lasts.
+	pub lifetime: Duration,
+	/// Distance the target must move before a new point is recorded.
+	pub min_distance: f32,
+	/// Width of the ribbon in world units, before `width_by_speed`.
+	pub width: f32,
+	/// Factor of `width` for a point's recorded speed.
+	#[reflect(ignore)]
+	pub width_by_speed: fn(f32) -> f32,
+	/// Vertex color for a point's recorded speed, multiplied with the material's color.
+	#[reflect(ignore)]
+	pub color_by_speed: fn(f32) -> LinearRgba,
+	pub uv_mode: TrailUvMode,
+	/// Recorded points, oldest first.
+	pub points: Vec<TrailPoint>,
+}
+
+impl Trail {
+	pub fn new(target: Entity, lifetime: Duration, width: f32) -> Self {
+		Self {
+			target,
+			lifetime,
+			min_distance: 0.05,
+			width,
+			width_by_speed: |_| 1.0,
+			color_by_speed: |_| LinearRgba::WHITE,
+			uv_mode: TrailUvMode::Stretch,
+			points: Vec::new(),
+		}
+	}
+
+	pub fn with_width_by_speed(self, width_by_speed: fn(f32) -> f32) -> Self {
+		Self {
+			width_by_speed,
+			..self
+		}
+	}
+
+	pub fn with_color_by_speed(self, color_by_speed: fn(f32) -> LinearRgba) -> Self {
+		Self {
+			color_by_speed,
+			..self
+		}
+	}
+
+	pub fn with_uv_mode(self, uv_mode: TrailUvMode) -> Self {
+		Self { uv_mode, ..self }
+	}
+
+	pub fn record(
+		mut cmds: Commands,
+		mut q: Query<(Entity, &mut Self)>,
+		targets: Query<&GlobalTransform>,
+		t: Res<Time>,
+	) {
+		let now = t.elapsed();
+		for (id, mut trail) in &mut q {
+			let lifetime = trail.lifetime;
+			let expired = trail
+				.points
+				.partition_point(|point| now.saturating_sub(point.time) >= lifetime);
+			if expired > 0 {
+				trail.points.drain(..expired);
+			}
+
+			let Ok(target) = targets.get(trail.target) else {
+				if trail.points.is_empty() {
+					cmds.entity(id).despawn_recursive();
+				}
+				continue;
+			};
+			let position = target.translation();
+			let (speed, distance) = match trail.points.last() {
+				Some(last) => {
+					let step = last.position.distance(position);
+					if step < trail.min_distance {
+						continue;
+					}
+					let secs = now.saturating_sub(last.time).as_secs_f32();
+					let speed = if secs > 0.0 { step / secs } else { last.speed };
+					(speed, last.distance + step)
+				}
+				None => (0.0, 0.0),
+			};
+			trail.points.push(TrailPoint {
+				position,
+				time: now,
+				speed,
+				distance,
+			});
+		}
+	}
+
+	/// Builds the ribbon from the recorded points, facing `view_position`.
+	pub fn build_mesh(&self, now: Duration, view_position: Vec3) -> Mesh {
+		let n = self.points.len();
+		let lifetime = self.lifetime.as_secs_f32();
+		let points = self
+			.points
+			.iter()
+			.enumerate()
+			.map(|(i, point)| {
+				let age = lifetime_progress(now.saturating_sub(point.time).as_secs_f32(), lifetime);
+				let color = (self.color_by_speed)(point.speed);
+				let v = match self.uv_mode {
+					TrailUvMode::Stretch if n > 1 => i as f32 / (n - 1) as f32,
+					TrailUvMode::Stretch => 0.0,
+					TrailUvMode::TilePerDistance { length } => point.distance / length,
+					// Repeating whole tiles, so the texture doesn't shift as old points expire.
+					TrailUvMode::TilePerSegment => i as f32,
+				};
+				RibbonPoint {
+					position: point.position,
+					half_width: 0.5 * self.width * (self.width_by_speed)(point.speed),
+					color: color.with_alpha(color.alpha * (1.0 - age)),
+					v,
+				}
+			})
+			.collect::<Vec<_>>();
+		let mut ribbon = RibbonBuilder::default();
+		ribbon.add_strip(&points, view_position);
+		ribbon.finish()
+	}
+
+	pub fn update_meshes(
+		mut cmds: Commands,
+		q: Query<(Entity, &Self, Option<&Handle<Mesh>>)>,
+		mut meshes: ResMut<Assets<Mesh>>,
+		cameras: Cameras,
+		t: Res<Time>,
+	) {
+		let Some((.., camera)) = main_camera(&cameras) else {
+			return;
+		};
+		let now = t.elapsed();
+		for (id, trail, handle) in &q {
+			let mesh = trail.build_mesh(now, camera.translation());
+			replace_mesh(&mut cmds, id, handle, &mut meshes, mesh);
+		}
+	}
+}
+
+/// One recorded pair of points of a [SweepTrail], in world space.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct SweepSample {
+	pub base: Vec3,
+	pub tip: Vec3,
+	/// Elapsed time when the sample was recorded.
+	pub time: Duration,
+}
+
+/// Records two points on `target` every frame, e.g. the hilt and tip of a sword, and
+/// draws the surface they swept through, fading out over `lifetime`.
+///
+/// The surface isn't camera-facing, so its material should usually disable backface
+/// culling (`cull_mode: None`). U runs from `0.0` at the base to `1.0` at the tip, and
+/// V from `0.0` at the newest sample to `1.0` at the oldest.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component, from_reflect = false)]
+pub struct SweepTrail {
+	pub target: Entity,
+	/// First tracked point, in the target's local space.
+	pub base: Vec3,
+	/// Second tracked point, in the target's local space.
+	pub tip: Vec3,
+	/// How long each sample lasts.
+	pub lifetime: Duration,
+	/// Vertex color over the age of a sample, from `0.0` to `1.0`, multiplied with the
+	/// material's color and faded out with age.
+	#[reflect(ignore)]
+	pub color_over_age: fn(f32) -> LinearRgba,
+	/// Only record while the tip moves faster than this, in units per second, so the
+	/// trail only shows up during swings.
+	pub min_speed: f32,
+	/// Recorded samples, oldest first.
+	pub samples: Vec<SweepSample>,
+}
+
+impl SweepTrail {
+	pub fn new(target: Entity, base: Vec3, tip: Vec3, lifetime: Duration) -> Self {
+		Self {
+			target,
+			base,
+			tip,
+			lifetime,
+			color_over_age: |_| LinearRgba::WHITE,
+			min_speed: 0.0,
+			samples: Vec::new(),
+		}
+	}
+
+	pub fn record(
+		mut cmds: Commands,
+		mut q: Query<(Entity, &mut Self)>,
+		targets: Query<&GlobalTransform>,
+		t: Res<Time>,
+	) {
+		let now = t.elapsed();
+		for (id, mut sweep) in &mut q {
+			let lifetime = sweep.lifetime;
+			let expired = sweep
+				.samples
+				.partition_point(|sample| now.saturating_sub(sample.time) >= lifetime);
+			if expired > 0 {
+				sweep.samples.drain(..expired);
+			}
+
+			let Ok(target) = targets.get(sweep.target) else {
+				if sweep.samples.is_empty() {
+					cmds.entity(id).despawn_recursive();
+				}
+				continue;
+			};
+			let sample = SweepSample {
+				base: target.transform_point(sweep.base),
+				tip: target.transform_point(sweep.tip),
+				time: now,
+			};
+			if let Some(last) = sweep.samples.last() {
+				let secs = now.saturating_sub(last.time).as_secs_f32();
+				if secs <= 0.0 || last.tip.distance(sample.tip) < sweep.min_speed * secs {
+					continue;
+				}
+			}
+			sweep.samples.push(sample);
+		}
+	}
+
+	pub fn build_mesh(&self, now: Duration) -> Mesh {
+		let lifetime = self.lifetime.as_secs_f32();
+		let n = self.samples.len();
+		let edges = self.samples.iter().enumerate().map(|(i, sample)| {
+			let age = lifetime_progress(now.saturating_sub(sample.time).as_secs_f32(), lifetime);
+			let color = (self.color_over_age)(age);
+			let next = self.samples[(i + 1).min(n - 1)].base;
+			let prev = self.samples[i.saturating_sub(1)].base;
+			let normal = (sample.tip - sample.base)
+				.cross(next - prev)
+				.normalize_or(Vec3::Y);
+			RibbonEdge {
+				a: sample.base,
+				b: sample.tip,
+				normal,
+				color: color.with_alpha(color.alpha * (1.0 - age)),
+				v: age,
+			}
+		});
+		let mut ribbon = RibbonBuilder::default();
+		ribbon.add_edges(edges);
+		ribbon.finish()
+	}
+
+	pub fn update_meshes(
+		mut cmds: Commands,
+		q: Query<(Entity, &Self, Option<&Handle<Mesh>>)>,
+		mut meshes: ResMut<Assets<Mesh>>,
+		t: Res<Time>,
+	) {
+		let now = t.elapsed();
+		for (id, sweep, handle) in &q {
+			replace_mesh(&mut cmds, id, handle, &mut meshes, sweep.build_mesh(now));
+		}
+	}
+}
+
+/// Gives the entity `mesh`, reusing its mesh asset if it already has one.
+pub(crate) fn replace_mesh(
+	cmds: &mut Commands,
+	id: Entity,
+	handle: Option<&Handle<Mesh>>,
+	meshes: &mut Assets<Mesh>,
+	mesh: Mesh,
+) {
+	match handle.and_then(|handle| meshes.get_mut(handle)) {
+		Some(existing) => *existing = mesh,
+		None => {
+			cmds.entity(id).insert(meshes.add(mesh));
+		}
+	}
+}
+
+/// One point along the middle of a camera-facing ribbon.
+pub(crate) struct RibbonPoint {
+	pub position: Vec3,
+	pub half_width: f32,
+	pub color: LinearRgba,
+	/// V texture coordinate.
+	pub v: f32,
+}
+
+/// One pair of vertices across a triangle strip.
+pub(crate) struct RibbonEdge {
+	pub a: Vec3,
+	pub b: Vec3,
+	pub normal: Vec3,
+	pub color: LinearRgba,
+	/// V texture coordinate. U is `0.0` at `a` and `1.0` at `b`.
+	pub v: f32,
+}
+
+/// Collects triangle strips into one mesh.
+#[derive(Default)]
+pub(crate) struct RibbonBuilder {
+	positions: Vec<Vec3>,
+	normals: Vec<Vec3>,
+	uvs: Vec<[f32; 2]>,
+	colors: Vec<[f32; 4]>,
+	indices: Vec<u32>,
+}
+
+impl RibbonBuilder {
+	/// Adds a strip through `points`, facing `view_position`.
+	pub fn add_strip(&mut self, points: &[RibbonPoint], view_position: Vec3) {
+		let n = points.len();
+		for (i, point) in points.iter().enumerate() {
+			let prev = points[i.saturating_sub(1)].position;
+			let next = points[(i + 1).min(n - 1)].position;
+			let tangent = (next - prev).normalize_or_zero();
+			let to_view = (view_position - point.position).normalize_or_zero();
+			let side = tangent.cross(to_view).normalize_or_zero() * point.half_width;
+			self.add_edge(
+				RibbonEdge {
+					a: point.position - side,
+					b: point.position + side,
+					normal: to_view,
+					color: point.color,
+					v: point.v,
+				},
+				i + 1 < n,
+			);
+		}
+	}
+
+	/// Adds a strip through `edges`. It faces the side `(b - a).cross(next.a - a)` points
+	/// to, where `next` is the following edge.
+	pub fn add_edges(&mut self, edges: impl ExactSizeIterator<Item = RibbonEdge>) {
+		let n = edges.len();
+		for (i, edge) in edges.enumerate() {
+			self.add_edge(edge, i + 1 < n);
+		}
+	}
+
+	fn add_edge(&mut self, edge: RibbonEdge, connect_next: bool) {
+		let i = self.positions.len() as u32;
+		self.positions.push(edge.a);
+		self.positions.push(edge.b);
+		self.normals.extend([edge.normal; 2]);
+		self.uvs.push([0.0, edge.v]);
+		self.uvs.push([1.0, edge.v]);
+		self.colors.extend([edge.color.to_f32_array(); 2]);
+		if connect_next {
+			self.indices.extend([i, i + 1, i + 2, i + 1, i + 3, i + 2]);
+		}
+	}
+
+	pub fn finish(self) -> Mesh {
+		Mesh::new(
+			PrimitiveTopology::TriangleList,
+			RenderAssetUsages::RENDER_WORLD,
+		)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+		.with_inserted_indices(Indices::U32(self.indices))
+	}
+}
+
+/// A [Trail] drawn with `material`. Its mesh is created by the trail.
+#[derive(Bundle)]
+pub struct TrailBundle<M: Material = StandardMaterial> {
+	pub trail: Trail,
+	pub material: Handle<M>,
+	pub spatial: SpatialBundle,
+	/// The ribbon's bounds change every frame, so it must not be culled based on them.
+	pub no_frustum_culling: NoFrustumCulling,
+}
+
+impl<M: Material> TrailBundle<M> {
+	pub fn new(trail: Trail, material: Handle<M>) -> Self {
+		Self {
+			trail,
+			material,
+			spatial: default(),
+			no_frustum_culling: NoFrustumCulling,
+		}
+	}
+}
+
+/// A [SweepTrail] drawn with `material`. Its mesh is created by the trail.
+#[derive(Bundle)]
+pub struct SweepTrailBundle<M: Material = StandardMaterial> {
+	pub sweep: SweepTrail,
+	pub material: Handle<M>,
+	pub spatial: SpatialBundle,
+	/// The surface's bounds change every frame, so it must not be culled based on them.
+	pub no_frustum_culling: NoFrustumCulling,
+}
+
+impl<M: Material> SweepTrailBundle<M> {
+	pub fn new(sweep: SweepTrail, material: Handle<M>) -> Self {
+		Self {
+			sweep,
+			material,
+			spatial: default(),
+			no_frustum_culling: NoFrustumCulling,
+		}
+	}
+}
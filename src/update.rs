@@ -1,115 +1,342 @@
-use bevy::prelude::*;
+use bevy::{ecs::query::QueryFilter, prelude::*};
 
 use super::*;
+use crate::{
+	dilation::ParticleTimeScale,
+	math::{decay, lifetime_progress, Easing},
+};
 
-#[derive(Debug, Component, Reflect)]
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
 pub struct Linear {
 	pub velocity: Vec3,
 }
 impl Linear {
-	pub fn tick(
-		mut q: Query<(&Self, &mut Transform, &InitialTransform, &TimeCreated)>,
-		t: Res<Time<Real>>,
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<(&Self, &mut Transform, &InitialTransform, &TimeCreated), F>,
+		t: Res<Time>,
 	) {
 		q.par_iter_mut()
-			.for_each(|(item, mut xform, init_xform, t_created)| {
-				xform.translation = init_xform.translation
-					+ (item.velocity
-						* t.last_update()
-							.unwrap()
-							.duration_since(t_created.0)
-							.as_secs_f32());
+			.for_each(|(item, xform, init_xform, t_created)| {
+				let translation = init_xform.translation
+					+ (item.velocity * t.elapsed().saturating_sub(t_created.0).as_secs_f32());
+				xform
+					.map_unchanged(|xform| &mut xform.translation)
+					.set_if_neq(translation);
 			});
 	}
 }
 
-#[derive(Debug, Component, Reflect)]
-pub struct Angular {
-	pub velocity: Quat,
+/// Time to simulate a particle for this frame. Particles spawned partway through the
+/// frame only simulate for the part of it they existed, so high-rate effects don't clump
+/// at frame boundaries, and particles with a [ParticleTimeScale] for that fraction of it.
+pub fn particle_dt(
+	t: &Time,
+	created: Option<&TimeCreated>,
+	scale: Option<&ParticleTimeScale>,
+) -> f32 {
+	let scale = scale.map_or(1.0, |scale| scale.0.max(0.0));
+	match created {
+		// `TimeCreated` has already been moved by the scale for this frame.
+		Some(created) => t
+			.elapsed()
+			.saturating_sub(created.0)
+			.min(t.delta().mul_f32(scale))
+			.as_secs_f32(),
+		None => t.delta_seconds() * scale,
+	}
 }
-impl Angular {
-	pub fn tick(mut q: Query<(&Self, &mut Transform)>, t: Res<Time>) {
-		q.par_iter_mut().for_each(|(item, mut xform)| {
-			xform.rotation = xform.rotation.slerp(item.velocity, t.delta_seconds())
-		});
+
+/// Spins the particle at a constant rate, as an axis in the particle's parent space scaled
+/// by the speed in radians per second, e.g. `AngularVelocity(Vec3::Z * TAU)` for one
+/// turn per second around Z.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
+pub struct AngularVelocity(pub Vec3);
+impl AngularVelocity {
+	pub fn new(axis: Dir3, speed: f32) -> Self {
+		Self(axis * speed)
+	}
+
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<
+			(
+				&Self,
+				&mut Transform,
+				Option<&TimeCreated>,
+				Option<&ParticleTimeScale>,
+			),
+			F,
+		>,
+		t: Res<Time>,
+	) {
+		q.par_iter_mut()
+			.for_each(|(item, xform, created, time_scale)| {
+				if item.0 == Vec3::ZERO {
+					return;
+				}
+				let step = Quat::from_scaled_axis(item.0 * particle_dt(&t, created, time_scale));
+				let rotation = (step * xform.rotation).normalize();
+				xform
+					.map_unchanged(|xform| &mut xform.rotation)
+					.set_if_neq(rotation);
+			});
 	}
 }
 
-#[derive(Debug, Component, Reflect)]
+#[allow(deprecated)]
+pub use angular::Angular;
+
+// Keeps the deprecation lints off the impls derived for `Angular`.
+#[allow(deprecated)]
+mod angular {
+	use bevy::{
+		ecs::component::{ComponentHooks, StorageType},
+		prelude::*,
+	};
+
+	use super::AngularVelocity;
+
+	/// Replaced by an [AngularVelocity] as soon as it is added, rotating by `velocity` once
+	/// per second. It used to slerp towards `velocity` by the frame's delta instead, which
+	/// depended on the frame rate.
+	#[deprecated(note = "use `AngularVelocity`, e.g. `AngularVelocity(velocity.to_scaled_axis())`")]
+	#[derive(Debug, Clone, Copy, Reflect)]
+	#[reflect(Component)]
+	pub struct Angular {
+		pub velocity: Quat,
+	}
+
+	impl Component for Angular {
+		const STORAGE_TYPE: StorageType = StorageType::Table;
+
+		fn register_component_hooks(hooks: &mut ComponentHooks) {
+			hooks.on_add(|mut world, id, _| {
+				let velocity = world
+					.get::<Angular>(id)
+					.map_or(Quat::IDENTITY, |angular| angular.velocity);
+				world
+					.commands()
+					.entity(id)
+					.insert(AngularVelocity(velocity.to_scaled_axis()))
+					.remove::<Angular>();
+			});
+		}
+	}
+}
+
+/// Grows the particle's scale exponentially, multiplying it by `scale` every second, e.g.
+/// `Vec3::splat(2.0)` to double in size each second or `Vec3::splat(0.5)` to halve it.
+///
+/// Applied as `scale.powf(dt)` each frame, so the particle is the same size after the
+/// same time at any frame rate. Components below zero are treated as zero, shrinking that
+/// axis to nothing straight away.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
 pub struct MulScale {
 	pub scale: Vec3,
 }
 impl MulScale {
-	pub fn tick(mut q: Query<(&Self, &mut Transform)>, t: Res<Time>) {
-		q.par_iter_mut().for_each(|(item, mut xform)| {
-			xform.scale *= Vec3::ONE.lerp(item.scale, t.delta_seconds())
-		});
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<
+			(
+				&Self,
+				&mut Transform,
+				Option<&TimeCreated>,
+				Option<&ParticleTimeScale>,
+			),
+			F,
+		>,
+		t: Res<Time>,
+	) {
+		q.par_iter_mut()
+			.for_each(|(item, xform, created, time_scale)| {
+				let dt = particle_dt(&t, created, time_scale);
+				if dt == 0.0 {
+					return;
+				}
+				let scale = xform.scale * item.scale.max(Vec3::ZERO).powf(dt);
+				xform
+					.map_unchanged(|xform| &mut xform.scale)
+					.set_if_neq(scale);
+			});
 	}
 }
 
-#[derive(Debug, Component, Reflect)]
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
 pub struct AddScale {
 	pub scale: Vec3,
 }
 impl AddScale {
-	pub fn tick(mut q: Query<(&Self, &mut Transform)>, t: Res<Time>) {
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<
+			(
+				&Self,
+				&mut Transform,
+				Option<&TimeCreated>,
+				Option<&ParticleTimeScale>,
+			),
+			F,
+		>,
+		t: Res<Time>,
+	) {
 		q.par_iter_mut()
-			.for_each(|(item, mut xform)| xform.scale += item.scale * t.delta_seconds());
+			.for_each(|(item, xform, created, time_scale)| {
+				let scale = xform.scale + item.scale * particle_dt(&t, created, time_scale);
+				xform
+					.map_unchanged(|xform| &mut xform.scale)
+					.set_if_neq(scale);
+			});
 	}
 }
 
-#[derive(Debug, Component, Reflect)]
+/// Interpolates the particle's scale from its initial scale to `scale` over its lifetime,
+/// along `easing`.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
 pub struct TargetScale {
 	pub scale: Vec3,
+	pub easing: Easing,
 }
 impl TargetScale {
-	pub fn tick(
-		mut q: Query<(
-			&Self,
-			&mut Transform,
-			&InitialTransform,
-			&TimeCreated,
-			&Lifetime,
-		)>,
-		t: Res<Time<Real>>,
+	pub fn new(scale: Vec3) -> Self {
+		Self {
+			scale,
+			easing: Easing::Linear,
+		}
+	}
+
+	pub fn with_easing(self, easing: Easing) -> Self {
+		Self { easing, ..self }
+	}
+
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<
+			(
+				&Self,
+				&mut Transform,
+				&InitialTransform,
+				&TimeCreated,
+				&Lifetime,
+			),
+			F,
+		>,
+		t: Res<Time>,
+	) {
+		q.par_iter_mut()
+			.for_each(|(target, xform, init_xform, t_created, lifetime)| {
+				let s = lifetime_progress(
+					t.elapsed().saturating_sub(t_created.0).as_secs_f32(),
+					lifetime.0.as_secs_f32(),
+				);
+				let scale = init_xform.scale.lerp(target.scale, target.easing.ease(s));
+				xform
+					.map_unchanged(|xform| &mut xform.scale)
+					.set_if_neq(scale);
+			});
+	}
+}
+
+/// Scales the particle's initial scale by a separate curve per axis, each mapping lifetime
+/// progress from `0.0` to `1.0` to a scale factor, e.g. smoke puffs that widen without
+/// growing taller:
+///
+/// ```ignore
+/// SizeOverLifetime::new(|s| 1.0 + 2.0 * s, |_| 1.0, |s| 1.0 + 2.0 * s)
+/// ```
+///
+/// Also used as [ParticleBuffer::size_over_lifetime](crate::buffer::ParticleBuffer::size_over_lifetime).
+#[derive(Debug, Clone, Copy, Component)]
+pub struct SizeOverLifetime {
+	pub x: fn(f32) -> f32,
+	pub y: fn(f32) -> f32,
+	pub z: fn(f32) -> f32,
+}
+impl SizeOverLifetime {
+	pub fn new(x: fn(f32) -> f32, y: fn(f32) -> f32, z: fn(f32) -> f32) -> Self {
+		Self { x, y, z }
+	}
+
+	/// The same curve for every axis.
+	pub fn uniform(curve: fn(f32) -> f32) -> Self {
+		Self::new(curve, curve, curve)
+	}
+
+	pub fn sample(&self, s: f32) -> Vec3 {
+		let s = s.clamp(0.0, 1.0);
+		Vec3::new((self.x)(s), (self.y)(s), (self.z)(s))
+	}
+
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<
+			(
+				&Self,
+				&mut Transform,
+				&InitialTransform,
+				&TimeCreated,
+				&Lifetime,
+			),
+			F,
+		>,
+		t: Res<Time>,
 	) {
 		q.par_iter_mut()
-			.for_each(|(target, mut xform, init_xform, t_created, lifetime)| {
-				xform.scale = init_xform.scale.lerp(
-					target.scale,
-					t.last_update()
-						.unwrap()
-						.duration_since(t_created.0)
-						.as_secs_f32() / lifetime.0.as_secs_f32(),
-				)
+			.for_each(|(item, xform, init_xform, t_created, lifetime)| {
+				let s = lifetime_progress(
+					t.elapsed().saturating_sub(t_created.0).as_secs_f32(),
+					lifetime.0.as_secs_f32(),
+				);
+				xform
+					.map_unchanged(|xform| &mut xform.scale)
+					.set_if_neq(init_xform.scale * item.sample(s));
 			});
 	}
 }
 
-#[derive(Debug, Component, Reflect)]
+/// Interpolates the particle's transform from its [InitialTransform] to `final_xform`
+/// over its lifetime, along `easing`.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
 pub struct TargetTransform {
 	pub final_xform: Transform,
+	pub easing: Easing,
 }
 impl TargetTransform {
-	pub fn tick(
-		mut q: Query<(
-			&Self,
-			&mut Transform,
-			&InitialTransform,
-			&TimeCreated,
-			&Lifetime,
-		)>,
-		t: Res<Time<Real>>,
+	pub fn new(final_xform: Transform) -> Self {
+		Self {
+			final_xform,
+			easing: Easing::Linear,
+		}
+	}
+
+	pub fn with_easing(self, easing: Easing) -> Self {
+		Self { easing, ..self }
+	}
+
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<
+			(
+				&Self,
+				&mut Transform,
+				&InitialTransform,
+				&TimeCreated,
+				&Lifetime,
+			),
+			F,
+		>,
+		t: Res<Time>,
 	) {
 		q.par_iter_mut()
 			.for_each(|(item, mut xform, init_xform, init_t, lifetime)| {
-				let elapsed = t.last_update().unwrap().duration_since(**init_t);
-				let s = elapsed.as_secs_f32() / lifetime.as_secs_f32();
-				*xform = Transform {
+				let elapsed = t.elapsed().saturating_sub(**init_t);
+				let s = lifetime_progress(elapsed.as_secs_f32(), lifetime.as_secs_f32());
+				let s = item.easing.ease(s);
+				xform.set_if_neq(Transform {
 					translation: init_xform.translation.lerp(item.final_xform.translation, s),
 					rotation: init_xform.rotation.slerp(item.final_xform.rotation, s),
 					scale: init_xform.scale.lerp(item.final_xform.scale, s),
-				}
+				});
 			});
 	}
 }
@@ -117,12 +344,644 @@ impl TargetTransform {
 pub trait ParticleUpdateFn: FnMut(ParticleDataItem, &Time) + Send + Sync + 'static {}
 impl<F> ParticleUpdateFn for F where F: FnMut(ParticleDataItem, &Time) + Send + Sync + 'static {}
 
+/// Runs a closure on every particle each frame.
+///
+/// Prefer `set_if_neq` over assigning through the [ParticleDataItem] fields, so particles
+/// that didn't move aren't marked changed and skipped by transform propagation.
 #[derive(Component)]
 pub struct DynParticleUpdate(Box<dyn ParticleUpdateFn>);
 
 impl DynParticleUpdate {
-	pub fn tick(mut q: Query<(&mut Self, ParticleData)>, t: Res<Time>) {
+	pub fn tick<F: QueryFilter>(mut q: Query<(&mut Self, ParticleData), F>, t: Res<Time>) {
 		q.par_iter_mut()
 			.for_each(|(item, data)| (item.map_unchanged(|it| &mut it.0))(data, &t));
 	}
 }
+
+/// Velocity integrated into the particle's translation every frame.
+///
+/// Unlike [Linear], which computes the position from the initial transform, this can be
+/// modified over time by other behaviors such as [VectorFieldAdvection].
+#[derive(Default, Debug, Clone, Copy, PartialEq, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
+pub struct Velocity(pub Vec3);
+impl Velocity {
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<
+			(
+				&Self,
+				&mut Transform,
+				Option<&TimeCreated>,
+				Option<&ParticleTimeScale>,
+			),
+			F,
+		>,
+		t: Res<Time>,
+	) {
+		q.par_iter_mut()
+			.for_each(|(vel, mut xform, created, time_scale)| {
+				let dt = particle_dt(&t, created, time_scale);
+				if vel.0 != Vec3::ZERO && dt != 0.0 {
+					xform.translation += vel.0 * dt;
+				}
+			});
+	}
+}
+
+/// World-space acceleration of every particle with a [GravityScale].
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Deref, DerefMut, Reflect)]
+#[reflect(Resource)]
+pub struct ParticleGravity(pub Vec3);
+
+impl Default for ParticleGravity {
+	fn default() -> Self {
+		Self(Vec3::new(0.0, -9.81, 0.0))
+	}
+}
+
+/// Accelerates the particle's [Velocity] by [ParticleGravity] times this factor. Negative
+/// factors make particles buoyant, e.g. for smoke or bubbles.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Deref, DerefMut, Reflect)]
+#[reflect(Component)]
+pub struct GravityScale(pub f32);
+
+impl Default for GravityScale {
+	fn default() -> Self {
+		Self(1.0)
+	}
+}
+
+impl GravityScale {
+	/// Picks a factor uniformly from `range`, e.g. in a factory so each particle falls
+	/// a little differently.
+	pub fn random(range: RangeInclusive<f32>, rng: &mut WyRand) -> Self {
+		Self(range.start() + (range.end() - range.start()) * rng.generate::<f32>())
+	}
+
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<
+			(
+				&Self,
+				&mut Velocity,
+				Option<&Parent>,
+				Option<&TimeCreated>,
+				Option<&ParticleTimeScale>,
+			),
+			F,
+		>,
+		parents: Query<&GlobalTransform>,
+		gravity: Res<ParticleGravity>,
+		t: Res<Time>,
+	) {
+		q.par_iter_mut()
+			.for_each(|(scale, mut vel, parent, created, time_scale)| {
+				let dt = particle_dt(&t, created, time_scale);
+				if scale.0 == 0.0 || dt == 0.0 {
+					return;
+				}
+				let parent = parent_global_transform(parent, &parents);
+				vel.0 += parent_space_vector(&parent, gravity.0 * scale.0) * dt;
+			});
+	}
+}
+
+/// Puts the particle to sleep once its [Velocity] has stayed below `threshold` for
+/// `frames` updates in a row, e.g. for settled debris or accumulated snow.
+///
+/// [Sleeping] particles are skipped by every behavior until they expire, unless the
+/// marker is removed again.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct Sleep {
+	/// Speed below which the particle counts as still, in units per second.
+	pub threshold: f32,
+	pub frames: u32,
+	/// Updates in a row the particle has been still for.
+	pub still_for: u32,
+}
+
+impl Sleep {
+	pub fn new(threshold: f32, frames: u32) -> Self {
+		Self {
+			threshold,
+			frames,
+			still_for: 0,
+		}
+	}
+
+	pub fn tick<F: QueryFilter>(
+		mut cmds: Commands,
+		mut q: Query<(Entity, &mut Self, &Velocity), (F, Without<Sleeping>)>,
+	) {
+		for (id, mut sleep, vel) in &mut q {
+			if vel.0.length_squared() >= sleep.threshold * sleep.threshold {
+				sleep.still_for = 0;
+				continue;
+			}
+			sleep.still_for += 1;
+			if sleep.still_for >= sleep.frames {
+				cmds.entity(id).insert(Sleeping);
+			}
+		}
+	}
+}
+
+/// Marks a particle that is no longer simulated. See [Sleep].
+#[derive(Default, Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct Sleeping;
+
+/// How far a particle with a [DistanceLifetime] may get.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum DistanceLimit {
+	/// Total length of the path it has traveled.
+	Traveled(f32),
+	/// Straight-line distance from where it spawned.
+	Radius(f32),
+}
+
+/// Despawns the particle once it has gone a certain distance, e.g. for muzzle smoke or
+/// spray cones that should end at the same range however fast they are emitted. Its
+/// [Lifetime] still applies, so give it [Lifetime::INFINITE] to only be limited by distance.
+///
+/// Distances are measured in the particle's parent space, like its `Transform`.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct DistanceLifetime {
+	pub limit: DistanceLimit,
+	/// Distance traveled so far.
+	pub traveled: f32,
+	/// Position at the last update.
+	pub previous: Option<Vec3>,
+}
+
+impl DistanceLifetime {
+	pub fn traveled(distance: f32) -> Self {
+		Self::new(DistanceLimit::Traveled(distance))
+	}
+
+	pub fn radius(radius: f32) -> Self {
+		Self::new(DistanceLimit::Radius(radius))
+	}
+
+	pub fn new(limit: DistanceLimit) -> Self {
+		Self {
+			limit,
+			traveled: 0.0,
+			previous: None,
+		}
+	}
+
+	pub fn tick<F: QueryFilter>(
+		mut cmds: Commands,
+		mut q: Query<(Entity, &mut Self, &Transform, &InitialTransform), F>,
+	) {
+		for (id, mut item, xform, initial) in &mut q {
+			let position = xform.translation;
+			let previous = item.previous.unwrap_or(initial.translation);
+			item.traveled += previous.distance(position);
+			item.previous = Some(position);
+			let exceeded = match item.limit {
+				DistanceLimit::Traveled(distance) => item.traveled > distance,
+				DistanceLimit::Radius(radius) => {
+					position.distance_squared(initial.translation) > radius * radius
+				}
+			};
+			if exceeded {
+				cmds.entity(id).despawn_recursive();
+			}
+		}
+	}
+}
+
+/// Slows the particle's [Velocity] like air or water resistance, so falling snow, ash,
+/// or bubbles settle at a terminal velocity instead of accelerating forever.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub struct Drag {
+	/// Deceleration proportional to speed, per second. Suits very small or slow particles.
+	pub linear: f32,
+	/// Drag coefficient times cross-sectional area over mass, for deceleration
+	/// proportional to the square of speed.
+	pub quadratic: f32,
+	/// Density of the medium `quadratic` drag is scaled by, e.g. about `1.2` for air
+	/// and `1000.0` for water.
+	pub density: f32,
+}
+
+impl Drag {
+	pub fn linear(linear: f32) -> Self {
+		Self {
+			linear,
+			..default()
+		}
+	}
+
+	pub fn quadratic(quadratic: f32, density: f32) -> Self {
+		Self {
+			quadratic,
+			density,
+			..default()
+		}
+	}
+
+	/// Speed at which quadratic drag balances an acceleration of `gravity`, ignoring
+	/// linear drag. Infinite without quadratic drag.
+	pub fn terminal_speed(&self, gravity: f32) -> f32 {
+		(2.0 * gravity.abs() / (self.density * self.quadratic)).sqrt()
+	}
+
+	/// Applies `dt` seconds of drag to the world-space `velocity`. Solved exactly for
+	/// each kind of drag, so long frames slow particles down without reversing them.
+	pub fn apply(&self, velocity: Vec3, dt: f32) -> Vec3 {
+		let velocity = velocity * decay(self.linear, dt);
+		let k = 0.5 * self.density * self.quadratic;
+		velocity / (1.0 + k * velocity.length() * dt)
+	}
+
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<
+			(
+				&Self,
+				&mut Velocity,
+				Option<&Parent>,
+				Option<&TimeCreated>,
+				Option<&ParticleTimeScale>,
+			),
+			F,
+		>,
+		parents: Query<&GlobalTransform>,
+		t: Res<Time>,
+	) {
+		q.par_iter_mut()
+			.for_each(|(drag, mut vel, parent, created, time_scale)| {
+				let dt = particle_dt(&t, created, time_scale);
+				if vel.0 == Vec3::ZERO || dt == 0.0 {
+					return;
+				}
+				let parent = parent_global_transform(parent, &parents);
+				let world_vel = world_space_vector(&parent, vel.0);
+				let new_vel = drag.apply(world_vel, dt);
+				vel.0 = parent_space_vector(&parent, new_vel);
+			});
+	}
+}
+
+/// Moves the particle from its initial translation to `target` over its lifetime,
+/// so many particles can assemble into a shape (see [PointCloud](crate::emission::PointCloud)).
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct MorphTarget {
+	pub target: Vec3,
+}
+impl MorphTarget {
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<
+			(
+				&Self,
+				&mut Transform,
+				&InitialTransform,
+				&TimeCreated,
+				&Lifetime,
+			),
+			F,
+		>,
+		t: Res<Time>,
+	) {
+		q.par_iter_mut()
+			.for_each(|(item, xform, init_xform, init_t, lifetime)| {
+				let elapsed = t.elapsed().saturating_sub(**init_t);
+				let s = lifetime_progress(elapsed.as_secs_f32(), lifetime.as_secs_f32());
+				xform
+					.map_unchanged(|xform| &mut xform.translation)
+					.set_if_neq(init_xform.translation.lerp(item.target, s));
+			});
+	}
+}
+
+/// Pulls the particle toward another entity each frame with a critically damped spring,
+/// e.g. for homing projectile trails or pickup magnets.
+///
+/// `offset` is in the target's local space. Higher `stiffness` follows the target more tightly.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct FollowTarget {
+	pub entity: Entity,
+	pub offset: Vec3,
+	pub stiffness: f32,
+}
+impl FollowTarget {
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<
+			(
+				&Self,
+				&mut Velocity,
+				&GlobalTransform,
+				Option<&Parent>,
+				Option<&TimeCreated>,
+				Option<&ParticleTimeScale>,
+			),
+			F,
+		>,
+		targets: Query<&GlobalTransform>,
+		t: Res<Time>,
+	) {
+		q.par_iter_mut().for_each(
+			|(item, mut vel, global_xform, parent, created, time_scale)| {
+				let dt = particle_dt(&t, created, time_scale);
+				let Ok(target) = targets.get(item.entity) else {
+					return;
+				};
+				let parent = parent_global_transform(parent, &targets);
+				let to_target = target.transform_point(item.offset) - global_xform.translation();
+				let accel = item.stiffness * to_target
+					- 2.0 * item.stiffness.sqrt() * world_space_vector(&parent, vel.0);
+				let new_vel = vel.0 + parent_space_vector(&parent, accel) * dt;
+				vel.set_if_neq(Velocity(new_vel));
+			},
+		);
+	}
+}
+
+/// Moves a world-space particle along with its emitter by a fraction of the emitter's
+/// motion that changes over the particle's lifetime, e.g. following it at first and then
+/// breaking free. Local-space particles already move with their spewer.
+///
+/// The fraction goes from `start` to `end`, with lifetime progress remapped by `curve`.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component, from_reflect = false)]
+pub struct InheritVelocityOverLifetime {
+	pub emitter: Entity,
+	pub start: f32,
+	pub end: f32,
+	#[reflect(ignore)]
+	pub curve: fn(f32) -> f32,
+	/// Where the emitter was on the previous tick, in world space.
+	pub last_emitter_position: Option<Vec3>,
+}
+impl InheritVelocityOverLifetime {
+	pub fn new(emitter: Entity, start: f32, end: f32) -> Self {
+		Self {
+			emitter,
+			start,
+			end,
+			curve: |s| s,
+			last_emitter_position: None,
+		}
+	}
+
+	pub fn with_curve(self, curve: fn(f32) -> f32) -> Self {
+		Self { curve, ..self }
+	}
+
+	pub fn fraction(&self, s: f32) -> f32 {
+		self.start + (self.end - self.start) * (self.curve)(s.clamp(0.0, 1.0))
+	}
+
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<
+			(
+				&mut Self,
+				&mut Transform,
+				Option<&Parent>,
+				&TimeCreated,
+				&Lifetime,
+			),
+			F,
+		>,
+		emitters: Query<&GlobalTransform>,
+		t: Res<Time>,
+	) {
+		q.par_iter_mut()
+			.for_each(|(mut item, mut xform, parent, created, lifetime)| {
+				let Ok(emitter) = emitters.get(item.emitter) else {
+					return;
+				};
+				let emitter_pos = emitter.translation();
+				let Some(last) = item.last_emitter_position.replace(emitter_pos) else {
+					return;
+				};
+				let s = lifetime_progress(
+					t.elapsed().saturating_sub(**created).as_secs_f32(),
+					lifetime.as_secs_f32(),
+				);
+				let delta = (emitter_pos - last) * item.fraction(s);
+				if delta != Vec3::ZERO {
+					let parent = parent_global_transform(parent, &emitters);
+					let delta = parent_space_vector(&parent, delta);
+					xform.translation += delta;
+				}
+			});
+	}
+}
+
+/// Keeps the particle's rotation aligned to something other than its parent, both when
+/// it spawns and every frame after, e.g. for arrows, leaves, or shards. Overrides any
+/// rotation set by other behaviors.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Component, Reflect)]
+#[reflect(Component)]
+pub enum Alignment {
+	/// Aligned with the world axes.
+	#[default]
+	World,
+	/// Aligned with the spewer it was spawned by (see [ParticleOf]), even in world space.
+	Local,
+	/// Local +Y points along the particle's [Velocity], or its [Linear] velocity. Works
+	/// with [Billboard::AxisAligned](crate::material::Billboard::AxisAligned) for
+	/// stretched billboards. Keeps its rotation while not moving.
+	Velocity,
+	/// Same rotation as the camera, so the mesh's XY plane faces it. Unlike a billboarded
+	/// material, this is only correct for one camera: the active one with the highest order.
+	FaceCamera,
+	/// A fixed world-space rotation.
+	Custom(Quat),
+}
+impl Alignment {
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<
+			(
+				&Self,
+				&mut Transform,
+				Option<&Parent>,
+				Option<&ParticleOf>,
+				Option<&Velocity>,
+				Option<&Linear>,
+			),
+			F,
+		>,
+		transforms: Query<&GlobalTransform>,
+		cameras: Cameras,
+	) {
+		let camera = main_camera(&cameras).map(|(.., xform)| xform.compute_transform().rotation);
+		let rotation_of = |id: Entity| {
+			transforms
+				.get(id)
+				.ok()
+				.map(|xform| xform.compute_transform().rotation)
+		};
+		q.par_iter_mut()
+			.for_each(|(item, xform, parent, spewer, vel, linear)| {
+				let parent_rotation = match parent {
+					Some(parent) => match rotation_of(parent.get()) {
+						Some(rotation) => rotation,
+						None => return,
+					},
+					None => Quat::IDENTITY,
+				};
+				let target = match *item {
+					Alignment::World => Some(Quat::IDENTITY),
+					Alignment::Local => spewer.and_then(|spewer| rotation_of(spewer.0)),
+					Alignment::Velocity => vel
+						.map(|vel| vel.0)
+						.or(linear.map(|linear| linear.velocity))
+						.and_then(|v| (parent_rotation * v).try_normalize())
+						.map(|dir| Quat::from_rotation_arc(Vec3::Y, dir)),
+					Alignment::FaceCamera => camera,
+					Alignment::Custom(rotation) => Some(rotation),
+				};
+				let Some(target) = target else {
+					return;
+				};
+				xform
+					.map_unchanged(|xform| &mut xform.rotation)
+					.set_if_neq(parent_rotation.inverse() * target);
+			});
+	}
+}
+
+#[cfg(feature = "render")]
+pub(crate) type Cameras<'w, 's> =
+	Query<'w, 's, (Entity, &'static Camera, &'static GlobalTransform)>;
+#[cfg(not(feature = "render"))]
+pub(crate) type Cameras<'w, 's> = ();
+
+/// Keeps queries writing a `GlobalTransform` disjoint from [Cameras].
+#[cfg(feature = "render")]
+pub(crate) type NotCamera = Without<Camera>;
+#[cfg(not(feature = "render"))]
+pub(crate) type NotCamera = ();
+
+/// Item of [Cameras] returned by [main_camera].
+#[cfg(feature = "render")]
+pub(crate) type CameraItem<'a> = (Entity, &'a Camera, &'a GlobalTransform);
+#[cfg(not(feature = "render"))]
+pub(crate) type CameraItem<'a> = (Entity, (), &'a GlobalTransform);
+
+/// The active camera with the highest order, usually the main one.
+#[cfg(feature = "render")]
+pub(crate) fn main_camera<'a>(cameras: &'a Cameras) -> Option<CameraItem<'a>> {
+	cameras
+		.iter()
+		.filter(|(_, camera, _)| camera.is_active)
+		.max_by_key(|(_, camera, _)| camera.order)
+}
+#[cfg(not(feature = "render"))]
+pub(crate) fn main_camera<'a>(_: &'a Cameras) -> Option<CameraItem<'a>> {
+	None
+}
+
+/// Wobbles the rendered position of the particle with animated noise, e.g. for fireflies
+/// or heat shimmer.
+///
+/// The offset is added to the `GlobalTransform` after transform propagation, so the
+/// `Transform` that behaviors like [Velocity] integrate stays smooth, and so do trails.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct PositionNoise {
+	/// Largest offset along each world axis.
+	pub amplitude: Vec3,
+	/// Roughly how many times per second the offset changes direction.
+	pub frequency: f32,
+	/// Offset added on the last update, and the translation it resulted in.
+	pub applied: Option<(Vec3, Vec3)>,
+}
+impl PositionNoise {
+	pub fn new(amplitude: Vec3, frequency: f32) -> Self {
+		Self {
+			amplitude,
+			frequency,
+			applied: None,
+		}
+	}
+
+	/// Offset at `secs` into the particle's lifetime, seeded by `seed`.
+	pub fn offset(&self, seed: u32, secs: f32) -> Vec3 {
+		let x = secs * self.frequency;
+		let noise = |axis| crate::noise::gradient_noise(crate::noise::hash(seed, axis), x);
+		self.amplitude * Vec3::new(noise(0), noise(1), noise(2))
+	}
+
+	pub fn tick(
+		mut q: Query<(
+			Entity,
+			&mut Self,
+			&mut GlobalTransform,
+			Option<&TimeCreated>,
+		)>,
+		t: Res<Time>,
+	) {
+		q.par_iter_mut()
+			.for_each(|(id, mut item, mut global_xform, created)| {
+				let mut translation = global_xform.translation();
+				// Unless transform propagation already replaced it, the last offset is still applied.
+				if let Some((offset, result)) = item.applied {
+					if translation == result {
+						translation -= offset;
+					}
+				}
+				let secs = match created {
+					Some(created) => t.elapsed().saturating_sub(created.0).as_secs_f32(),
+					None => t.elapsed_seconds_wrapped(),
+				};
+				let offset = item.offset(id.index(), secs);
+				let mut affine = global_xform.affine();
+				affine.translation = (translation + offset).into();
+				*global_xform = affine.into();
+				item.applied = Some((offset, translation + offset));
+			});
+	}
+}
+
+/// The [GlobalTransform] of the particle's parent, which is the space its [Transform] and
+/// [Velocity] are in, or the identity for root particles.
+///
+/// Read from the parent itself rather than derived from the particle's own transforms,
+/// which can't be inverted while the particle is scaled to zero.
+pub(crate) fn parent_global_transform<F: QueryFilter>(
+	parent: Option<&Parent>,
+	globals: &Query<&GlobalTransform, F>,
+) -> GlobalTransform {
+	parent
+		.and_then(|parent| globals.get(parent.get()).ok())
+		.copied()
+		.unwrap_or_default()
+}
+
+/// Converts a world-space vector into the space of the particle's parent (see
+/// [parent_global_transform]).
+pub(crate) fn parent_space_vector(parent: &GlobalTransform, v: Vec3) -> Vec3 {
+	parent.affine().inverse().transform_vector3(v)
+}
+
+/// Moves an entity by the world-space `delta` right away: its `Transform` for the next
+/// transform propagation, and its `GlobalTransform` for the rest of this frame. Returns
+/// the move in the space of its parent.
+pub(crate) fn translate_now(
+	xform: &mut Transform,
+	global_xform: &mut GlobalTransform,
+	parent: &GlobalTransform,
+	delta: Vec3,
+) -> Vec3 {
+	let local_delta = parent_space_vector(parent, delta);
+	xform.translation += local_delta;
+	let mut affine = global_xform.affine();
+	affine.translation += bevy::math::Vec3A::from(delta);
+	*global_xform = affine.into();
+	local_delta
+}
+
+/// Converts a vector in the space of the particle's parent (see [parent_global_transform])
+/// into world space.
+pub(crate) fn world_space_vector(parent: &GlobalTransform, v: Vec3) -> Vec3 {
+	parent.affine().transform_vector3(v)
+}
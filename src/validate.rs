@@ -0,0 +1,176 @@
+//! Checks in debug builds for effect settings that would misbehave, warning about them
+//! and clamping them to safe values.
+
+use bevy::{
+	prelude::*,
+	utils::{Duration, HashSet},
+};
+
+use crate::{
+	buffer::ParticleBuffer, lifecycle::ParticleOf, update::Velocity, EffectName, Lifetime, Spewer,
+};
+
+/// Limits the settings of effects are clamped to in debug builds, where
+/// [ParticlesPlugin](crate::ParticlesPlugin) checks every [Spewer] and
+/// [ParticleBuffer] when it changes, and every particle as it is spawned:
+///
+/// - An `interval` shorter than `min_interval` is raised to it, since it would spawn
+///   thousands of particles per spewer each frame. A zero `interval` is left alone, since
+///   it is how spewers only emit bursts.
+/// - A spewer's `jitter` longer than its `interval` is shortened to it.
+/// - A [Lifetime] shorter than `min_lifetime`, which would likely expire before it is
+///   ever drawn, is raised to it.
+/// - A non-finite [Velocity] is zeroed, since it would spread NaN through the
+///   particle's transform.
+///
+/// Warnings name the [EffectName] or `Name` of the spewer where it has one. Particle
+/// warnings are only logged once per spewer and problem, for as long as the spewer exists.
+#[derive(Resource, Debug, Clone, Copy, Reflect)]
+#[reflect(Resource)]
+pub struct ValidationLimits {
+	pub min_interval: Duration,
+	pub min_lifetime: Duration,
+}
+
+impl Default for ValidationLimits {
+	fn default() -> Self {
+		Self {
+			min_interval: Duration::from_micros(100),
+			min_lifetime: Duration::from_secs_f32(1.0 / 60.0),
+		}
+	}
+}
+
+impl ValidationLimits {
+	pub fn validate_spewers(
+		limits: Res<Self>,
+		mut q: Query<(Entity, &mut Spewer, Option<&EffectName>, Option<&Name>), Changed<Spewer>>,
+	) {
+		for (id, mut spewer, effect, name) in &mut q {
+			let spewer = spewer.bypass_change_detection();
+			if !spewer.interval.is_zero() && spewer.interval < limits.min_interval {
+				warn!(
+					"{}: interval {:?} is shorter than {:?}; clamping",
+					label(id, effect, name),
+					spewer.interval,
+					limits.min_interval,
+				);
+				spewer.interval = limits.min_interval;
+			}
+			if spewer.jitter > spewer.interval {
+				warn!(
+					"{}: jitter {:?} is longer than the interval {:?}; clamping",
+					label(id, effect, name),
+					spewer.jitter,
+					spewer.interval,
+				);
+				spewer.jitter = spewer.interval;
+			}
+		}
+	}
+
+	pub fn validate_buffers(
+		limits: Res<Self>,
+		mut q: Query<
+			(
+				Entity,
+				&mut ParticleBuffer,
+				Option<&EffectName>,
+				Option<&Name>,
+			),
+			Changed<ParticleBuffer>,
+		>,
+	) {
+		for (id, mut buffer, effect, name) in &mut q {
+			let buffer = buffer.bypass_change_detection();
+			if !buffer.interval.is_zero() && buffer.interval < limits.min_interval {
+				warn!(
+					"{}: buffer interval {:?} is shorter than {:?}; clamping",
+					label(id, effect, name),
+					buffer.interval,
+					limits.min_interval,
+				);
+				buffer.interval = limits.min_interval;
+			}
+			if buffer.lifetime < limits.min_lifetime {
+				warn!(
+					"{}: buffer lifetime {:?} is shorter than {:?}; clamping",
+					label(id, effect, name),
+					buffer.lifetime,
+					limits.min_lifetime,
+				);
+				buffer.lifetime = limits.min_lifetime;
+			}
+		}
+	}
+
+	pub fn validate_particles(
+		limits: Res<Self>,
+		mut lifetimes: Query<
+			(
+				Entity,
+				&mut Lifetime,
+				Option<&EffectName>,
+				Option<&ParticleOf>,
+			),
+			Added<Lifetime>,
+		>,
+		mut velocities: Query<
+			(
+				Entity,
+				&mut Velocity,
+				Option<&EffectName>,
+				Option<&ParticleOf>,
+			),
+			Changed<Velocity>,
+		>,
+		names: Query<(Option<&EffectName>, Option<&Name>)>,
+		mut removed_spewers: RemovedComponents<Spewer>,
+		mut warned: Local<HashSet<(Entity, &'static str)>>,
+	) {
+		let removed = removed_spewers.read().collect::<HashSet<_>>();
+		if !removed.is_empty() {
+			warned.retain(|(spewer, _)| !removed.contains(spewer));
+		}
+		// Particles carry their spewer's `EffectName`, but not its `Name`.
+		let source = |id: Entity, effect: Option<&EffectName>, of: Option<&ParticleOf>| {
+			let spewer = of.map_or(id, |of| of.0);
+			let (spewer_effect, name) = names.get(spewer).unwrap_or_default();
+			(spewer, label(spewer, effect.or(spewer_effect), name))
+		};
+		for (id, mut lifetime, effect, of) in &mut lifetimes {
+			if lifetime.0 >= limits.min_lifetime {
+				continue;
+			}
+			let (spewer, label) = source(id, effect, of);
+			if warned.insert((spewer, "lifetime")) {
+				warn!(
+					"{label}: particle lifetime {:?} is shorter than {:?}; clamping",
+					lifetime.0, limits.min_lifetime,
+				);
+			}
+			lifetime.0 = limits.min_lifetime;
+		}
+		for (id, mut velocity, effect, of) in &mut velocities {
+			if velocity.is_finite() {
+				continue;
+			}
+			let (spewer, label) = source(id, effect, of);
+			if warned.insert((spewer, "velocity")) {
+				warn!(
+					"{label}: particle velocity {:?} is not finite; zeroing",
+					velocity.0
+				);
+			}
+			velocity.0 = Vec3::ZERO;
+		}
+	}
+}
+
+fn label(id: Entity, effect: Option<&EffectName>, name: Option<&Name>) -> String {
+	match (effect, name) {
+		(Some(effect), _) => format!("effect {:?} ({id})", effect.as_str()),
+		(None, Some(name)) => format!("spewer {:?} ({id})", name.as_str()),
+		(None, None) => format!("spewer {id}"),
+	}
+}
@@ -0,0 +1,237 @@
+use bevy::{ecs::query::QueryFilter, prelude::*};
+
+use crate::{
+	dilation::ParticleTimeScale,
+	math::decay,
+	update::{parent_global_transform, parent_space_vector, particle_dt, Velocity},
+	ParticleLayers, TimeCreated,
+};
+
+/// A baked 3D grid of velocities, sampled with trilinear filtering.
+///
+/// Cells are stored X-major, then Y, then Z.
+#[derive(Asset, Debug, Clone, Reflect)]
+pub struct VectorField {
+	pub dimensions: UVec3,
+	pub data: Vec<Vec3>,
+}
+
+impl VectorField {
+	/// Bakes a field by evaluating `f` at the normalized (`0.0..=1.0`) coordinates of each cell.
+	pub fn from_fn(dimensions: UVec3, mut f: impl FnMut(Vec3) -> Vec3) -> Self {
+		let dimensions = dimensions.max(UVec3::ONE);
+		let max = (dimensions - UVec3::ONE).max(UVec3::ONE).as_vec3();
+		let mut data = Vec::with_capacity((dimensions.x * dimensions.y * dimensions.z) as usize);
+		for z in 0..dimensions.z {
+			for y in 0..dimensions.y {
+				for x in 0..dimensions.x {
+					data.push(f(UVec3::new(x, y, z).as_vec3() / max));
+				}
+			}
+		}
+		Self { dimensions, data }
+	}
+
+	fn cell(&self, cell: UVec3) -> Vec3 {
+		let cell = cell.min(self.dimensions.saturating_sub(UVec3::ONE));
+		let i = cell.x + self.dimensions.x * (cell.y + self.dimensions.y * cell.z);
+		self.data.get(i as usize).copied().unwrap_or(Vec3::ZERO)
+	}
+
+	/// Samples the field at normalized coordinates, clamped to `0.0..=1.0`.
+	pub fn sample(&self, uvw: Vec3) -> Vec3 {
+		let pos =
+			uvw.clamp(Vec3::ZERO, Vec3::ONE) * self.dimensions.saturating_sub(UVec3::ONE).as_vec3();
+		let base = pos.floor();
+		let f = pos - base;
+		let base = base.as_uvec3();
+		let lerp_x = |y, z| {
+			self.cell(base + UVec3::new(0, y, z))
+				.lerp(self.cell(base + UVec3::new(1, y, z)), f.x)
+		};
+		let lerp_y = |z| lerp_x(0, z).lerp(lerp_x(1, z), f.y);
+		lerp_y(0).lerp(lerp_y(1), f.z)
+	}
+}
+
+#[cfg(feature = "render")]
+impl VectorField {
+	/// Reads a field from a flowmap texture, 3D or 2D (a single layer deep), with velocities
+	/// in its RGB channels. Float formats are read as is, while `Rgba8Unorm` maps `0..=255`
+	/// to `-1.0..=1.0` as flowmaps are usually painted. Returns `None` for other formats.
+	pub fn from_image(image: &Image) -> Option<Self> {
+		use bevy::render::render_resource::TextureFormat;
+
+		let size = image.texture_descriptor.size;
+		let dimensions = UVec3::new(size.width, size.height, size.depth_or_array_layers);
+		let data = match image.texture_descriptor.format {
+			TextureFormat::Rgba32Float => image
+				.data
+				.chunks_exact(16)
+				.map(|texel| {
+					let c =
+						|i: usize| f32::from_le_bytes(texel[i * 4..i * 4 + 4].try_into().unwrap());
+					Vec3::new(c(0), c(1), c(2))
+				})
+				.collect(),
+			TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => image
+				.data
+				.chunks_exact(4)
+				.map(|texel| {
+					let c = |i: usize| texel[i] as f32 / 255.0 * 2.0 - 1.0;
+					Vec3::new(c(0), c(1), c(2))
+				})
+				.collect(),
+			TextureFormat::Rgba8Snorm => image
+				.data
+				.chunks_exact(4)
+				.map(|texel| {
+					let c = |i: usize| (texel[i] as i8 as f32 / 127.0).max(-1.0);
+					Vec3::new(c(0), c(1), c(2))
+				})
+				.collect(),
+			_ => return None,
+		};
+		Some(Self {
+			dimensions: dimensions.max(UVec3::ONE),
+			data,
+		})
+	}
+}
+
+/// Sources the [VectorField] of the [VectorFieldVolume] on the same entity from a flowmap
+/// texture (see [VectorField::from_image]) instead of a baked grid. The field is replaced
+/// once the image loads, and again whenever it changes, e.g. when hot-reloaded.
+#[cfg(feature = "render")]
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub struct VectorFieldTexture(pub Handle<Image>);
+
+#[cfg(feature = "render")]
+impl VectorFieldTexture {
+	pub fn bake(
+		mut q: Query<(Ref<Self>, &mut VectorFieldVolume)>,
+		mut events: EventReader<AssetEvent<Image>>,
+		images: Option<Res<Assets<Image>>>,
+		fields: Option<ResMut<Assets<VectorField>>>,
+	) {
+		let (Some(images), Some(mut fields)) = (images, fields) else {
+			return;
+		};
+		let changed = events
+			.read()
+			.filter_map(|event| match *event {
+				AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(id),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		for (texture, mut volume) in &mut q {
+			if !texture.is_changed() && !changed.contains(&texture.0.id()) {
+				continue;
+			}
+			let Some(image) = images.get(&texture.0) else {
+				continue;
+			};
+			match VectorField::from_image(image) {
+				Some(field) => volume.field = fields.add(field),
+				None => warn!(
+					"can't read a vector field from a {:?} texture",
+					image.texture_descriptor.format
+				),
+			}
+		}
+	}
+}
+
+/// Places a [VectorField] in the world. The field fills the unit cube `-0.5..=0.5` in
+/// this entity's local space, so the transform positions, rotates, and scales it.
+/// Sampled velocities are in the same local space, so they are rotated and scaled too.
+///
+/// Only affects particles sharing one of its [ParticleLayers].
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub struct VectorFieldVolume {
+	pub field: Handle<VectorField>,
+	/// Multiplier for the sampled velocities, in world units per second.
+	pub strength: f32,
+}
+
+/// Advects particles through every [VectorFieldVolume] they are inside.
+///
+/// The particle's [Velocity] approaches the field velocity at a rate set by `drag`.
+/// Higher values follow the field more tightly; `f32::INFINITY` snaps to it.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct VectorFieldAdvection {
+	pub drag: f32,
+}
+impl VectorFieldAdvection {
+	pub fn tick<F: QueryFilter>(
+		mut q: Query<
+			(
+				&Self,
+				&mut Velocity,
+				&GlobalTransform,
+				Option<&Parent>,
+				Option<&ParticleLayers>,
+				Option<&TimeCreated>,
+				Option<&ParticleTimeScale>,
+			),
+			F,
+		>,
+		volumes: Query<(
+			&VectorFieldVolume,
+			&GlobalTransform,
+			Option<&ParticleLayers>,
+		)>,
+		parents: Query<&GlobalTransform>,
+		fields: Option<Res<Assets<VectorField>>>,
+		t: Res<Time>,
+	) {
+		// May run without `AssetPlugin`, e.g. in a headless rollback simulation.
+		let Some(fields) = fields else {
+			return;
+		};
+		let volumes = volumes
+			.iter()
+			.filter_map(|(volume, xform, layers)| {
+				let field = fields.get(&volume.field)?;
+				let affine = xform.affine();
+				Some((field, volume.strength, affine, affine.inverse(), layers))
+			})
+			.collect::<Vec<_>>();
+		if volumes.is_empty() {
+			return;
+		}
+		q.par_iter_mut().for_each(
+			|(item, mut vel, global_xform, parent, layers, created, time_scale)| {
+				let dt = particle_dt(&t, created, time_scale);
+				if dt == 0.0 {
+					return;
+				}
+				let pos = global_xform.translation();
+				let mut target = Vec3::ZERO;
+				let mut inside = false;
+				for &(field, strength, volume_xform, inverse, volume_layers) in &volumes {
+					if !ParticleLayers::interact(layers, volume_layers) {
+						continue;
+					}
+					let local = inverse.transform_point3(pos);
+					if local.abs().cmple(Vec3::splat(0.5)).all() {
+						inside = true;
+						target +=
+							volume_xform.transform_vector3(field.sample(local + 0.5)) * strength;
+					}
+				}
+				if !inside {
+					return;
+				}
+				let parent = parent_global_transform(parent, &parents);
+				let target = parent_space_vector(&parent, target);
+				let s = 1.0 - decay(item.drag, dt);
+				let new_vel = vel.0.lerp(target, s);
+				vel.set_if_neq(Velocity(new_vel));
+			},
+		);
+	}
+}
@@ -0,0 +1,176 @@
+//! Particles interacting with a flat water surface, e.g. rain splashing into a lake or
+//! bubbles rising to the surface.
+
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+	ecs::{query::QueryFilter, system::EntityCommands},
+	prelude::*,
+};
+
+use crate::{
+	dilation::ParticleTimeScale,
+	update::{
+		parent_global_transform, parent_space_vector, particle_dt, world_space_vector, Velocity,
+	},
+	ParticleFactory, TimeCreated,
+};
+
+/// A factory shared by every particle that splashes with it, spawning `count` particles
+/// where a particle enters the water.
+#[derive(Clone)]
+pub struct WaterSplash {
+	pub factory: Arc<Mutex<Box<dyn ParticleFactory>>>,
+	pub count: u32,
+}
+
+impl WaterSplash {
+	pub fn new(factory: impl ParticleFactory, count: u32) -> Self {
+		Self {
+			factory: Arc::new(Mutex::new(Box::new(factory))),
+			count,
+		}
+	}
+}
+
+/// What happens to a particle when it enters the water.
+#[derive(Debug, Clone, Copy)]
+pub enum WaterEntry {
+	/// Keeps going, e.g. for bubbles or sinking debris.
+	Pass,
+	/// Despawned.
+	Kill,
+	/// Changed by the function, e.g. a raindrop turned into a ripple by removing its
+	/// [Velocity] and inserting a decal. It stops interacting with the water.
+	Convert(fn(&mut EntityCommands)),
+}
+
+/// Sent to a particle with a [WaterSurface] when it crosses the surface, before any
+/// [WaterEntry] response is applied.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WaterCrossing {
+	/// Where the particle crossed the surface, in world space.
+	pub position: Vec3,
+	/// World-space velocity of the particle.
+	pub velocity: Vec3,
+	/// Whether the particle went under water, rather than out of it.
+	pub entering: bool,
+}
+
+/// A horizontal water plane this particle interacts with.
+///
+/// Crossings are detected from the particle's `GlobalTransform`, once it is on the
+/// other side of the surface, so [WaterCrossing::position] is estimated from its [Velocity].
+#[derive(Component, Clone, Reflect)]
+#[reflect(Component, from_reflect = false)]
+pub struct WaterSurface {
+	/// World-space height of the surface.
+	pub height: f32,
+	#[reflect(ignore)]
+	pub entry: WaterEntry,
+	#[reflect(ignore)]
+	pub splash: Option<WaterSplash>,
+	/// Upward acceleration of the particle's [Velocity] while under water.
+	pub buoyancy: f32,
+	/// Whether the particle was under water at the last update.
+	pub submerged: Option<bool>,
+}
+
+impl WaterSurface {
+	pub fn new(height: f32, entry: WaterEntry) -> Self {
+		Self {
+			height,
+			entry,
+			splash: None,
+			buoyancy: 0.0,
+			submerged: None,
+		}
+	}
+
+	pub fn with_splash(self, splash: WaterSplash) -> Self {
+		Self {
+			splash: Some(splash),
+			..self
+		}
+	}
+
+	pub fn with_buoyancy(self, buoyancy: f32) -> Self {
+		Self { buoyancy, ..self }
+	}
+
+	/// Updates particles one at a time, so splashes are spawned in a stable order.
+	pub fn tick<F: QueryFilter>(
+		mut cmds: Commands,
+		mut q: Query<
+			(
+				Entity,
+				&mut Self,
+				&GlobalTransform,
+				Option<&Parent>,
+				Option<&mut Velocity>,
+				Option<&TimeCreated>,
+				Option<&ParticleTimeScale>,
+			),
+			F,
+		>,
+		parents: Query<&GlobalTransform>,
+		t: Res<Time>,
+	) {
+		let now = t.elapsed();
+		for (id, mut water, global_xform, parent, vel, created, time_scale) in &mut q {
+			let parent = parent_global_transform(parent, &parents);
+			let position = global_xform.translation();
+			let submerged = position.y < water.height;
+			let crossed = water.submerged.replace(submerged) == Some(!submerged);
+
+			let velocity = vel
+				.as_deref()
+				.map_or(Vec3::ZERO, |vel| world_space_vector(&parent, vel.0));
+			if submerged && water.buoyancy != 0.0 {
+				if let Some(mut vel) = vel {
+					let accel = Vec3::Y * water.buoyancy * particle_dt(&t, created, time_scale);
+					vel.0 += parent_space_vector(&parent, accel);
+				}
+			}
+			if !crossed {
+				continue;
+			}
+
+			let depth = position.y - water.height;
+			let position = if velocity.y.abs() > f32::EPSILON {
+				position - velocity * (depth / velocity.y)
+			} else {
+				position.with_y(water.height)
+			};
+			cmds.trigger_targets(
+				WaterCrossing {
+					position,
+					velocity,
+					entering: submerged,
+				},
+				id,
+			);
+			if !submerged {
+				continue;
+			}
+
+			if let Some(splash) = &water.splash {
+				if let Ok(mut factory) = splash.factory.lock() {
+					let xform = GlobalTransform::from_translation(position);
+					for _ in 0..splash.count {
+						factory(&mut cmds, &xform, TimeCreated(now));
+					}
+				}
+			}
+			match water.entry {
+				WaterEntry::Pass => {}
+				WaterEntry::Kill => cmds.entity(id).despawn_recursive(),
+				WaterEntry::Convert(convert) => {
+					let mut particle = cmds.entity(id);
+					particle.remove::<Self>();
+					convert(&mut particle);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,196 @@
+//! Rain, snow, and other weather kept around the camera wherever it goes.
+
+use bevy::{math::Vec3A, prelude::*, utils::HashMap};
+use nanorand::{Rng, WyRand};
+
+#[cfg(feature = "render")]
+use crate::ParticleLayers;
+use crate::{
+	lifecycle::{Finishing, ParticleOf},
+	update::{main_camera, parent_global_transform, translate_now, Cameras, NotCamera},
+	InitialGlobalTransform, InitialTransform, Spewer,
+};
+
+/// Keeps the particles of the [Spewer] on the same entity at a constant density within a
+/// box centered on the main camera, e.g. for rain or snow around the player, instead of
+/// emitting them from one point.
+///
+/// The spewer bursts as many particles as the box is missing, which are moved to random
+/// points inside it. Particles leaving the box, e.g. as they fall or as the camera moves
+/// away, are moved to the opposite side instead of despawning. Give them a long or
+/// [infinite](crate::Lifetime::INFINITE) lifetime, and the spewer a zero `interval` so it
+/// only emits for the volume.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct WeatherVolume {
+	pub size: Vec3,
+	/// Particles per cubic unit.
+	pub density: f32,
+	/// Offset of the box's center from the camera, in world space.
+	pub offset: Vec3,
+	/// Most particles spawned per frame while filling the box, so starting the weather or
+	/// raising its density doesn't hitch.
+	pub max_spawns_per_frame: u32,
+}
+
+impl WeatherVolume {
+	pub fn new(size: Vec3, density: f32) -> Self {
+		Self {
+			size,
+			density,
+			offset: Vec3::ZERO,
+			max_spawns_per_frame: 500,
+		}
+	}
+
+	pub fn with_offset(self, offset: Vec3) -> Self {
+		Self { offset, ..self }
+	}
+
+	pub fn with_max_spawns_per_frame(self, max_spawns_per_frame: u32) -> Self {
+		Self {
+			max_spawns_per_frame,
+			..self
+		}
+	}
+
+	/// Number of particles kept in the box.
+	pub fn target_count(&self) -> u32 {
+		let volume = self.size.max(Vec3::ZERO).element_product();
+		(self.density.max(0.0) * volume).round() as u32
+	}
+
+	/// Queues bursts for the particles each volume is missing, unless its spewer is
+	/// [Finishing].
+	pub fn fill(
+		mut spewers: Query<(Entity, &Self, &mut Spewer), Without<Finishing>>,
+		particles: Query<&ParticleOf>,
+	) {
+		if spewers.is_empty() {
+			return;
+		}
+		let mut counts = HashMap::<Entity, u32>::default();
+		for &ParticleOf(spewer) in &particles {
+			*counts.entry(spewer).or_default() += 1;
+		}
+		for (id, volume, mut spewer) in &mut spewers {
+			let count = counts.get(&id).copied().unwrap_or(0);
+			let missing = volume.target_count().saturating_sub(count);
+			let burst = missing.min(volume.max_spawns_per_frame);
+			if spewer.pending_burst < burst {
+				spewer.pending_burst = burst;
+			}
+		}
+	}
+
+	/// Scatters new particles inside their volume, and wraps ones that left it around to
+	/// the other side.
+	pub fn tick(
+		volumes: Query<&Self>,
+		mut particles: Query<
+			(
+				Ref<ParticleOf>,
+				&mut Transform,
+				&mut GlobalTransform,
+				Option<&Parent>,
+				Option<&mut InitialTransform>,
+				Option<&mut InitialGlobalTransform>,
+			),
+			NotCamera,
+		>,
+		parents: Query<&GlobalTransform, Without<ParticleOf>>,
+		cameras: Cameras,
+		mut rng: Local<WyRand>,
+	) {
+		if volumes.is_empty() {
+			return;
+		}
+		let Some(camera) = main_camera(&cameras).map(|(.., xform)| xform.translation()) else {
+			return;
+		};
+		for (of, mut xform, mut global_xform, parent, initial, initial_global) in &mut particles {
+			let Ok(volume) = volumes.get(of.0) else {
+				continue;
+			};
+			let size = volume.size.max(Vec3::splat(f32::EPSILON));
+			let center = camera + volume.offset;
+			let relative = global_xform.translation() - center;
+			let target = if of.is_added() {
+				let unit = Vec3::new(rng.generate(), rng.generate(), rng.generate());
+				(unit - 0.5) * size
+			} else {
+				(relative + size * 0.5).rem_euclid(size) - size * 0.5
+			};
+			let delta = target - relative;
+			if delta == Vec3::ZERO {
+				continue;
+			}
+			let parent = parent_global_transform(parent, &parents);
+			let local_delta = translate_now(&mut xform, &mut global_xform, &parent, delta);
+			if of.is_added() {
+				if let Some(mut initial) = initial {
+					initial.translation += local_delta;
+				}
+				if let Some(mut initial_global) = initial_global {
+					let mut affine = initial_global.affine();
+					affine.translation += Vec3A::from(delta);
+					**initial_global = affine.into();
+				}
+			}
+		}
+	}
+}
+
+/// Stops [WeatherVolume] particles from showing inside it, e.g. under a roof or inside a
+/// building. Occludes the unit cube `-0.5..=0.5` in this entity's local space, so the
+/// transform positions, rotates, and scales it.
+///
+/// Only occludes particles sharing one of its [ParticleLayers]. Occluded particles are
+/// hidden rather than despawned, so they keep their place in the volume's density, and
+/// show again once they leave every occluder.
+#[derive(Default, Debug, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+pub struct WeatherOccluder;
+
+impl WeatherOccluder {
+	#[cfg(feature = "render")]
+	pub fn tick(
+		occluders: Query<(&GlobalTransform, Option<&ParticleLayers>), With<Self>>,
+		volumes: Query<(), With<WeatherVolume>>,
+		mut particles: Query<(
+			&ParticleOf,
+			&GlobalTransform,
+			&mut Visibility,
+			Option<&ParticleLayers>,
+		)>,
+	) {
+		if volumes.is_empty() {
+			return;
+		}
+		let occluders = occluders
+			.iter()
+			.map(|(xform, layers)| (xform.affine().inverse(), layers))
+			.collect::<Vec<_>>();
+		particles
+			.par_iter_mut()
+			.for_each(|(of, xform, mut visibility, layers)| {
+				if !volumes.contains(of.0) {
+					return;
+				}
+				let pos = xform.translation();
+				let occluded = occluders.iter().any(|&(inverse, occluder_layers)| {
+					ParticleLayers::interact(layers, occluder_layers)
+						&& inverse
+							.transform_point3(pos)
+							.abs()
+							.cmple(Vec3::splat(0.5))
+							.all()
+				});
+				if occluded {
+					visibility.set_if_neq(Visibility::Hidden);
+				} else if *visibility == Visibility::Hidden {
+					*visibility = Visibility::Inherited;
+				}
+			});
+	}
+}
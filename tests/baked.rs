@@ -0,0 +1,71 @@
+//! Baked loops played back by a `BakedLoopPlayer`.
+
+use bevy::{prelude::*, utils::Duration};
+use sond_bevy_particles::{
+	baked::{BakedLoop, BakedLoopPlayer, BakedParticle},
+	template::ParticleTemplate,
+	Lifetime,
+};
+
+fn baked_loop(particles: usize) -> BakedLoop {
+	BakedLoop {
+		period: 1.0,
+		particles: (0..particles)
+			.map(|i| BakedParticle {
+				spawn_offset: i as f32 * 0.1,
+				lifetime: 0.5,
+				samples: vec![
+					(0.0, Transform::IDENTITY),
+					(0.5, Transform::from_xyz(0.0, 1.0, 0.0)),
+				],
+			})
+			.collect(),
+	}
+}
+
+#[test]
+fn players_follow_shrinking_loops() {
+	let mut app = App::new();
+	app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+		.init_asset::<BakedLoop>()
+		.add_systems(Update, BakedLoopPlayer::<StandardMaterial>::tick);
+	let mut loops = app.world_mut().resource_mut::<Assets<BakedLoop>>();
+	let long = loops.add(baked_loop(8));
+	let short = loops.add(baked_loop(4));
+	let template =
+		ParticleTemplate::<StandardMaterial>::new(default(), default(), Lifetime(Duration::ZERO));
+	let player = app
+		.world_mut()
+		.spawn((
+			BakedLoopPlayer::new(long.clone(), template),
+			SpatialBundle::default(),
+		))
+		.id();
+	let children = |app: &App| app.world().get::<Children>(player).map_or(0, |c| c.len());
+	app.update();
+	app.update();
+	assert_eq!(children(&app), 8);
+
+	// Pointed at a shorter loop.
+	app.world_mut()
+		.get_mut::<BakedLoopPlayer>(player)
+		.unwrap()
+		.baked = short;
+	app.update();
+	app.update();
+	assert_eq!(children(&app), 4);
+
+	// Hot-reloaded with fewer particles.
+	app.world_mut()
+		.get_mut::<BakedLoopPlayer>(player)
+		.unwrap()
+		.baked = long.clone();
+	app.update();
+	app.update();
+	app.world_mut()
+		.resource_mut::<Assets<BakedLoop>>()
+		.insert(&long, baked_loop(2));
+	app.update();
+	app.update();
+	assert_eq!(children(&app), 2);
+}
@@ -0,0 +1,103 @@
+//! Particles in a `ParticleBuffer`.
+
+use bevy::{prelude::*, utils::Duration};
+use nanorand::WyRand;
+use sond_bevy_particles::{
+	buffer::{BufferParticle, ParticleBuffer},
+	dilation::TimeDilation,
+};
+
+#[test]
+fn attributes_follow_their_particles() {
+	let mut app = App::new();
+	app.init_resource::<Time>()
+		.add_systems(Update, ParticleBuffer::tick);
+	let mut buffer = ParticleBuffer::new(|_: &mut WyRand| BufferParticle {
+		attributes: Vec4::new(1.0, 2.0, 3.0, 4.0),
+		..default()
+	});
+	buffer.interval = Duration::from_millis(100);
+	let id = app
+		.world_mut()
+		.spawn((buffer, GlobalTransform::IDENTITY))
+		.id();
+	for _ in 0..5 {
+		app.world_mut()
+			.resource_mut::<Time>()
+			.advance_by(Duration::from_millis(100));
+		app.update();
+	}
+	let mut buffer = app.world_mut().get_mut::<ParticleBuffer>(id).unwrap();
+	assert_eq!(buffer.len(), 4);
+	assert_eq!(buffer.attributes.len(), buffer.len());
+	buffer.attributes[0] = Vec4::ZERO;
+	let taken = buffer.take(0);
+	assert_eq!(taken.attributes, Vec4::ZERO);
+	assert_eq!(buffer.attributes.len(), 3);
+	assert!(buffer
+		.attributes
+		.iter()
+		.all(|&attributes| attributes == Vec4::new(1.0, 2.0, 3.0, 4.0)));
+}
+
+#[test]
+fn long_stalls_only_catch_up_on_what_fits() {
+	let mut app = App::new();
+	app.init_resource::<Time>()
+		.add_systems(Update, ParticleBuffer::tick);
+	let mut buffer = ParticleBuffer::new(|_: &mut WyRand| BufferParticle::default());
+	buffer.interval = Duration::from_micros(10);
+	buffer.lifetime = Duration::from_secs(3600);
+	buffer.capacity = 10;
+	// E.g. loaded from a scene saved long before.
+	buffer.last_spawn = Duration::from_secs(1);
+	let id = app
+		.world_mut()
+		.spawn((buffer, GlobalTransform::IDENTITY))
+		.id();
+	app.world_mut()
+		.resource_mut::<Time>()
+		.advance_by(Duration::from_secs(1000));
+	app.update();
+	let buffer = app.world().get::<ParticleBuffer>(id).unwrap();
+	assert_eq!(buffer.len(), 10);
+	assert!(
+		buffer.ages.iter().all(|&age| age <= 1e-4),
+		"{:?}",
+		buffer.ages
+	);
+}
+
+#[test]
+fn particles_in_time_dilation_bubbles_age_and_move_at_its_scale() {
+	let mut app = App::new();
+	app.init_resource::<Time>()
+		.add_systems(Update, ParticleBuffer::tick);
+	let mut buffer = ParticleBuffer::new(|_: &mut WyRand| BufferParticle::default());
+	buffer.lifetime = Duration::from_secs(10);
+	// One particle inside the bubble, one far outside it.
+	buffer.push(Vec3::ZERO, Vec3::X, LinearRgba::WHITE, 0);
+	buffer.push(Vec3::Y * 100.0, Vec3::X, LinearRgba::WHITE, 0);
+	let world = app.world_mut();
+	world.spawn((TimeDilation::new(0.5, 10.0), GlobalTransform::IDENTITY));
+	let id = world.spawn((buffer, GlobalTransform::IDENTITY)).id();
+	for _ in 0..10 {
+		app.world_mut()
+			.resource_mut::<Time>()
+			.advance_by(Duration::from_millis(100));
+		app.update();
+	}
+	let buffer = app.world().get::<ParticleBuffer>(id).unwrap();
+	assert!((buffer.ages[0] - 0.5).abs() < 1e-4, "{:?}", buffer.ages);
+	assert!((buffer.ages[1] - 1.0).abs() < 1e-4, "{:?}", buffer.ages);
+	assert!(
+		(buffer.positions[0].x - 0.5).abs() < 1e-4,
+		"{:?}",
+		buffer.positions
+	);
+	assert!(
+		(buffer.positions[1].x - 1.0).abs() < 1e-4,
+		"{:?}",
+		buffer.positions
+	);
+}
@@ -0,0 +1,58 @@
+//! Particles colliding with `ParticleCollider`s.
+
+use bevy::{prelude::*, utils::Duration};
+use sond_bevy_particles::{
+	collision::{
+		ColliderShape, CollisionResponse, ImpactSpawn, ParticleCollider, ParticleCollision,
+	},
+	lifecycle::ParticleOf,
+	update::Velocity,
+	Deterministic, ParticleLayers, TimeCreated,
+};
+
+#[test]
+fn impact_particles_belong_to_the_same_spewer() {
+	let mut app = App::new();
+	app.init_resource::<Time>()
+		.add_systems(Update, ParticleCollision::tick::<()>);
+	let world = app.world_mut();
+	let spewer = world.spawn_empty().id();
+	world.spawn((
+		ParticleCollider::new(ColliderShape::Plane),
+		GlobalTransform::IDENTITY,
+		ParticleLayers(0b10),
+	));
+	let impact = ImpactSpawn::new(
+		|cmds: &mut Commands, xform: &GlobalTransform, created: TimeCreated| {
+			cmds.spawn((xform.compute_transform(), created))
+		},
+		3,
+	);
+	// Fell through the plane since the last update.
+	world.spawn((
+		ParticleCollision {
+			previous: Some(Vec3::Y),
+			..ParticleCollision::new(0.1).with_response(CollisionResponse::Spawn(impact))
+		},
+		Transform::from_xyz(0.0, -1.0, 0.0),
+		Velocity(Vec3::NEG_Y),
+		ParticleOf(spewer),
+		ParticleLayers(0b10),
+		Deterministic,
+	));
+	app.world_mut()
+		.resource_mut::<Time>()
+		.advance_by(Duration::from_millis(100));
+	app.update();
+
+	let world = app.world_mut();
+	let spawned = world
+		.query_filtered::<(&ParticleOf, &ParticleLayers, Has<Deterministic>), With<TimeCreated>>()
+		.iter(world)
+		.map(|(of, layers, deterministic)| (*of, *layers, deterministic))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		spawned,
+		vec![(ParticleOf(spewer), ParticleLayers(0b10), true); 3]
+	);
+}
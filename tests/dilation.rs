@@ -0,0 +1,49 @@
+//! Particles in `TimeDilation` bubbles expire when their scaled lifetime is up.
+
+use bevy::{prelude::*, utils::Duration};
+use sond_bevy_particles::{dilation::TimeDilation, handle_lifetimes, Lifetime, TimeCreated};
+
+/// Steps a particle with a one second lifetime inside a bubble of `scale`, returning the
+/// how long it lived for.
+fn lifespan(scale: f32) -> Duration {
+	let mut app = App::new();
+	app.init_resource::<Time>().add_systems(
+		Update,
+		(TimeDilation::tick::<()>, handle_lifetimes::<()>).chain(),
+	);
+	// Far enough into the clock that ages can be moved back.
+	let start = Duration::from_secs(10);
+	let world = app.world_mut();
+	world.resource_mut::<Time>().advance_to(start);
+	world.spawn((TimeDilation::new(scale, 10.0), GlobalTransform::IDENTITY));
+	let id = world
+		.spawn((
+			GlobalTransform::IDENTITY,
+			TimeCreated(start),
+			Lifetime(Duration::from_secs(1)),
+		))
+		.id();
+	for _ in 0..100 {
+		app.world_mut()
+			.resource_mut::<Time>()
+			.advance_by(Duration::from_millis(50));
+		app.update();
+		if app.world().get_entity(id).is_none() {
+			return app.world().resource::<Time>().elapsed() - start;
+		}
+	}
+	panic!("particle never expired");
+}
+
+#[test]
+fn sped_up_particles_expire_early() {
+	let lifespan = lifespan(2.0);
+	assert!(lifespan <= Duration::from_millis(600), "{lifespan:?}");
+}
+
+#[test]
+fn slowed_down_particles_expire_late() {
+	let lifespan = lifespan(0.5);
+	assert!(lifespan >= Duration::from_millis(1900), "{lifespan:?}");
+	assert!(lifespan <= Duration::from_millis(2100), "{lifespan:?}");
+}
@@ -0,0 +1,98 @@
+//! Spewers stopped with [stop_emitting_and_finish] don't emit anymore, whatever asks them to,
+//! and are despawned once their particles have expired.
+
+use bevy::{prelude::*, utils::Duration};
+use nanorand::WyRand;
+use sond_bevy_particles::{
+	buffer::{BufferParticle, ParticleBuffer},
+	handle_emit_burst,
+	lifecycle::{despawn_finished_spewers, stop_emitting_and_finish},
+	spawn_particles, EmitBurst, Spewer, SpewerBundle, TimeCreated,
+};
+
+#[test]
+fn finishing_spewers_ignore_bursts() {
+	let mut app = App::new();
+	app.init_resource::<Time>()
+		.add_systems(Update, spawn_particles::<()>)
+		.observe(handle_emit_burst);
+	let spewer = app
+		.world_mut()
+		.spawn((
+			SpewerBundle {
+				spewer: Spewer {
+					interval: Duration::from_millis(100),
+					use_global_coords: true,
+					factory: Box::new(|cmds: &mut Commands, _: &GlobalTransform, t| cmds.spawn(t)),
+					..Spewer::seeded(0)
+				},
+				..default()
+			},
+			Name::new("sparks"),
+		))
+		.id();
+	app.update();
+	app.world_mut().commands().add(move |world: &mut World| {
+		stop_emitting_and_finish(spewer, world);
+	});
+	app.world_mut().flush();
+	let world = app.world_mut();
+	world.trigger_targets(EmitBurst::new(5), spewer);
+	world.trigger_targets(EmitBurst::new(5).from_spewer("sparks"), spewer);
+	for _ in 0..10 {
+		app.world_mut()
+			.resource_mut::<Time>()
+			.advance_by(Duration::from_millis(50));
+		app.update();
+	}
+	let world = app.world_mut();
+	let spawned = world.query::<&TimeCreated>().iter(world).count();
+	assert_eq!(spawned, 0);
+}
+
+#[test]
+fn finishing_spewers_wait_for_their_buffers() {
+	let mut app = App::new();
+	app.init_resource::<Time>().add_systems(
+		Update,
+		(ParticleBuffer::tick, despawn_finished_spewers).chain(),
+	);
+	let mut buffer = ParticleBuffer::new(|_: &mut WyRand| BufferParticle::default());
+	buffer.interval = Duration::from_millis(100);
+	buffer.lifetime = Duration::from_millis(500);
+	let world = app.world_mut();
+	let spewer = world
+		.spawn(SpewerBundle {
+			spewer: Spewer {
+				interval: Duration::ZERO,
+				..Spewer::seeded(0)
+			},
+			..default()
+		})
+		.with_children(|spewer| {
+			spewer.spawn((buffer, GlobalTransform::IDENTITY));
+		})
+		.id();
+	let step = |app: &mut App| {
+		app.world_mut()
+			.resource_mut::<Time>()
+			.advance_by(Duration::from_millis(100));
+		app.update();
+	};
+	for _ in 0..3 {
+		step(&mut app);
+	}
+	app.world_mut().commands().add(move |world: &mut World| {
+		stop_emitting_and_finish(spewer, world);
+	});
+	app.world_mut().flush();
+	step(&mut app);
+	assert!(
+		app.world().get_entity(spewer).is_some(),
+		"despawned with live buffer particles"
+	);
+	for _ in 0..5 {
+		step(&mut app);
+	}
+	assert!(app.world().get_entity(spewer).is_none());
+}
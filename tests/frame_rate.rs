@@ -0,0 +1,47 @@
+//! Behaviors integrating over time end up in the same state after the same time, whatever
+//! the frame rate.
+
+use bevy::{prelude::*, utils::Duration};
+use sond_bevy_particles::{
+	update::{AngularVelocity, MulScale},
+	TimeCreated,
+};
+
+/// Steps a particle with `behavior` at `hz` for two seconds, returning its final transform.
+fn simulate<M>(hz: u32, behavior: impl Bundle, tick: impl IntoSystemConfigs<M>) -> Transform {
+	let mut app = App::new();
+	app.init_resource::<Time>().add_systems(Update, tick);
+	let id = app
+		.world_mut()
+		.spawn((Transform::default(), TimeCreated(Duration::ZERO), behavior))
+		.id();
+	for _ in 0..hz * 2 {
+		app.world_mut()
+			.resource_mut::<Time>()
+			.advance_by(Duration::from_secs(1) / hz);
+		app.update();
+	}
+	*app.world().get::<Transform>(id).unwrap()
+}
+
+#[test]
+fn angular_velocity_is_frame_rate_independent() {
+	let spin = AngularVelocity(Vec3::new(0.3, 1.0, -0.5) * 2.0);
+	let slow = simulate(30, spin, AngularVelocity::tick::<()>).rotation;
+	let fast = simulate(240, spin, AngularVelocity::tick::<()>).rotation;
+	assert!(slow.angle_between(fast) < 1e-3, "{slow} != {fast}");
+	let expected = Quat::from_scaled_axis(spin.0 * 2.0);
+	assert!(fast.angle_between(expected) < 1e-3, "{fast} != {expected}");
+}
+
+#[test]
+fn mul_scale_is_frame_rate_independent() {
+	let grow = MulScale {
+		scale: Vec3::new(2.0, 0.5, 1.0),
+	};
+	let slow = simulate(30, grow, MulScale::tick::<()>).scale;
+	let fast = simulate(240, grow, MulScale::tick::<()>).scale;
+	assert!(slow.abs_diff_eq(fast, 1e-3), "{slow} != {fast}");
+	let expected = Vec3::new(4.0, 0.25, 1.0);
+	assert!(fast.abs_diff_eq(expected, 1e-3), "{fast} != {expected}");
+}
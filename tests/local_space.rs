@@ -0,0 +1,94 @@
+//! Particles of spewers without `use_global_coords` are spawned as children of the spewer
+//! where the spewer emitted them, and follow it afterwards.
+#![cfg(feature = "render")]
+
+use bevy::{prelude::*, utils::Duration};
+use sond_bevy_particles::{
+	spawn_particles, template::ParticleTemplate, Lifetime, Spewer, SpewerBundle, TimeCreated,
+};
+
+const INTERVAL: Duration = Duration::from_millis(100);
+
+fn app(spewer: Transform, offset: Vec3) -> (App, Entity) {
+	let mut app = App::new();
+	app.add_plugins((TransformPlugin, HierarchyPlugin))
+		.init_resource::<Time>()
+		.add_systems(Update, spawn_particles::<()>);
+	let template = ParticleTemplate::<StandardMaterial>::new(
+		default(),
+		default(),
+		Lifetime(Duration::from_secs(10)),
+	);
+	let mut factory = template.factory();
+	let id = app
+		.world_mut()
+		.spawn(SpewerBundle {
+			spewer: Spewer {
+				interval: INTERVAL,
+				..Spewer::new(move |cmds: &mut Commands, xform: &GlobalTransform, t| {
+					factory(
+						cmds,
+						&xform.mul_transform(Transform::from_translation(offset)),
+						t,
+					)
+				})
+			},
+			transform: TransformBundle::from_transform(spewer),
+			..default()
+		})
+		.id();
+	app.update();
+	(app, id)
+}
+
+fn particles(app: &mut App) -> Vec<(Option<Entity>, GlobalTransform)> {
+	let world = app.world_mut();
+	world
+		.query_filtered::<(Option<&Parent>, &GlobalTransform), With<TimeCreated>>()
+		.iter(world)
+		.map(|(parent, xform)| (parent.map(Parent::get), *xform))
+		.collect()
+}
+
+#[test]
+fn local_particles_spawn_at_the_spewer() {
+	let spewer = Transform::from_xyz(10.0, 0.0, 0.0)
+		.with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2))
+		.with_scale(Vec3::splat(2.0));
+	let (mut app, id) = app(spewer, Vec3::X);
+	app.world_mut().resource_mut::<Time>().advance_by(INTERVAL);
+	app.update();
+
+	let expected = spewer.transform_point(Vec3::X);
+	let spawned = particles(&mut app);
+	assert_eq!(spawned.len(), 1);
+	let (parent, xform) = spawned[0];
+	assert_eq!(parent, Some(id));
+	// Right away, from the spawn commands.
+	assert!(xform.translation().abs_diff_eq(expected, 1e-5), "{xform:?}");
+
+	// And after transform propagation.
+	app.update();
+	let (_, xform) = particles(&mut app)[0];
+	assert!(xform.translation().abs_diff_eq(expected, 1e-5), "{xform:?}");
+}
+
+#[test]
+fn local_particles_follow_the_spewer() {
+	let (mut app, id) = app(Transform::from_xyz(10.0, 0.0, 0.0), Vec3::ZERO);
+	app.world_mut().resource_mut::<Time>().advance_by(INTERVAL);
+	app.update();
+	app.world_mut()
+		.get_mut::<Transform>(id)
+		.unwrap()
+		.translation
+		.y = 5.0;
+	app.update();
+	let (_, xform) = particles(&mut app)[0];
+	assert!(
+		xform
+			.translation()
+			.abs_diff_eq(Vec3::new(10.0, 5.0, 0.0), 1e-5),
+		"{xform:?}"
+	);
+}
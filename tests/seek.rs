@@ -0,0 +1,69 @@
+//! Seeking works with either of the plugins that simulate particles, and is a no-op
+//! without them.
+
+use bevy::{prelude::*, utils::Duration};
+use sond_bevy_particles::{
+	lifecycle::ParticleOf,
+	seek::{SeekEffect, Seekable},
+	Deterministic, DeterministicParticlesPlugin, InitialTransform, Lifetime, Spewer, SpewerBundle,
+};
+
+fn spawn_spewer(app: &mut App) -> Entity {
+	app.world_mut()
+		.spawn((
+			SpewerBundle {
+				spewer: Spewer {
+					interval: Duration::from_millis(100),
+					use_global_coords: true,
+					factory: Box::new(|cmds: &mut Commands, xform: &GlobalTransform, t| {
+						let xform = xform.compute_transform();
+						cmds.spawn((
+							TransformBundle::from_transform(xform),
+							InitialTransform(xform),
+							t,
+							Lifetime(Duration::from_secs(10)),
+						))
+					}),
+					..Spewer::seeded(3)
+				},
+				..default()
+			},
+			Seekable::new(3),
+			Deterministic,
+		))
+		.id()
+}
+
+fn seek(app: &mut App, spewer: Entity, target: Duration) -> usize {
+	app.world_mut()
+		.commands()
+		.entity(spewer)
+		.add(SeekEffect(target));
+	app.world_mut().flush();
+	let world = app.world_mut();
+	world
+		.query::<&ParticleOf>()
+		.iter(world)
+		.filter(|of| of.0 == spewer)
+		.count()
+}
+
+#[test]
+fn seeks_with_only_the_deterministic_plugin() {
+	let mut app = App::new();
+	app.init_resource::<Time>()
+		.add_plugins(DeterministicParticlesPlugin::new(Update));
+	let spewer = spawn_spewer(&mut app);
+	app.update();
+	let count = seek(&mut app, spewer, Duration::from_millis(550));
+	assert!(count >= 5, "{count}");
+}
+
+#[test]
+fn seeking_without_a_plugin_does_nothing() {
+	let mut app = App::new();
+	app.init_resource::<Time>();
+	let spewer = spawn_spewer(&mut app);
+	app.update();
+	assert_eq!(seek(&mut app, spewer, Duration::from_millis(550)), 0);
+}
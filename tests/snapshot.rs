@@ -0,0 +1,188 @@
+//! Restoring a [SpewerSnapshot] brings back the spewer's particles where they were, and
+//! the spewer carries on emitting as it would have.
+
+use bevy::{prelude::*, utils::Duration};
+use sond_bevy_particles::{
+	budget::ParticlePriority, color::SpewerTint, lifecycle::DespawnWithSpewer,
+	lifecycle::ParticleOf, snapshot::SpewerSnapshot, spawn_particles, update::Velocity,
+	Deterministic, EffectName, InitialGlobalTransform, InitialTransform, Lifetime, ParticleLayers,
+	ParticleUserData, Spewer, SpewerBundle, TimeCreated,
+};
+
+const INTERVAL: Duration = Duration::from_millis(100);
+
+fn new_app() -> (App, Entity) {
+	let mut app = App::new();
+	app.init_resource::<Time>()
+		.add_systems(Update, spawn_particles::<()>);
+	let spewer = app
+		.world_mut()
+		.spawn(SpewerBundle {
+			spewer: Spewer {
+				interval: INTERVAL,
+				jitter: INTERVAL / 2,
+				use_global_coords: true,
+				factory: Box::new(|cmds: &mut Commands, xform: &GlobalTransform, t| {
+					let xform = xform.compute_transform();
+					cmds.spawn((
+						TransformBundle::from_transform(xform),
+						InitialTransform(xform),
+						t,
+						Lifetime(Duration::from_secs(10)),
+						Velocity(Vec3::Y),
+					))
+				}),
+				..Spewer::seeded(7)
+			},
+			transform: TransformBundle::from_transform(Transform::from_xyz(3.0, 0.0, 0.0)),
+			..default()
+		})
+		.id();
+	app.update();
+	(app, spewer)
+}
+
+fn step(app: &mut App, dt: Duration) {
+	app.world_mut().resource_mut::<Time>().advance_by(dt);
+	app.update();
+}
+
+/// Transforms and creation times of the spewer's particles, sorted by creation time.
+fn particles(app: &mut App, spewer: Entity) -> Vec<(Duration, Transform, Option<Entity>)> {
+	let world = app.world_mut();
+	let mut particles = world
+		.query::<(&ParticleOf, &TimeCreated, &Transform, Option<&Parent>)>()
+		.iter(world)
+		.filter(|(of, ..)| of.0 == spewer)
+		.map(|(_, created, xform, parent)| (created.0, *xform, parent.map(Parent::get)))
+		.collect::<Vec<_>>();
+	particles.sort_by_key(|&(created, ..)| created);
+	particles
+}
+
+#[test]
+fn world_space_particles_are_restored() {
+	let (mut app, spewer) = new_app();
+	for _ in 0..10 {
+		step(&mut app, Duration::from_millis(33));
+	}
+	// Moves them apart, as if they had been simulated.
+	let world = app.world_mut();
+	for (mut xform, created) in world
+		.query::<(&mut Transform, &TimeCreated)>()
+		.iter_mut(world)
+	{
+		xform.translation.y = created.0.as_secs_f32();
+	}
+	let before = particles(&mut app, spewer);
+	assert!(before.len() >= 2, "{before:?}");
+	assert!(before.iter().all(|&(.., parent)| parent.is_none()));
+
+	let snapshot = SpewerSnapshot::capture(app.world_mut(), spewer).unwrap();
+	assert_eq!(snapshot.particles.len(), before.len());
+	let world = app.world_mut();
+	snapshot.restore(world, spewer);
+	let after = particles(&mut app, spewer);
+	assert_eq!(after, before);
+}
+
+#[test]
+fn restored_spewer_resumes_where_it_left_off() {
+	let (mut app, spewer) = new_app();
+	for _ in 0..5 {
+		step(&mut app, Duration::from_millis(33));
+	}
+	let snapshot = SpewerSnapshot::capture(app.world_mut(), spewer).unwrap();
+	let frames = [45, 10, 120, 33, 70].map(Duration::from_millis);
+	for dt in frames {
+		step(&mut app, dt);
+	}
+	let live = particles(&mut app, spewer);
+
+	let (mut restored, restored_spewer) = new_app();
+	for _ in 0..5 {
+		step(&mut restored, Duration::from_millis(33));
+	}
+	// Messes up the state the snapshot has to replace.
+	{
+		let world = restored.world_mut();
+		let mut state = world.get_mut::<Spewer>(restored_spewer).unwrap();
+		state.next_interval = Some(Duration::from_secs(5));
+		state.pending_burst = 3;
+	}
+	snapshot.restore(restored.world_mut(), restored_spewer);
+	for dt in frames {
+		step(&mut restored, dt);
+	}
+	let resumed = particles(&mut restored, restored_spewer);
+	let times = |particles: &[(Duration, Transform, Option<Entity>)]| {
+		particles.iter().map(|p| p.0).collect::<Vec<_>>()
+	};
+	assert_eq!(times(&resumed), times(&live));
+}
+
+#[test]
+fn restored_particles_get_what_the_spewer_passes_on() {
+	let (mut app, spewer) = new_app();
+	let world = app.world_mut();
+	world.entity_mut(spewer).insert((
+		Deterministic,
+		ParticleLayers(0b10),
+		DespawnWithSpewer,
+		ParticleUserData(Vec4::ONE),
+		SpewerTint(Color::WHITE),
+		ParticlePriority(3),
+		EffectName("sparks".into()),
+	));
+	{
+		let mut state = world.get_mut::<Spewer>(spewer).unwrap();
+		state.use_global_coords = false;
+		state.factory = Box::new(|cmds: &mut Commands, xform: &GlobalTransform, t| {
+			let xform = xform.compute_transform();
+			cmds.spawn((
+				TransformBundle::from_transform(xform),
+				InitialTransform(xform),
+				InitialGlobalTransform::default(),
+				t,
+				Lifetime(Duration::from_secs(10)),
+			))
+		});
+	}
+	for _ in 0..5 {
+		step(&mut app, Duration::from_millis(33));
+	}
+	let components = |app: &App, id: Entity| {
+		let mut names = app
+			.world()
+			.inspect_entity(id)
+			.into_iter()
+			.map(|info| info.name().to_owned())
+			.collect::<Vec<_>>();
+		names.sort();
+		names
+	};
+	let spawned = app
+		.world_mut()
+		.query::<(Entity, &ParticleOf)>()
+		.iter(app.world())
+		.map(|(id, _)| id)
+		.next()
+		.unwrap();
+	let fresh = components(&app, spawned);
+
+	let snapshot = SpewerSnapshot::capture(app.world_mut(), spewer).unwrap();
+	snapshot.restore(app.world_mut(), spewer);
+	let world = app.world_mut();
+	let restored = world
+		.query::<(Entity, &ParticleOf, &InitialGlobalTransform)>()
+		.iter(world)
+		.map(|(id, _, initial)| (id, *initial))
+		.collect::<Vec<_>>();
+	assert_eq!(restored.len(), snapshot.particles.len());
+	let spewer_xform = *world.get::<GlobalTransform>(spewer).unwrap();
+	for (id, initial) in restored {
+		assert_eq!(components(&app, id), fresh);
+		let xform = app.world().get::<InitialTransform>(id).unwrap().0;
+		assert_eq!(initial.0, spewer_xform.mul_transform(xform));
+	}
+}
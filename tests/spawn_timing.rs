@@ -0,0 +1,368 @@
+//! Properties of interval spawn timing, each checked over many random sequences of frame
+//! lengths, including zero-length frames and long hitches.
+
+use bevy::{prelude::*, utils::Duration};
+use nanorand::{Rng, WyRand};
+use sond_bevy_particles::{
+	spawn_particles, CatchUp, CatchUpOverflow, EmissionGate, SpawnTiming, Spewer, SpewerBundle,
+	TimeCreated,
+};
+
+/// Random cases each property is checked for.
+const CASES: u64 = 32;
+
+const INTERVAL: Duration = Duration::from_micros(16_667);
+
+/// One spewer in a world whose `Time` only advances by the frame lengths it is given.
+struct Harness {
+	app: App,
+}
+
+impl Harness {
+	/// Adds `spewer` at time zero, with a factory spawning only a [TimeCreated].
+	fn new(spewer: Spewer) -> Self {
+		let mut app = App::new();
+		app.init_resource::<Time>()
+			.add_systems(Update, spawn_particles::<()>);
+		app.world_mut().spawn(SpewerBundle {
+			spewer: Spewer {
+				factory: Box::new(|cmds: &mut Commands, _: &GlobalTransform, t| cmds.spawn(t)),
+				use_global_coords: true,
+				..spewer
+			},
+			..default()
+		});
+		app.update();
+		Self { app }
+	}
+
+	fn now(&self) -> Duration {
+		self.app.world().resource::<Time>().elapsed()
+	}
+
+	/// Runs a frame of length `dt`, returning the creation times of the particles it
+	/// spawned, which are despawned again.
+	fn step(&mut self, dt: Duration) -> Vec<Duration> {
+		self.app.world_mut().resource_mut::<Time>().advance_by(dt);
+		self.app.update();
+		let world = self.app.world_mut();
+		let spawned = world
+			.query::<(Entity, &TimeCreated)>()
+			.iter(world)
+			.map(|(id, created)| (id, created.0))
+			.collect::<Vec<_>>();
+		for &(id, _) in &spawned {
+			world.despawn(id);
+		}
+		let mut times = spawned.into_iter().map(|(_, t)| t).collect::<Vec<_>>();
+		times.sort();
+		times
+	}
+
+	/// Runs every frame in `frames`, returning the creation times of all particles.
+	fn run(&mut self, frames: &[Duration]) -> Vec<Duration> {
+		frames.iter().flat_map(|&dt| self.step(dt)).collect()
+	}
+}
+
+/// Random frame lengths adding up to exactly `total`: mostly steady frames, with some
+/// zero-length frames and hitches of up to half a second.
+fn frames(rng: &mut WyRand, total: Duration) -> Vec<Duration> {
+	let mut frames = Vec::new();
+	let mut elapsed = Duration::ZERO;
+	while elapsed < total {
+		let dt = match rng.generate_range(0..20u32) {
+			0 => Duration::ZERO,
+			1 => Duration::from_micros(rng.generate_range(100_000..500_000)),
+			_ => Duration::from_micros(rng.generate_range(1..40_000)),
+		};
+		let dt = dt.min(total - elapsed);
+		elapsed += dt;
+		frames.push(dt);
+	}
+	frames
+}
+
+fn spewer(seed: u64, timing: SpawnTiming, jitter: Duration) -> Spewer {
+	Spewer {
+		interval: INTERVAL,
+		jitter,
+		timing,
+		..Spewer::seeded(seed)
+	}
+}
+
+#[test]
+fn unjittered_interval_spawns_never_drift() {
+	let total = Duration::from_secs(20);
+	for seed in 0..CASES {
+		let mut rng = WyRand::new_seed(seed);
+		let mut harness = Harness::new(spewer(seed, SpawnTiming::Interval, Duration::ZERO));
+		let times = harness.run(&frames(&mut rng, total));
+		let expected = (1..=(total.as_nanos() / INTERVAL.as_nanos()) as u32)
+			.map(|i| INTERVAL * i)
+			.collect::<Vec<_>>();
+		assert_eq!(times, expected, "seed {seed}");
+	}
+}
+
+#[test]
+fn spawn_times_do_not_depend_on_frame_lengths() {
+	let total = Duration::from_secs(10);
+	for timing in [SpawnTiming::Interval, SpawnTiming::Poisson] {
+		for seed in 0..CASES {
+			let mut rng = WyRand::new_seed(seed);
+			let jitter = INTERVAL / 2;
+			let a = Harness::new(spewer(seed, timing, jitter)).run(&frames(&mut rng, total));
+			let b = Harness::new(spewer(seed, timing, jitter)).run(&frames(&mut rng, total));
+			assert_eq!(a, b, "{timing:?}, seed {seed}");
+		}
+	}
+}
+
+#[test]
+fn spawns_are_never_in_the_future() {
+	let total = Duration::from_secs(5);
+	for seed in 0..CASES {
+		let mut rng = WyRand::new_seed(seed);
+		let mut harness = Harness::new(spewer(seed, SpawnTiming::Poisson, Duration::ZERO));
+		for dt in frames(&mut rng, total) {
+			let start = harness.now();
+			for t in harness.step(dt) {
+				assert!(
+					t <= harness.now(),
+					"seed {seed}: spawned at {t:?} by {start:?} + {dt:?}"
+				);
+			}
+		}
+	}
+}
+
+#[test]
+fn jittered_gaps_stay_within_jitter() {
+	let total = Duration::from_secs(10);
+	// Jitter larger than the interval is clamped to it.
+	for jitter in [INTERVAL / 4, INTERVAL, INTERVAL * 3] {
+		for seed in 0..CASES {
+			let mut rng = WyRand::new_seed(seed);
+			let mut harness = Harness::new(spewer(seed, SpawnTiming::Interval, jitter));
+			let times = harness.run(&frames(&mut rng, total));
+			let jitter = jitter.min(INTERVAL);
+			let mut last = Duration::ZERO;
+			for t in times {
+				let gap = t - last;
+				assert!(
+					gap >= INTERVAL - jitter && gap <= INTERVAL + jitter,
+					"jitter {jitter:?}, seed {seed}: gap of {gap:?}",
+				);
+				last = t;
+			}
+		}
+	}
+}
+
+#[test]
+fn spawn_counts_match_the_rate() {
+	let total = Duration::from_secs(10);
+	let expected = total.as_secs_f64() / INTERVAL.as_secs_f64();
+	for (timing, jitter) in [
+		(SpawnTiming::Interval, INTERVAL / 2),
+		(SpawnTiming::Interval, INTERVAL),
+		(SpawnTiming::Poisson, Duration::ZERO),
+	] {
+		let mut counts = 0;
+		for seed in 0..CASES {
+			let mut rng = WyRand::new_seed(seed);
+			let mut harness = Harness::new(spewer(seed, timing, jitter));
+			counts += harness.run(&frames(&mut rng, total)).len();
+		}
+		let mean = counts as f64 / CASES as f64;
+		// Poisson counts have a variance of `expected`, jittered intervals less, so five
+		// standard errors of the mean leave no room for flakes or drift alike.
+		let tolerance = 5.0 * (expected / CASES as f64).sqrt();
+		assert!(
+			(mean - expected).abs() < tolerance,
+			"{timing:?} with jitter {jitter:?}: {mean} spawns on average, expected {expected}",
+		);
+	}
+}
+
+#[test]
+fn every_spawn_emits_particles_per_spawn() {
+	let total = Duration::from_secs(5);
+	for seed in 0..CASES {
+		let mut rng = WyRand::new_seed(seed);
+		let mut harness = Harness::new(Spewer {
+			particles_per_spawn: 2..=5,
+			..spewer(seed, SpawnTiming::Interval, Duration::ZERO)
+		});
+		let times = harness.run(&frames(&mut rng, total));
+		for window in times.chunk_by(|a, b| a == b) {
+			assert!((2..=5).contains(&window.len()), "seed {seed}: {window:?}");
+		}
+		let spawns = times.chunk_by(|a, b| a == b).count() as u128;
+		assert_eq!(
+			spawns,
+			total.as_nanos() / INTERVAL.as_nanos(),
+			"seed {seed}"
+		);
+	}
+}
+
+#[test]
+fn reaching_the_spawn_limit_exactly_changes_nothing() {
+	let total = Duration::from_secs(10);
+	for overflow in [CatchUpOverflow::Drop, CatchUpOverflow::Spread] {
+		for seed in 0..CASES {
+			let mut rng = WyRand::new_seed(seed);
+			// Frames shorter than the interval, so each is due at most one spawn.
+			let frames = (0..)
+				.map(|_| Duration::from_micros(rng.generate_range(1..16_000)))
+				.scan(Duration::ZERO, |elapsed, dt| {
+					*elapsed += dt;
+					(*elapsed <= total).then_some(dt)
+				})
+				.collect::<Vec<_>>();
+			let unlimited =
+				Harness::new(spewer(seed, SpawnTiming::Interval, Duration::ZERO)).run(&frames);
+			let limited = Harness::new(Spewer {
+				catch_up: CatchUp {
+					max_spawns_per_frame: Some(1),
+					overflow,
+					..default()
+				},
+				..spewer(seed, SpawnTiming::Interval, Duration::ZERO)
+			})
+			.run(&frames);
+			assert_eq!(limited, unlimited, "{overflow:?}, seed {seed}");
+		}
+	}
+}
+
+#[test]
+fn dropped_spawns_stay_within_the_limit() {
+	let total = Duration::from_secs(10);
+	for seed in 0..CASES {
+		let mut rng = WyRand::new_seed(seed);
+		let mut harness = Harness::new(Spewer {
+			catch_up: CatchUp {
+				max_spawns_per_frame: Some(3),
+				overflow: CatchUpOverflow::Drop,
+				..default()
+			},
+			..spewer(seed, SpawnTiming::Poisson, Duration::ZERO)
+		});
+		for dt in frames(&mut rng, total) {
+			let spawned = harness.step(dt).len();
+			assert!(spawned <= 3, "seed {seed}: {spawned} spawns in one frame");
+		}
+	}
+}
+
+#[test]
+fn spread_spawns_catch_up_with_their_original_times() {
+	let total = Duration::from_secs(10);
+	for seed in 0..CASES {
+		let mut rng = WyRand::new_seed(seed);
+		let frames = frames(&mut rng, total);
+		let unlimited =
+			Harness::new(spewer(seed, SpawnTiming::Poisson, Duration::ZERO)).run(&frames);
+
+		let mut harness = Harness::new(Spewer {
+			catch_up: CatchUp {
+				max_spawns_per_frame: Some(3),
+				overflow: CatchUpOverflow::Spread,
+				..default()
+			},
+			..spewer(seed, SpawnTiming::Poisson, Duration::ZERO)
+		});
+		let mut spread = Vec::new();
+		for &dt in &frames {
+			let spawned = harness.step(dt);
+			assert!(
+				spawned.len() <= 3,
+				"seed {seed}: {} spawns in one frame",
+				spawned.len()
+			);
+			spread.extend(spawned);
+		}
+		// Zero-length frames work off the rest of the backlog without any more becoming due.
+		loop {
+			let spawned = harness.step(Duration::ZERO);
+			if spawned.is_empty() {
+				break;
+			}
+			spread.extend(spawned);
+		}
+		assert_eq!(spread, unlimited, "seed {seed}");
+	}
+}
+
+#[test]
+fn backlogs_are_capped() {
+	let total = Duration::from_secs(10);
+	let max_backlog = Duration::from_millis(100);
+	for overflow in [CatchUpOverflow::Drop, CatchUpOverflow::Spread] {
+		for seed in 0..CASES {
+			let mut rng = WyRand::new_seed(seed);
+			let mut harness = Harness::new(Spewer {
+				catch_up: CatchUp {
+					max_backlog: Some(max_backlog),
+					max_spawns_per_frame: Some(3),
+					overflow,
+				},
+				..spewer(seed, SpawnTiming::Interval, INTERVAL / 2)
+			});
+			for dt in frames(&mut rng, total) {
+				for t in harness.step(dt) {
+					let age = harness.now() - t;
+					assert!(
+						age <= max_backlog,
+						"{overflow:?}, seed {seed}: {age:?} late"
+					);
+				}
+			}
+		}
+	}
+}
+
+#[test]
+fn tiny_and_non_finite_gated_rates_emit_nothing() {
+	for rate in [
+		1e-30,
+		f32::MIN_POSITIVE,
+		f32::NAN,
+		f32::INFINITY,
+		f32::NEG_INFINITY,
+	] {
+		let mut harness = Harness::new(spewer(0, SpawnTiming::Interval, Duration::ZERO));
+		let world = harness.app.world_mut();
+		let id = world.query_filtered::<Entity, With<Spewer>>().single(world);
+		world
+			.entity_mut(id)
+			.insert(EmissionGate::new(move |_| rate));
+		let spawned = harness.run(&frames(&mut WyRand::new_seed(0), Duration::from_secs(1)));
+		assert!(spawned.is_empty(), "rate {rate}: {spawned:?}");
+	}
+}
+
+#[test]
+fn reinserted_spewers_keep_their_timing() {
+	let total = Duration::from_secs(2);
+	for seed in 0..CASES {
+		let mut rng = WyRand::new_seed(seed);
+		let frames = frames(&mut rng, total);
+		let (before, after) = frames.split_at(frames.len() / 2);
+		let live = Harness::new(spewer(seed, SpawnTiming::Interval, INTERVAL / 2)).run(&frames);
+
+		let mut harness = Harness::new(spewer(seed, SpawnTiming::Interval, INTERVAL / 2));
+		let mut times = harness.run(before);
+		// Like a rollback loading the spewer.
+		let world = harness.app.world_mut();
+		let id = world.query_filtered::<Entity, With<Spewer>>().single(world);
+		let spewer = world.entity_mut(id).take::<Spewer>().unwrap();
+		world.entity_mut(id).insert(spewer);
+		times.extend(harness.run(after));
+		assert_eq!(times, live, "seed {seed}");
+	}
+}
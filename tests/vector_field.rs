@@ -0,0 +1,41 @@
+//! Vector fields read from flowmap textures.
+
+use bevy::{
+	prelude::*,
+	render::{
+		render_asset::RenderAssetUsages,
+		render_resource::{Extent3d, TextureDimension, TextureFormat},
+	},
+};
+use sond_bevy_particles::vector_field::VectorField;
+
+#[test]
+fn flowmap_textures_map_to_signed_velocities() {
+	let image = Image::new(
+		Extent3d {
+			width: 2,
+			height: 1,
+			depth_or_array_layers: 2,
+		},
+		TextureDimension::D3,
+		vec![
+			255, 0, 128, 255, //
+			0, 255, 128, 255, //
+			128, 128, 255, 255, //
+			128, 128, 0, 255,
+		],
+		TextureFormat::Rgba8Unorm,
+		RenderAssetUsages::default(),
+	);
+	let field = VectorField::from_image(&image).unwrap();
+	assert_eq!(field.dimensions, UVec3::new(2, 1, 2));
+	let sample = |uvw| field.sample(uvw);
+	assert!(sample(Vec3::ZERO).abs_diff_eq(Vec3::new(1.0, -1.0, 0.0), 0.01));
+	assert!(sample(Vec3::X).abs_diff_eq(Vec3::new(-1.0, 1.0, 0.0), 0.01));
+	assert!(sample(Vec3::Z).abs_diff_eq(Vec3::Z, 0.01));
+	assert!(sample(Vec3::new(1.0, 0.0, 1.0)).abs_diff_eq(Vec3::NEG_Z, 0.01));
+
+	let mut image = image;
+	image.texture_descriptor.format = TextureFormat::R8Unorm;
+	assert!(VectorField::from_image(&image).is_none());
+}
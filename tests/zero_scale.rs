@@ -0,0 +1,77 @@
+//! Particles scaled to zero, e.g. while growing in with `AddScale` or shrinking out with
+//! `TargetScale`, still move in the space of their parent.
+
+use bevy::{prelude::*, utils::Duration};
+use sond_bevy_particles::{
+	update::{Drag, GravityScale, ParticleGravity, Velocity},
+	TimeCreated,
+};
+
+/// Steps a zero-scale particle with `behavior` for a second, returning its final velocity
+/// and transform. With `parent_scale`, the particle is the child of an entity scaled by it.
+fn simulate<M>(
+	parent_scale: Option<f32>,
+	behavior: impl Bundle,
+	tick: impl IntoSystemConfigs<M>,
+) -> (Velocity, Transform) {
+	let mut app = App::new();
+	app.init_resource::<Time>()
+		.init_resource::<ParticleGravity>()
+		.add_systems(Update, (tick, Velocity::tick::<()>).chain());
+	let world = app.world_mut();
+	let id = world
+		.spawn((
+			Transform::from_scale(Vec3::ZERO),
+			GlobalTransform::from_scale(Vec3::ZERO),
+			TimeCreated(Duration::ZERO),
+			behavior,
+		))
+		.id();
+	if let Some(scale) = parent_scale {
+		let parent = world
+			.spawn(GlobalTransform::from_scale(Vec3::splat(scale)))
+			.id();
+		world.entity_mut(id).set_parent(parent);
+	}
+	for _ in 0..10 {
+		app.world_mut()
+			.resource_mut::<Time>()
+			.advance_by(Duration::from_millis(100));
+		app.update();
+	}
+	let world = app.world();
+	(
+		*world.get::<Velocity>(id).unwrap(),
+		*world.get::<Transform>(id).unwrap(),
+	)
+}
+
+#[test]
+fn zero_scale_particles_fall() {
+	let gravity = ParticleGravity::default().0;
+	let behavior = (GravityScale(1.0), Velocity(Vec3::ZERO));
+
+	let (vel, xform) = simulate(None, behavior, GravityScale::tick::<()>);
+	assert!(vel.0.abs_diff_eq(gravity, 1e-3), "{} != {gravity}", vel.0);
+	assert!(xform.translation.is_finite(), "{}", xform.translation);
+
+	let (vel, xform) = simulate(Some(2.0), behavior, GravityScale::tick::<()>);
+	assert!(
+		vel.0.abs_diff_eq(gravity / 2.0, 1e-3),
+		"{} != {}",
+		vel.0,
+		gravity / 2.0
+	);
+	assert!(xform.translation.is_finite(), "{}", xform.translation);
+}
+
+#[test]
+fn zero_scale_particles_slow_down() {
+	let behavior = (Drag::linear(1.0), Velocity(Vec3::X * 10.0));
+	let expected = Vec3::X * 10.0 * (-1.0f32).exp();
+	for parent_scale in [None, Some(2.0)] {
+		let (vel, xform) = simulate(parent_scale, behavior, Drag::tick::<()>);
+		assert!(vel.0.abs_diff_eq(expected, 1e-3), "{} != {expected}", vel.0);
+		assert!(xform.translation.is_finite(), "{}", xform.translation);
+	}
+}